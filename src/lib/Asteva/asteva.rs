@@ -1,28 +1,38 @@
-use crate::lib::Enums::astnode::ASTNode;
+use crate::lib::Enums::astnode::{ASTNode, MatchArm, Pattern};
+use crate::lib::Asteva::budget::BoundedString;
 use crate::lib::Asteva::context::Context;
-use crate::lib::Enums::value::Value;
-use crate::lib::Asteva::context::FunctionDefinition;
+use crate::lib::Enums::value::{Binding, Closure, EnumValue, FunctionValue, MapKey, OrderedMap, Value};
+use crate::lib::Asteva::context::{check_stack, ControlFlow, EnumDefinition, FunctionDefinition, StructDefinition};
+use crate::lib::Asteva::error::{ErrorKind, RuntimeError};
+use crate::lib::Enums::span::Span;
+use std::rc::Rc;
 
 /// Evaluates a block of statements in the provided context.
 ///
 /// # Arguments
 ///
-/// * `block` - A reference to a vector of ASTNode representing the block of statements to evaluate.
+/// * `block` - A slice of ASTNode representing the block of statements to evaluate.
 /// * `context` - A mutable reference to the Context struct representing the evaluation context.
 ///
 /// # Returns
 ///
-/// A Value representing the result of the last evaluated statement in the block.
-pub fn evaluate_block(block: &Vec<ASTNode>, context: &mut Context) -> Value {
+/// A Value representing the result of the last evaluated statement in the block,
+/// or an error message if evaluation fails. Evaluation stops early when a
+/// statement raises a `break`, `continue` or `return`, which is left pending in
+/// the context for the enclosing loop or function call to handle.
+pub fn evaluate_block(block: &[ASTNode], context: &mut Context) -> Result<Value, RuntimeError> {
     // Initialize the result to Void
     let mut result = Value::Void;
     // Iterate over each statement in the block
     for statement in block {
         // Evaluate the statement in the context
-        result = evaluate(statement, context);
+        result = evaluate(statement, context)?;
+        if context.control.is_some() {
+            break;
+        }
     }
     // Return the result of the last evaluated statement
-    result
+    Ok(result)
 }
 
 
@@ -36,47 +46,647 @@ pub fn evaluate_block(block: &Vec<ASTNode>, context: &mut Context) -> Value {
 ///
 /// # Returns
 ///
-/// A Value representing the result of the evaluated expression.
-pub fn evaluate_expression(expr: &ASTNode, context: &mut Context) -> Value {
+/// A Value representing the result of the evaluated expression, or an error message.
+pub fn evaluate_expression(expr: &ASTNode, context: &mut Context) -> Result<Value, RuntimeError> {
     match expr {
         // Evaluate an integer literal to an Integer value
-        ASTNode::IntegerLiteral(value) => Value::Integer(*value),
+        ASTNode::IntegerLiteral(value) => Ok(Value::Integer(*value)),
         // Evaluate a boolean literal to a Boolean value
-        ASTNode::BooleanLiteral(value) => Value::Boolean(*value),
+        ASTNode::BooleanLiteral(value) => Ok(Value::Boolean(*value)),
         // Evaluate an identifier to the corresponding value in the context
-        ASTNode::Identifier(name) => match context.variables.get(name) {
-            Some(value) => Value::Integer(value.clone()), // Clone the value to avoid ownership issues
-            None => panic!("Variable '{}' not found", name), // Panic if the variable is not found
+        ASTNode::Identifier(name) => match context.get_variable(name) {
+            Some(value) => Ok(value),
+            None => evaluate(expr, context), // Fall back to the general evaluator, which also resolves functions
         },
-        // Evaluate a function call
-        ASTNode::FunctionCall { name, arguments } => {
-            // Retrieve the function definition from the context
-            let function_def = match context.functions.get(name) {
-                Some(def) => def,
-                None => panic!("Function '{}' not found", name), // Panic if the function is not found
+        // Every other expression is handled by the general evaluator
+        _ => evaluate(expr, context),
+    }
+}
+
+
+/// Applies a binary operator to two already evaluated operands.
+///
+/// Arithmetic and comparisons work on two integers, or on floats. When an
+/// integer meets a float it is converted to a float first.
+///
+/// # Arguments
+///
+/// * `op` - The name of the operator, as produced by the parser (e.g. "Plus").
+/// * `left_value` - The value of the left operand.
+/// * `right_value` - The value of the right operand.
+///
+/// # Returns
+///
+/// The resulting Value, or an error message if the operands are invalid.
+pub fn apply_binary_op(op: &str, left_value: Value, right_value: Value) -> Result<Value, RuntimeError> {
+    match (op, numeric_pair(&left_value, &right_value)) {
+        ("Equals", Some((a, b))) => return Ok(Value::Boolean(a == b)),
+        ("NotEquals", Some((a, b))) => return Ok(Value::Boolean(a != b)),
+        ("Equals", None) => return Ok(Value::Boolean(left_value == right_value)),
+        ("NotEquals", None) => return Ok(Value::Boolean(left_value != right_value)),
+        _ => {}
+    }
+    let symbol = operator_symbol(op);
+    if let (Value::Integer(a), Value::Integer(b)) = (&left_value, &right_value) {
+        let (a, b) = (*a, *b);
+        let overflow = || RuntimeError::new(ErrorKind::Overflow, format!("Integer overflow in {}", symbol));
+        return match op {
+            "Plus" => a.checked_add(b).map(Value::Integer).ok_or_else(overflow),
+            "Minus" => a.checked_sub(b).map(Value::Integer).ok_or_else(overflow),
+            "Multiply" => a.checked_mul(b).map(Value::Integer).ok_or_else(overflow),
+            "Divide" | "Modulo" if b == 0 => Err(RuntimeError::new(ErrorKind::DivisionByZero, "Division by zero")),
+            "Divide" => a.checked_div(b).map(Value::Integer).ok_or_else(overflow),
+            "Modulo" => a.checked_rem(b).map(Value::Integer).ok_or_else(overflow),
+            "LessThan" => Ok(Value::Boolean(a < b)),
+            "LessThanOrEqual" => Ok(Value::Boolean(a <= b)),
+            "GreaterThan" => Ok(Value::Boolean(a > b)),
+            "GreaterThanOrEqual" => Ok(Value::Boolean(a >= b)),
+            _ => Err(format!("Operator '{}' not implemented", op).into()),
+        };
+    }
+    let (a, b) = match numeric_pair(&left_value, &right_value) {
+        Some(pair) => pair,
+        None => return Err(format!("Invalid operands for {}", symbol).into()),
+    };
+    match op {
+        "Plus" => Ok(Value::Float(a + b)),
+        "Minus" => Ok(Value::Float(a - b)),
+        "Multiply" => Ok(Value::Float(a * b)),
+        "Divide" | "Modulo" if b == 0.0 => Err(RuntimeError::new(ErrorKind::DivisionByZero, "Division by zero")),
+        "Divide" => Ok(Value::Float(a / b)),
+        "Modulo" => Ok(Value::Float(a % b)),
+        "LessThan" => Ok(Value::Boolean(a < b)),
+        "LessThanOrEqual" => Ok(Value::Boolean(a <= b)),
+        "GreaterThan" => Ok(Value::Boolean(a > b)),
+        "GreaterThanOrEqual" => Ok(Value::Boolean(a >= b)),
+        _ => Err(format!("Operator '{}' not implemented", op).into()),
+    }
+}
+
+
+/// Returns both operands as floats if they are numbers and at least one of
+/// them is a float.
+fn numeric_pair(left: &Value, right: &Value) -> Option<(f64, f64)> {
+    match (left, right) {
+        (Value::Float(a), Value::Float(b)) => Some((*a, *b)),
+        (Value::Float(a), Value::Integer(b)) => Some((*a, *b as f64)),
+        (Value::Integer(a), Value::Float(b)) => Some((*a as f64, *b)),
+        _ => None,
+    }
+}
+
+
+/// Returns the source symbol of a binary operator, for error messages.
+fn operator_symbol(op: &str) -> &str {
+    match op {
+        "Plus" => "+",
+        "Minus" => "-",
+        "Multiply" => "*",
+        "Divide" => "/",
+        "Modulo" => "%",
+        "LessThan" => "<",
+        "LessThanOrEqual" => "<=",
+        "GreaterThan" => ">",
+        "GreaterThanOrEqual" => ">=",
+        other => other,
+    }
+}
+
+
+/// Applies a unary operator to an already evaluated operand.
+///
+/// # Arguments
+///
+/// * `op` - The name of the operator, as produced by the parser ("Minus" or "Not").
+/// * `value` - The value of the operand.
+///
+/// # Returns
+///
+/// The resulting Value, or an error message if the operand is invalid.
+pub fn apply_unary_op(op: &str, value: Value) -> Result<Value, RuntimeError> {
+    match (op, value) {
+        ("Minus", Value::Integer(a)) => a.checked_neg().map(Value::Integer).ok_or_else(|| RuntimeError::new(ErrorKind::Overflow, "Integer overflow in unary -")),
+        ("Minus", Value::Float(a)) => Ok(Value::Float(-a)),
+        ("Minus", _) => Err("Invalid operand for unary -".to_string().into()),
+        ("Not", Value::Boolean(a)) => Ok(Value::Boolean(!a)),
+        ("Not", _) => Err("Invalid operand for !".to_string().into()),
+        (op, _) => Err(format!("Operator '{}' not implemented", op).into()),
+    }
+}
+
+
+/// Evaluates a logical operand, which must be a boolean.
+fn logical_operand(node: &ASTNode, symbol: &str, context: &mut Context) -> Result<bool, RuntimeError> {
+    match evaluate(node, context)? {
+        Value::Boolean(value) => Ok(value),
+        other => Err(format!("Operands of {} must be booleans, got {}", symbol, other.type_name()).into()),
+    }
+}
+
+
+/// Converts an index value into a position within a list of the given length.
+///
+/// # Returns
+///
+/// The position as a usize, or a runtime error if the index is not an integer
+/// or lies outside the list.
+fn list_position(index: &Value, length: usize) -> Result<usize, RuntimeError> {
+    match index {
+        Value::Integer(i) if *i >= 0 && (*i as usize) < length => Ok(*i as usize),
+        Value::Integer(i) => Err(RuntimeError::new(
+            ErrorKind::IndexOutOfBounds,
+            format!("Index {} out of bounds for list of length {}", i, length),
+        )),
+        other => Err(format!("List index must be an integer, got {}", other.type_name()).into()),
+    }
+}
+
+
+/// Reads the element of a list or the entry of a map at the given index.
+fn read_index(target: &Value, index: &Value) -> Result<Value, RuntimeError> {
+    match target {
+        Value::List(items) => {
+            let items = items.borrow();
+            let position = list_position(index, items.len())?;
+            Ok(items[position].clone())
+        },
+        Value::Map(map) => {
+            let key = MapKey::from_value(index)?;
+            match map.borrow().get(&key) {
+                Some(value) => Ok(value.clone()),
+                None => Err(RuntimeError::new(ErrorKind::KeyNotFound, format!("Key {} not found in map", key))),
+            }
+        },
+        other => Err(format!("Cannot index into a value of type {}", other.type_name()).into()),
+    }
+}
+
+
+/// Stores a value in a list element or map entry. Writing to a missing map key
+/// inserts it; writing past the end of a list is an error.
+fn write_index(target: &Value, index: &Value, value: Value) -> Result<(), RuntimeError> {
+    match target {
+        Value::List(items) => {
+            let mut items = items.borrow_mut();
+            let position = list_position(index, items.len())?;
+            items[position] = value;
+            Ok(())
+        },
+        Value::Map(map) => {
+            let key = MapKey::from_value(index)?;
+            map.borrow_mut().insert(key, value);
+            Ok(())
+        },
+        other => Err(format!("Cannot index into a value of type {}", other.type_name()).into()),
+    }
+}
+
+
+/// Reads a field of a struct value.
+fn read_field(target: &Value, field: &str) -> Result<Value, RuntimeError> {
+    match target {
+        Value::Struct(instance) => {
+            let instance = instance.borrow();
+            match instance.get(field) {
+                Some(value) => Ok(value.clone()),
+                None => Err(format!("Struct '{}' has no field '{}'", instance.name, field).into()),
+            }
+        },
+        other => Err(format!("Cannot access field '{}' on a value of type {}", field, other.type_name()).into()),
+    }
+}
+
+
+/// Stores a value in a field of a struct value after checking it against the
+/// field's declared type.
+fn write_field(target: &Value, field: &str, value: Value, context: &Context) -> Result<(), RuntimeError> {
+    match target {
+        Value::Struct(instance) => {
+            let mut instance = instance.borrow_mut();
+            let struct_name = instance.name.clone();
+            let field_type = context
+                .structs
+                .get(&struct_name)
+                .and_then(|definition| definition.fields.iter().find(|(_, name)| name == field))
+                .map(|(field_type, _)| field_type.clone());
+            let field_type = match field_type {
+                Some(field_type) => field_type,
+                None => return Err(format!("Struct '{}' has no field '{}'", struct_name, field).into()),
             };
-            // Create a new context to evaluate the function body
-            let mut new_context = context.clone();
-            // Iterate over the function parameters and arguments
-            for (param, arg) in function_def.parameters.iter().zip(arguments.iter()) {
-                // Evaluate the argument expression
-                let value = evaluate_expression(arg, &mut new_context);
-                // Insert the argument value into the new context
-                if let Value::Integer(int_value) = value {
-                    new_context.variables.insert(param.1.clone(), int_value);
-                } else {
-                    panic!("Expected integer value for parameter '{}'", param.1);
-                }
+            if !value.matches_type(&field_type) {
+                return Err(format!(
+                    "Field '{}' of struct '{}' expects {}, got {}",
+                    field, struct_name, field_type, value.type_name()
+                ).into());
+            }
+            if let Some(slot) = instance.get_mut(field) {
+                *slot = value;
+            }
+            Ok(())
+        },
+        other => Err(format!("Cannot assign field '{}' on a value of type {}", field, other.type_name()).into()),
+    }
+}
+
+
+/// Tests a value against a match pattern.
+///
+/// # Arguments
+///
+/// * `pattern` - The pattern to test.
+/// * `value` - The value being matched.
+/// * `bindings` - Collects the names bound by the pattern and their values.
+///
+/// # Returns
+///
+/// True if the value matches the pattern.
+fn match_pattern(pattern: &Pattern, value: &Value, bindings: &mut Vec<(String, Value)>) -> bool {
+    match (pattern, value) {
+        (Pattern::Wildcard, _) => true,
+        (Pattern::Binding(name), value) => {
+            bindings.push((name.clone(), value.clone()));
+            true
+        }
+        (Pattern::Integer(expected), Value::Integer(actual)) => expected == actual,
+        (Pattern::Boolean(expected), Value::Boolean(actual)) => expected == actual,
+        (Pattern::String(expected), Value::String(actual)) => expected == actual,
+        (Pattern::Variant { enum_name, variant, fields, .. }, Value::Enum(actual)) => {
+            *enum_name == actual.enum_name
+                && *variant == actual.variant
+                && fields.len() == actual.values.len()
+                && fields
+                    .iter()
+                    .zip(actual.values.iter())
+                    .all(|(field, value)| match_pattern(field, value, bindings))
+        }
+        _ => false,
+    }
+}
+
+
+/// Resolves a name used in call position or as a value to a function.
+///
+/// A variable holding a function takes precedence over a declared function of
+/// the same name, which in turn takes precedence over a builtin.
+///
+/// # Returns
+///
+/// The function as a Value, or an error message if nothing callable has that name.
+fn resolve_function(name: &str, context: &Context) -> Result<Value, RuntimeError> {
+    if let Some(value @ Value::Function(_)) = context.get_variable(name) {
+        return Ok(value);
+    }
+    if context.functions.contains_key(name) {
+        return Ok(Value::Function(Rc::new(FunctionValue::Named(name.to_string()))));
+    }
+    if context.natives.contains_key(name) {
+        return Ok(Value::Function(Rc::new(FunctionValue::Native(name.to_string()))));
+    }
+    Err(format!("Function '{}' not defined", name).into())
+}
+
+
+/// Calls the function with the given name, which may be a variable holding a
+/// function value, a function declared in the script or a native builtin.
+///
+/// # Arguments
+///
+/// * `name` - The name of the function to call.
+/// * `arguments` - The argument expressions, evaluated in the caller's context.
+/// * `span` - The position of the call, recorded in the trace of any error the call raises.
+/// * `context` - The caller's evaluation context.
+///
+/// # Returns
+///
+/// The value produced by the function, or an error message.
+fn call_function(name: &str, arguments: &[ASTNode], span: Span, context: &mut Context) -> Result<Value, RuntimeError> {
+    let function = resolve_function(name, context)?;
+    let mut values = Vec::with_capacity(arguments.len());
+    for arg in arguments {
+        values.push(evaluate_expression(arg, context)?);
+    }
+    call_at(&function, values, span, context)
+}
+
+
+/// Calls a function value with already evaluated arguments, on behalf of a
+/// native such as `map`. The call is recorded at the position of the native's
+/// own call.
+///
+/// # Arguments
+///
+/// * `function` - The function to call.
+/// * `arguments` - The argument values.
+/// * `context` - The caller's evaluation context.
+///
+/// # Returns
+///
+/// The value produced by the function, or an error message.
+pub fn call_value(function: &Value, arguments: Vec<Value>, context: &mut Context) -> Result<Value, RuntimeError> {
+    let call_site = context.current_call_site().unwrap_or_default();
+    call_at(function, arguments, call_site, context)
+}
+
+
+/// Calls a function value with already evaluated arguments, recording the call
+/// on the call stack while it runs.
+///
+/// An error raised by the call captures the stack as it was when the error
+/// left the innermost function, so its trace names every call it unwound through.
+///
+/// # Arguments
+///
+/// * `function` - The function to call.
+/// * `arguments` - The argument values.
+/// * `call_site` - The position of the call.
+/// * `context` - The caller's evaluation context.
+///
+/// # Returns
+///
+/// The value produced by the function, or an error message.
+fn call_at(function: &Value, arguments: Vec<Value>, call_site: Span, context: &mut Context) -> Result<Value, RuntimeError> {
+    let function = match function {
+        Value::Function(function) => function.clone(),
+        other => return Err(format!("A value of type {} cannot be called", other.type_name()).into()),
+    };
+    context.push_frame(function_name(&function), call_site)?;
+    let result = invoke(function, arguments, context).map_err(|mut error| {
+        if error.trace.is_empty() {
+            error.trace = context.backtrace();
+        }
+        error
+    });
+    context.pop_frame();
+    result
+}
+
+
+/// Returns the name a function is shown under in backtraces.
+fn function_name(function: &FunctionValue) -> String {
+    match function {
+        FunctionValue::Named(name) | FunctionValue::Native(name) => name.clone(),
+        FunctionValue::Closure(_) => "<anonymous>".to_string(),
+    }
+}
+
+
+/// Runs a function with already evaluated arguments.
+///
+/// Declared functions run in a copy of the caller's context. Anonymous
+/// functions run with the variables they captured when they were created,
+/// which they share with the scope that created them.
+///
+/// A call the function returns in tail position runs here too, in place of
+/// the function that made it, so that chains of tail calls use constant stack.
+fn invoke(function: Rc<FunctionValue>, arguments: Vec<Value>, context: &mut Context) -> Result<Value, RuntimeError> {
+    let (mut function, mut arguments) = (function, arguments);
+    // The context of the function that made the pending tail call, which the
+    // called function is copied from instead of the original caller's
+    let mut tail_caller: Option<Context> = None;
+    loop {
+        let caller = tail_caller.as_ref().unwrap_or(context);
+        let (body, mut new_context, void) = match &*function {
+            FunctionValue::Named(name) => {
+                // Find the function definition
+                let function_def = match caller.functions.get(name) {
+                    Some(def) => def.clone(),
+                    None => return Err(format!("Function '{}' not defined", name).into()),
+                };
+                check_arity(name, function_def.parameters.len(), arguments.len())?;
+
+                // Evaluate the function body with the arguments
+                let mut new_context = caller.clone();
+                new_context.detach_variables();
+                bind_parameters(&function_def.parameters, arguments, &mut new_context)?;
+                let void = function_def.return_type == "void";
+                (function_def.body, new_context, void)
+            }
+            FunctionValue::Native(name) => {
+                // Natives are never tail called, so the caller is the original one
+                return match context.natives.get(name).copied() {
+                    Some(native) => native(context, arguments),
+                    None => Err(format!("Function '{}' not defined", name).into()),
+                };
+            }
+            FunctionValue::Closure(closure) => {
+                check_arity("<anonymous>", closure.parameters.len(), arguments.len())?;
+                let mut new_context = caller.clone();
+                new_context.variables = closure.captured.clone();
+                bind_parameters(&closure.parameters, arguments, &mut new_context)?;
+                (Rc::clone(&closure.body), new_context, closure.return_type == "void")
+            }
+        };
+        new_context.control = None;
+        new_context.tail_calls = true;
+        let result = evaluate_block(&body, &mut new_context)?;
+        match new_context.control.take() {
+            Some(ControlFlow::TailCall(next, next_arguments, call_site)) => {
+                new_context.replace_frame(function_name(&next), call_site);
+                function = next;
+                arguments = next_arguments;
+                tail_caller = Some(new_context);
             }
-            // Evaluate the function body in the new context and return the result
-            evaluate_block(&function_def.body, &mut new_context)
+            Some(ControlFlow::Return(value)) => return Ok(value),
+            // A `void` function that runs to the end returns no value, whatever its last statement was
+            _ if void => return Ok(Value::Void),
+            _ => return Ok(result),
         }
-        // Panic if the node type is not supported for evaluation
-        _ => panic!("Evaluation for this node not implemented: {:?}", expr),
     }
 }
 
 
+/// Evaluates the value of a `return` statement, which is in tail position.
+///
+/// The callee and arguments of a call are evaluated here, but a script
+/// function is not called: it is left pending for `invoke` to run once the
+/// current function has ended. Natives do not grow the stack and are called at
+/// once. The last expression of the chosen branch of an `if` or `match` is in
+/// tail position too, so `return if (n == 0) { acc } else { f(n - 1, acc) };`
+/// calls `f` in the same way.
+///
+/// # Returns
+///
+/// The `TailCall` to leave pending, or the `Return` of the value.
+fn tail_call(call: &ASTNode, context: &mut Context) -> Result<ControlFlow, RuntimeError> {
+    let (function, arguments, span) = match call {
+        ASTNode::FunctionCall { name, arguments, span } => {
+            (resolve_function(name, context).map_err(|error| error.at(Some(*span)))?, arguments, *span)
+        }
+        ASTNode::CallExpression { callee, arguments, span } => (evaluate(callee, context)?, arguments, *span),
+        ASTNode::Conditional { branches, else_block, .. } => {
+            context.budget.step()?;
+            return match choose_branch(branches, else_block.as_deref(), context)? {
+                Some(block) => tail_block(block, context),
+                None => Ok(ControlFlow::Return(Value::Void)),
+            };
+        }
+        ASTNode::Match { subject, arms, .. } => {
+            context.budget.step()?;
+            return evaluate_match(subject, arms, context, tail_block);
+        }
+        other => return Ok(ControlFlow::Return(evaluate(other, context)?)),
+    };
+    let mut values = Vec::with_capacity(arguments.len());
+    for arg in arguments {
+        values.push(evaluate_expression(arg, context)?);
+    }
+    match function {
+        Value::Function(function) if !matches!(*function, FunctionValue::Native(_)) => {
+            Ok(ControlFlow::TailCall(function, values, span))
+        }
+        function => Ok(ControlFlow::Return(
+            call_at(&function, values, span, context).map_err(|error| error.at(Some(span)))?,
+        )),
+    }
+}
+
+
+/// Evaluates a block whose value is returned, with its last statement in tail
+/// position.
+fn tail_block(block: &[ASTNode], context: &mut Context) -> Result<ControlFlow, RuntimeError> {
+    let Some((last, statements)) = block.split_last() else {
+        return Ok(ControlFlow::Return(Value::Void));
+    };
+    evaluate_block(statements, context)?;
+    match context.control.take() {
+        // A statement before the end left the block early
+        Some(control) => Ok(control),
+        None => tail_call(last, context),
+    }
+}
+
+
+/// Returns the block of the first branch of an `if` whose condition holds, or
+/// the else block, or `None` if neither runs.
+fn choose_branch<'a>(
+    branches: &'a [(ASTNode, Vec<ASTNode>)],
+    else_block: Option<&'a [ASTNode]>,
+    context: &mut Context,
+) -> Result<Option<&'a [ASTNode]>, RuntimeError> {
+    for (condition, block) in branches {
+        if evaluate(condition, context)?.as_bool()? {
+            return Ok(Some(block));
+        }
+    }
+    Ok(else_block)
+}
+
+
+/// Evaluates a `match` by running `body` on the body of the first arm whose
+/// pattern and guard accept the subject, with the arm's bindings in scope.
+///
+/// # Returns
+///
+/// What `body` returns, or an error if no arm matches.
+fn evaluate_match<T>(
+    subject: &ASTNode,
+    arms: &[MatchArm],
+    context: &mut Context,
+    body: fn(&[ASTNode], &mut Context) -> Result<T, RuntimeError>,
+) -> Result<T, RuntimeError> {
+    let subject_value = evaluate(subject, context)?;
+    for arm in arms {
+        let mut bindings = Vec::new();
+        if !match_pattern(&arm.pattern, &subject_value, &mut bindings) {
+            continue;
+        }
+
+        // Bindings are only visible inside the arm, so remember what they shadow
+        let shadowed: Vec<(String, Option<Binding>)> = bindings
+            .into_iter()
+            .map(|(name, value)| {
+                let shadowed = context.bind_variable(&name, value);
+                (name, shadowed)
+            })
+            .collect();
+
+        let guard_passed = match &arm.guard {
+            Some(guard) => evaluate(guard, context).and_then(|value| Ok(value.as_bool()?)),
+            None => Ok(true),
+        };
+        let result = match guard_passed {
+            Ok(true) => Some(body(&arm.body, context)),
+            Ok(false) => None,
+            Err(error) => Some(Err(error)),
+        };
+
+        for (name, previous) in shadowed.into_iter().rev() {
+            context.restore_variable(&name, previous);
+        }
+
+        if let Some(result) = result {
+            return result;
+        }
+    }
+    Err(format!("No match arm matched the value {}", subject_value).into())
+}
+
+
+/// Runs one iteration of a loop body and reports whether the loop should stop.
+///
+/// A `break` or `continue` raised by the body is consumed here; a `return` is
+/// left pending so that it unwinds through the loop to the enclosing function.
+///
+/// # Returns
+///
+/// The value of the body's last statement and true if the loop must stop.
+fn run_loop_body(body: &[ASTNode], context: &mut Context) -> Result<(Value, bool), RuntimeError> {
+    // An iteration costs a step even when the body is empty
+    context.budget.step()?;
+    let result = evaluate_block(body, context)?;
+    let stop = match context.control.take() {
+        Some(ControlFlow::Break) => true,
+        Some(ControlFlow::Continue) | None => false,
+        Some(control @ (ControlFlow::Return(_) | ControlFlow::TailCall(..))) => {
+            context.control = Some(control);
+            true
+        }
+    };
+    Ok((result, stop))
+}
+
+
+/// Evaluates both bounds of a range to integers.
+fn range_bounds(start: &ASTNode, end: &ASTNode, context: &mut Context) -> Result<(i32, i32), RuntimeError> {
+    match (evaluate(start, context)?, evaluate(end, context)?) {
+        (Value::Integer(start), Value::Integer(end)) => Ok((start, end)),
+        (start, end) => Err(format!(
+            "Range bounds must be integers, got {} and {}",
+            start.type_name(),
+            end.type_name()
+        ).into()),
+    }
+}
+
+
+/// Binds the arguments of a call to the parameters of the called function,
+/// checking that each has its parameter's declared type.
+fn bind_parameters(parameters: &[(String, String)], arguments: Vec<Value>, context: &mut Context) -> Result<(), RuntimeError> {
+    for ((param_type, param_name), arg_value) in parameters.iter().zip(arguments) {
+        if !arg_value.matches_type(param_type) {
+            return Err(format!(
+                "Parameter '{}' expects {}, got {}",
+                param_name, param_type, arg_value.type_name()
+            ).into());
+        }
+        context.declare_variable(param_name, arg_value);
+    }
+    Ok(())
+}
+
+
+/// Checks that a function received as many arguments as it declares parameters.
+fn check_arity(name: &str, expected: usize, actual: usize) -> Result<(), RuntimeError> {
+    if expected != actual {
+        return Err(format!(
+            "Function '{}' expects {} argument(s), got {}",
+            name, expected, actual
+        ).into());
+    }
+    Ok(())
+}
+
+
 /// Evaluates an ASTNode and returns the resulting Value.
 ///
 /// # Arguments
@@ -86,271 +696,367 @@ pub fn evaluate_expression(expr: &ASTNode, context: &mut Context) -> Value {
 ///
 /// # Returns
 ///
-/// The resulting Value of the evaluation.
+/// The resulting Value of the evaluation, or the runtime error that stopped it,
+/// located at the innermost node with a known position. Each evaluation is
+/// one step of the script's budget, and fails with a `StackOverflow` error
+/// when the native stack is nearly used up.
+pub fn evaluate(node: &ASTNode, contextparam: &mut Context) -> Result<Value, RuntimeError> {
+    contextparam.budget.step().map_err(|error| error.at(node.span()))?;
+    // Blocks and expressions nest on the native stack as deeply as calls do
+    check_stack(|| "evaluating deeply nested code".to_string()).map_err(|error| error.at(node.span()))?;
+    evaluate_node(node, contextparam).map_err(|error| error.at(node.span()))
+}
 
-pub fn evaluate(node: &ASTNode, contextparam: &mut Context) -> Value {
+
+/// Evaluates a single node for `evaluate`, which adds the position to any error.
+fn evaluate_node(node: &ASTNode, contextparam: &mut Context) -> Result<Value, RuntimeError> {
     match node {
-        ASTNode::IntegerLiteral(value) => Value::Integer(*value),
-        ASTNode::BooleanLiteral(value) => Value::Boolean(*value),
-        ASTNode::StringLiteral(value) => Value::String(value.clone()),
+        ASTNode::IntegerLiteral(value) => Ok(Value::Integer(*value)),
+        ASTNode::FloatLiteral(value) => Ok(Value::Float(*value)),
+        ASTNode::BooleanLiteral(value) => Ok(Value::Boolean(*value)),
+        ASTNode::StringLiteral(value) => Ok(Value::String(value.clone())),
+        ASTNode::InterpolatedString(parts) => {
+            let budget = Rc::clone(&contextparam.budget);
+            let mut text = BoundedString::new(&budget);
+            for part in parts {
+                text.push(&evaluate(part, contextparam)?)?;
+            }
+            Ok(Value::String(text.into_string()))
+        }
         ASTNode::Identifier(name) => {
             if let Some(value) = contextparam.get_variable(name) {
-                Value::Integer(value.clone())
+                Ok(value)
             }  else {
-                panic!("Variable '{}' not found", name);
+                // A bare function name evaluates to the function itself
+                resolve_function(name, contextparam).map_err(|_| format!("Variable '{}' not found", name).into())
             }
         },
-        ASTNode::Assignment { identifier, value } => {
-            let new_value = match value.as_ref() {
-                ASTNode::BinaryOp { op, left, right } => {
-                    let left_value = evaluate(left, contextparam);
-                    let right_value = evaluate(right, contextparam);
-                    match op.as_str() {
-                        "Plus" => match (left_value, right_value) {
-                            (Value::Integer(a), Value::Integer(b)) => Value::Integer(a + b),
-                            _ => panic!("Invalid operands for +"),
-                        },
-                        _ => unimplemented!("Operator '{}' not implemented", op),
-                    }
-                },
-                _ => unimplemented!("Evaluation for assignment value not implemented: {:?}", value),
+        ASTNode::FunctionExpression { return_type, parameters, body, .. } => {
+            Ok(Value::Function(Rc::new(FunctionValue::Closure(Closure {
+                return_type: return_type.clone(),
+                parameters: parameters.clone(),
+                body: Rc::new(body.clone()),
+                captured: contextparam.variables.clone(),
+            }))))
+        },
+        ASTNode::ListLiteral(elements) => {
+            let mut items = Vec::with_capacity(elements.len());
+            for element in elements {
+                items.push(evaluate(element, contextparam)?);
+            }
+            Ok(Value::list(items))
+        },
+        ASTNode::MapLiteral(entries) => {
+            let mut map = OrderedMap::new();
+            for (key, value) in entries {
+                let key = MapKey::from_value(&evaluate(key, contextparam)?)?;
+                let value = evaluate(value, contextparam)?;
+                map.insert(key, value);
+            }
+            Ok(Value::map(map))
+        },
+        ASTNode::Index { target, index, .. } => {
+            let target_value = evaluate(target, contextparam)?;
+            let index_value = evaluate(index, contextparam)?;
+            read_index(&target_value, &index_value)
+        },
+        ASTNode::IndexAssignment { target, index, value } => {
+            let target_value = evaluate(target, contextparam)?;
+            let index_value = evaluate(index, contextparam)?;
+            let new_value = evaluate(value, contextparam)?;
+            write_index(&target_value, &index_value, new_value.clone())?;
+            Ok(new_value)
+        },
+        ASTNode::StructDeclaration { name, fields } => {
+            contextparam.structs.insert(name.clone(), StructDefinition { fields: fields.clone() });
+            Ok(Value::Void)
+        },
+        ASTNode::StructLiteral { name, fields, .. } => {
+            let definition = match contextparam.structs.get(name) {
+                Some(definition) => definition.clone(),
+                None => return Err(format!("Struct '{}' not defined", name).into()),
             };
-            contextparam.set_variable(identifier, new_value.clone());
-            new_value
-        },        
-        ASTNode::BinaryOp { op, left, right } => {
-            let left_value = evaluate(left, contextparam);
-            let right_value = evaluate(right, contextparam);
-            match op.as_str() {
-                "Plus" => match (left_value, right_value) {
-                    (Value::Integer(a), Value::Integer(b)) => Value::Integer(a + b),
-                    _ => panic!("Invalid operands for +"),
-                },
-                "Minus" => match (left_value, right_value) {
-                    (Value::Integer(a), Value::Integer(b)) => Value::Integer(a - b),
-                    _ => panic!("Invalid operands for -"),
-                },
-                "Multiply" => match (left_value, right_value) {
-                    (Value::Integer(a), Value::Integer(b)) => Value::Integer(a * b),
-                    _ => panic!("Invalid operands for *"),
-                },
-                "Divide" => match (left_value, right_value) {
-                    (Value::Integer(a), Value::Integer(b)) => Value::Integer(a / b),
-                    _ => panic!("Invalid operands for /"),
-                },
-                "Modulo" => match (left_value, right_value) {
-                    (Value::Integer(a), Value::Integer(b)) => Value::Integer(a % b),
-                    _ => panic!("Invalid operands for %"),
-                },
-                "Equals" => Value::Boolean(left_value == right_value),
-                "NotEquals" => Value::Boolean(left_value != right_value),
-                "LessThan" => match (left_value, right_value) {
-                    (Value::Integer(a), Value::Integer(b)) => Value::Boolean(a < b),
-                    _ => panic!("Invalid operands for <"),
-                },
-                "LessThanOrEqual" => match (left_value, right_value) {
-                    (Value::Integer(a), Value::Integer(b)) => Value::Boolean(a <= b),
-                    _ => panic!("Invalid operands for <="),
-                },
-                "GreaterThan" => match (left_value, right_value) {
-                    (Value::Integer(a), Value::Integer(b)) => Value::Boolean(a > b),
-                    _ => panic!("Invalid operands for >"),
-                },
-                "GreaterThanOrEqual" => match (left_value, right_value) {
-                    (Value::Integer(a), Value::Integer(b)) => Value::Boolean(a >= b),
-                    _ => panic!("Invalid operands for >="),
-                },
-                "And" => match (left_value, right_value) {
-                    (Value::Boolean(a), Value::Boolean(b)) => Value::Boolean(a && b),
-                    _ => panic!("Invalid operands for 'and'"),
-                },
-                "Or" => match (left_value, right_value) {
-                    (Value::Boolean(a), Value::Boolean(b)) => Value::Boolean(a || b),
-                    _ => panic!("Invalid operands for 'or'"),
-                },
-                "Not" => match left_value {
-                    Value::Boolean(a) => Value::Boolean(!a),
-                    _ => panic!("Invalid operand for 'not'"),
+            for (field, _) in fields {
+                if !definition.fields.iter().any(|(_, declared)| declared == field) {
+                    return Err(format!("Struct '{}' has no field '{}'", name, field).into());
+                }
+                if fields.iter().filter(|(other, _)| other == field).count() > 1 {
+                    return Err(format!("Field '{}' of struct '{}' is initialised more than once", field, name).into());
+                }
+            }
+            // Evaluate the initialisers and store them in declaration order
+            let mut values = Vec::with_capacity(definition.fields.len());
+            for (field_type, field_name) in &definition.fields {
+                let initialiser = match fields.iter().find(|(field, _)| field == field_name) {
+                    Some((_, initialiser)) => initialiser,
+                    None => return Err(format!("Missing field '{}' in construction of struct '{}'", field_name, name).into()),
+                };
+                let value = evaluate(initialiser, contextparam)?;
+                if !value.matches_type(field_type) {
+                    return Err(format!(
+                        "Field '{}' of struct '{}' expects {}, got {}",
+                        field_name, name, field_type, value.type_name()
+                    ).into());
+                }
+                values.push((field_name.clone(), value));
+            }
+            Ok(Value::structure(name, values))
+        },
+        ASTNode::FieldAccess { target, field, .. } => {
+            let target_value = evaluate(target, contextparam)?;
+            read_field(&target_value, field)
+        },
+        ASTNode::FieldAssignment { target, field, value, .. } => {
+            let target_value = evaluate(target, contextparam)?;
+            let new_value = evaluate(value, contextparam)?;
+            write_field(&target_value, field, new_value.clone(), contextparam)?;
+            Ok(new_value)
+        },
+        ASTNode::EnumDeclaration { name, variants } => {
+            contextparam.enums.insert(name.clone(), EnumDefinition { variants: variants.clone() });
+            Ok(Value::Void)
+        },
+        ASTNode::EnumVariant { enum_name, variant, arguments, .. } => {
+            let payload_types = match contextparam.enums.get(enum_name) {
+                Some(definition) => match definition.variants.iter().find(|(name, _)| name == variant) {
+                    Some((_, payload_types)) => payload_types.clone(),
+                    None => return Err(format!("Enum '{}' has no variant '{}'", enum_name, variant).into()),
                 },
-                _ => unimplemented!("Operator '{}' not implemented", op),
+                None => return Err(format!("Enum '{}' not defined", enum_name).into()),
+            };
+            if payload_types.len() != arguments.len() {
+                return Err(format!(
+                    "Variant '{}::{}' expects {} value(s), got {}",
+                    enum_name, variant, payload_types.len(), arguments.len()
+                ).into());
             }
+            let mut values = Vec::with_capacity(arguments.len());
+            for (payload_type, argument) in payload_types.iter().zip(arguments.iter()) {
+                let value = evaluate(argument, contextparam)?;
+                if !value.matches_type(payload_type) {
+                    return Err(format!(
+                        "Variant '{}::{}' expects {}, got {}",
+                        enum_name, variant, payload_type, value.type_name()
+                    ).into());
+                }
+                values.push(value);
+            }
+            Ok(Value::Enum(Rc::new(EnumValue {
+                enum_name: enum_name.clone(),
+                variant: variant.clone(),
+                values,
+            })))
+        },
+        ASTNode::Match { subject, arms, .. } => evaluate_match(subject, arms, contextparam, evaluate_block),
+        ASTNode::Assignment { identifier, value, .. } => {
+            let new_value = evaluate(value, contextparam)?;
+            contextparam.set_variable(identifier, new_value.clone());
+            Ok(new_value)
+        },
+        ASTNode::CompoundAssignment { target, op, value, .. } => match target.as_ref() {
+            ASTNode::Identifier(name) => {
+                let current = match contextparam.get_variable(name) {
+                    Some(current) => current,
+                    None => return Err(format!("Variable '{}' not found", name).into()),
+                };
+                let new_value = apply_binary_op(op, current, evaluate(value, contextparam)?)?;
+                contextparam.set_variable(name, new_value.clone());
+                Ok(new_value)
+            },
+            ASTNode::Index { target, index, .. } => {
+                let target_value = evaluate(target, contextparam)?;
+                let index_value = evaluate(index, contextparam)?;
+                let current = read_index(&target_value, &index_value)?;
+                let new_value = apply_binary_op(op, current, evaluate(value, contextparam)?)?;
+                write_index(&target_value, &index_value, new_value.clone())?;
+                Ok(new_value)
+            },
+            ASTNode::FieldAccess { target, field, span } => {
+                let target_value = evaluate(target, contextparam)?;
+                let current = read_field(&target_value, field).map_err(|error| error.at(Some(*span)))?;
+                let new_value = apply_binary_op(op, current, evaluate(value, contextparam)?)?;
+                write_field(&target_value, field, new_value.clone(), contextparam).map_err(|error| error.at(Some(*span)))?;
+                Ok(new_value)
+            },
+            _ => Err("Invalid compound assignment target".to_string().into()),
+        },
+        // `&&` and `||` only evaluate their right operand when the left does not decide the result
+        ASTNode::BinaryOp { op, left, right, .. } if op == "And" => {
+            Ok(Value::Boolean(logical_operand(left, "&&", contextparam)? && logical_operand(right, "&&", contextparam)?))
+        }
+        ASTNode::BinaryOp { op, left, right, .. } if op == "Or" => {
+            Ok(Value::Boolean(logical_operand(left, "||", contextparam)? || logical_operand(right, "||", contextparam)?))
+        }
+        ASTNode::UnaryOp { op, operand, .. } => {
+            let value = evaluate(operand, contextparam)?;
+            apply_unary_op(op, value)
         }
-        ASTNode::VariableDeclaration {
-            name,
-            value,
-            var_type: _,
-        } => {
-            let value = evaluate(value, contextparam);
-            contextparam.set_variable(name, value.clone());
-            value
+        ASTNode::BinaryOp { op, left, right, .. } => {
+            let left_value = evaluate(left, contextparam)?;
+            let right_value = evaluate(right, contextparam)?;
+            apply_binary_op(op, left_value, right_value)
+        }
+        ASTNode::VariableDeclaration { name, value, .. } | ASTNode::ConstantDeclaration { name, value, .. } => {
+            let value = evaluate(value, contextparam)?;
+            contextparam.declare_variable(name, value.clone());
+            Ok(value)
         },
-        ASTNode::PrintStatement { context } => {
-            let value = evaluate(context, contextparam);
-            println!("here is the context");
-            println!("{:?}",context);
-            println!("{}", value); 
-            Value::Void 
+        ASTNode::PrintStatement { arguments, .. } => {
+            let budget = Rc::clone(&contextparam.budget);
+            let mut line = BoundedString::new(&budget);
+            for (i, argument) in arguments.iter().enumerate() {
+                if i > 0 {
+                    line.push(&" ")?;
+                }
+                line.push(&evaluate(argument, contextparam)?)?;
+            }
+            println!("{}", line.into_string());
+            Ok(Value::Void)
         }
 
-        ASTNode::Loop { condition, body } => {
+        ASTNode::Loop { condition, body, .. } => {
             let mut result = Value::Void; // Default result for an empty loop
-            println!("This is inside evaluation loop");
-            println!("{:?}",evaluate(condition, contextparam));
-            while evaluate(condition, contextparam).as_bool() {
+            while evaluate(condition, contextparam)?.as_bool()? {
                 // Evaluate the body of the loop
-                for statement in body.iter() {
-                    result = evaluate(statement, contextparam);
+                let (value, stop) = run_loop_body(body, contextparam)?;
+                result = value;
+                if stop {
+                    break;
                 }
             }
-            result // Return the result of the last iteration
+            Ok(result) // Return the result of the last iteration
         },
-        ASTNode::IfElse { condition, if_block, else_block } => {
-            if evaluate(condition, contextparam).as_bool() {
-                // Evaluate the if block
-                let mut result = Value::Void;
-                for statement in if_block.iter() {
-                    result = evaluate(statement, contextparam);
-                }
-                result
-            } else if let Some(else_block) = else_block {
-                // Evaluate the else block
-                let mut result = Value::Void;
-                for statement in else_block.iter() {
-                    result = evaluate(statement, contextparam);
+        ASTNode::For { variable, iterable, body } => {
+            // The loop variable is only visible inside the loop, with a binding
+            // of its own in each iteration for closures to capture
+            let shadowed = contextparam.variables.get(variable).cloned();
+            let mut result = Ok(Value::Void);
+
+            if let ASTNode::Range { start, end } = iterable.as_ref() {
+                // Count through a range without materialising it as a list
+                let (start, end) = range_bounds(start, end, contextparam)?;
+                for i in start..end {
+                    contextparam.bind_variable(variable, Value::Integer(i));
+                    match run_loop_body(body, contextparam) {
+                        Ok((value, stop)) => {
+                            result = Ok(value);
+                            if stop {
+                                break;
+                            }
+                        }
+                        Err(error) => {
+                            result = Err(error);
+                            break;
+                        }
+                    }
                 }
-                result
             } else {
-                // No else block, return Void
-                Value::Void
+                let items = match evaluate(iterable, contextparam)? {
+                    Value::List(items) => items.borrow().clone(),
+                    Value::Map(map) => map.borrow().iter().map(|(key, _)| key.to_value()).collect(),
+                    other => return Err(format!("Cannot iterate over a value of type {}", other.type_name()).into()),
+                };
+                for item in items {
+                    contextparam.bind_variable(variable, item);
+                    match run_loop_body(body, contextparam) {
+                        Ok((value, stop)) => {
+                            result = Ok(value);
+                            if stop {
+                                break;
+                            }
+                        }
+                        Err(error) => {
+                            result = Err(error);
+                            break;
+                        }
+                    }
+                }
+            }
+
+            contextparam.restore_variable(variable, shadowed);
+            result
+        },
+        ASTNode::Break { .. } => {
+            contextparam.control = Some(ControlFlow::Break);
+            Ok(Value::Void)
+        },
+        ASTNode::Continue { .. } => {
+            contextparam.control = Some(ControlFlow::Continue);
+            Ok(Value::Void)
+        },
+        ASTNode::Conditional { branches, else_block, .. } => {
+            match choose_branch(branches, else_block.as_deref(), contextparam)? {
+                Some(block) => evaluate_block(block, contextparam),
+                // No branch was taken and there is no else block
+                None => Ok(Value::Void),
             }
         },
-        ASTNode::FunctionDeclaration { name, return_type, parameters, body } => {
+        ASTNode::FunctionDeclaration { name, return_type, parameters, body, .. } => {
             // Add the function definition to the context
             contextparam.functions.insert(name.clone(), FunctionDefinition {
                 return_type: return_type.clone(),
                 parameters: parameters.clone(),
-                body: body.clone(),
+                body: Rc::new(body.clone()),
             });
-            println!("I am inside the function declaration section");
-            // Evaluate the function body (optional, depends on your interpreter design)
-            // for statement in body.iter() {
-            //     evaluate(statement, contextparam); // Assuming evaluate_statement evaluates a single statement
-            // }
-        
+
             // Return Void since function declaration doesn't produce a value
-            Value::Void
+            Ok(Value::Void)
         },
-        ASTNode::ReturnStatement(value) => {
-            match &**value {
-                ASTNode::BinaryOp { op, left, right } => {
-                    let left_value = evaluate(&left, contextparam);
-                    let right_value = evaluate(&right, contextparam);
-        
-                    match op.as_str() {
-                        "Plus" => {
-                            match (left_value, right_value) {
-                                (Value::Integer(left), Value::Integer(right)) => Value::Integer(left + right),
-                                _ => panic!("Operands must be integers for addition"),
-                            }
-                        },
-                        "Minus" => {
-                            match (left_value, right_value) {
-                                (Value::Integer(left), Value::Integer(right)) => Value::Integer(left - right),
-                                _ => panic!("Operands must be integers for subtraction"),
-                            }
-                        },
-                        "Multiply" => {
-                            match (left_value, right_value) {
-                                (Value::Integer(left), Value::Integer(right)) => Value::Integer(left * right),
-                                _ => panic!("Operands must be integers for multiplication"),
-                            }
-                        },
-                        "Divide" => {
-                            match (left_value, right_value) {
-                                (Value::Integer(left), Value::Integer(right)) => Value::Integer(left / right),
-                                _ => panic!("Operands must be integers for division"),
-                            }
-                        },
-                        "Modulo" => {
-                            match (left_value, right_value) {
-                                (Value::Integer(left), Value::Integer(right)) => Value::Integer(left % right),
-                                _ => panic!("Operands must be integers for modulo"),
-                            }
-                        },
-                        "Equals" => Value::Boolean(match (left_value, right_value) {
-                            (Value::Integer(left), Value::Integer(right)) => left == right,
-                            (Value::Boolean(left), Value::Boolean(right)) => left == right,
-                            _ => false,
-                        }),
-                        "NotEquals" => Value::Boolean(match (left_value, right_value) {
-                            (Value::Integer(left), Value::Integer(right)) => left != right,
-                            (Value::Boolean(left), Value::Boolean(right)) => left != right,
-                            _ => false,
-                        }),
-                        "LessThan" => Value::Boolean(match (left_value, right_value) {
-                            (Value::Integer(left), Value::Integer(right)) => left < right,
-                            _ => false,
-                        }),
-                        "LessThanOrEqual" => Value::Boolean(match (left_value, right_value) {
-                            (Value::Integer(left), Value::Integer(right)) => left <= right,
-                            _ => false,
-                        }),
-                        "GreaterThan" => Value::Boolean(match (left_value, right_value) {
-                            (Value::Integer(left), Value::Integer(right)) => left > right,
-                            _ => false,
-                        }),
-                        "GreaterThanOrEqual" => Value::Boolean(match (left_value, right_value) {
-                            (Value::Integer(left), Value::Integer(right)) => left >= right,
-                            _ => false,
-                        }),
-                        
-                        "And" => {
-                            match (left_value, right_value) {
-                                (Value::Boolean(left), Value::Boolean(right)) => Value::Boolean(left && right),
-                                _ => panic!("Operands must be booleans for logical AND"),
-                            }
-                        },
-                        "Or" => {
-                            match (left_value, right_value) {
-                                (Value::Boolean(left), Value::Boolean(right)) => Value::Boolean(left || right),
-                                _ => panic!("Operands must be booleans for logical OR"),
-                            }
-                        },
-                        "Not" => {
-                            match right_value {
-                                Value::Boolean(value) => Value::Boolean(!value),
-                                _ => panic!("Operand must be a boolean for logical NOT"),
-                            }
-                        },
-                        _ => unimplemented!("Operation not implemented: {}", op),
-                    }
-                    
-                    
-                },
-                _ => unimplemented!("Return value must be a binary operation"),
+        ASTNode::ReturnStatement { value, .. } => {
+            let value = match value {
+                Some(value) if contextparam.tail_calls => {
+                    contextparam.control = Some(tail_call(value, contextparam)?);
+                    return Ok(Value::Void);
+                }
+                Some(value) => evaluate(value, contextparam)?,
+                None => Value::Void,
+            };
+            contextparam.control = Some(ControlFlow::Return(value.clone()));
+            Ok(value)
+        },
+        ASTNode::FunctionCall { name, arguments, span } => call_function(name, arguments, *span, contextparam),
+        ASTNode::CallExpression { callee, arguments, span } => {
+            let function = evaluate(callee, contextparam)?;
+            let mut values = Vec::with_capacity(arguments.len());
+            for arg in arguments {
+                values.push(evaluate_expression(arg, contextparam)?);
             }
+            call_at(&function, values, *span, contextparam)
         },
-        ASTNode::FunctionCall { name, arguments } => {
-            // Find the function definition
-            let function_def = match contextparam.functions.get(name) {
-                Some(def) => def,
-                None => panic!("Function '{}' not defined", name),
+        ASTNode::Throw { value, .. } => Err(RuntimeError::thrown(evaluate(value, contextparam)?, &contextparam.budget)),
+        ASTNode::Try { body, catch, finally } => {
+            // A call returned from the body or catch block must finish before
+            // the catch and finally blocks can run, so it is not a tail call
+            let tail_calls = std::mem::replace(&mut contextparam.tail_calls, false);
+            let mut result = evaluate_block(body, contextparam);
+            let caught = match (&result, catch) {
+                (Err(error), _) if error.kind.is_fatal() => None,
+                (Err(error), Some(handler)) => Some((error.to_value(), handler)),
+                _ => None,
             };
-        
-            // Evaluate the function body with the arguments
-            let mut new_context = contextparam.clone();
-            new_context.enter_function(name.clone());
-        
-            // Borrow contextparam mutably here
-            for (param, arg) in function_def.parameters.iter().zip(arguments.iter()) {
-                let arg_value = evaluate_expression(arg, &mut new_context);
-                new_context.set_variable(&param.1, arg_value);
+            if let Some((error, (name, handler))) = caught {
+                // The caught error is only visible inside the catch block
+                let shadowed = contextparam.bind_variable(name, error);
+                result = evaluate_block(handler, contextparam);
+                contextparam.restore_variable(name, shadowed);
+            }
+            contextparam.tail_calls = tail_calls;
+            if result.as_ref().is_err_and(|error| error.kind.is_fatal()) {
+                return result;
             }
-        
-            evaluate_block(&function_def.body, &mut new_context)
+            if let Some(finally) = finally {
+                let pending = contextparam.control.take();
+                let value = evaluate_block(finally, contextparam)?;
+                if contextparam.control.is_some() {
+                    // A break, continue or return in the finally block replaces the outcome of the rest
+                    return Ok(value);
+                }
+                contextparam.control = pending;
+            }
+            result
         },
-        
-        _ => unimplemented!("Evaluation for this node not implemented: {:?}", node),
+
+        _ => Err(format!("Evaluation for this node not implemented: {:?}", node).into()),
     }
 }
 
@@ -366,10 +1072,13 @@ pub fn evaluate(node: &ASTNode, contextparam: &mut Context) -> Value {
 ///
 /// # Returns
 ///
-/// * `Ok(())` if evaluation succeeds, otherwise returns an error message as a String.
-pub fn evaluate_ast(ast: Vec<ASTNode>, context: &mut Context) -> Result<(), String> {
+/// * `Ok(())` if evaluation succeeds, otherwise the runtime error that stopped it.
+pub fn evaluate_ast(ast: Vec<ASTNode>, context: &mut Context) -> Result<(), RuntimeError> {
     for node in ast {
-        evaluate(&node, context); // Evaluate each ASTNode in the list
+        evaluate(&node, context)?; // Evaluate each ASTNode in the list
+        if context.control.is_some() {
+            break; // A top-level return ends the program
+        }
     }
     Ok(()) // Return Ok(()) if evaluation succeeds
 }
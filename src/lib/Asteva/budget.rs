@@ -0,0 +1,331 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt::{self, Write};
+use std::io::{self, BufRead};
+use std::time::Instant;
+use crate::lib::Asteva::error::{ErrorKind, RuntimeError};
+
+/// How many steps pass between two readings of the clock, which is slower to
+/// consult than the counters.
+const CLOCK_INTERVAL: u64 = 1024;
+
+/// How far past its heap limit a script may allocate, at least, before
+/// `CountingAllocator` refuses. Allocations between the limit and the ceiling
+/// are left for `Budget::step` to report, and give the interpreter room to
+/// build that report.
+const HEADROOM: usize = 1024 * 1024;
+
+thread_local! {
+    /// The bytes currently allocated through `CountingAllocator` by this thread.
+    static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+    /// The count of allocated bytes past which `CountingAllocator` refuses
+    /// to allocate on this thread.
+    static CEILING: Cell<isize> = const { Cell::new(isize::MAX) };
+}
+
+/// Adds `change` bytes to the count of the current thread. Allocations made
+/// while the thread is shutting down are not counted.
+fn count(change: isize) {
+    let _ = ALLOCATED.try_with(|allocated| allocated.set(allocated.get().wrapping_add(change)));
+}
+
+/// Returns the bytes currently allocated by this thread, as counted by
+/// `CountingAllocator`.
+fn allocated() -> isize {
+    ALLOCATED.try_with(Cell::get).unwrap_or(0)
+}
+
+/// Returns true if allocating `size` more bytes would take this thread past its ceiling.
+fn exceeds_ceiling(size: usize) -> bool {
+    CEILING
+        .try_with(|ceiling| allocated().saturating_add(size as isize) > ceiling.get())
+        .unwrap_or(false)
+}
+
+/// A global allocator that counts the bytes each thread has allocated, which
+/// is how a `Budget` measures the memory a script uses.
+///
+/// A program that limits the heap must install it:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator;
+/// ```
+///
+/// Without it the heap limit is never reached.
+///
+/// While a budget with a heap limit is in force, the allocator also refuses any
+/// allocation that would take the thread well past the limit, so that a single
+/// operation cannot allocate without bound before `Budget::step` notices. A
+/// refused allocation aborts the process, so the builtins that allocate in
+/// proportion to their arguments ask `Budget::reserve` first and fail with an
+/// `OutOfMemory` error instead, as do the readers of input through
+/// `Budget::read_until` and the formatting of values through `BoundedString`.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if exceeds_ceiling(layout.size()) {
+            return std::ptr::null_mut();
+        }
+        let pointer = System.alloc(layout);
+        if !pointer.is_null() {
+            count(layout.size() as isize);
+        }
+        pointer
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if exceeds_ceiling(layout.size()) {
+            return std::ptr::null_mut();
+        }
+        let pointer = System.alloc_zeroed(layout);
+        if !pointer.is_null() {
+            count(layout.size() as isize);
+        }
+        pointer
+    }
+
+    unsafe fn realloc(&self, pointer: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size > layout.size() && exceeds_ceiling(new_size - layout.size()) {
+            return std::ptr::null_mut();
+        }
+        let moved = System.realloc(pointer, layout, new_size);
+        if !moved.is_null() {
+            count(new_size as isize - layout.size() as isize);
+        }
+        moved
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        System.dealloc(pointer, layout);
+        count(-(layout.size() as isize));
+    }
+}
+
+/// The resources a script may use before it is stopped: a number of
+/// evaluation steps, a point in time and an amount of heap memory.
+///
+/// Running out of any of them stops the script with an `OutOfFuel`, `Timeout`
+/// or `OutOfMemory` error, which `try` cannot catch.
+///
+/// Memory is every byte the running thread allocates, which includes the
+/// interpreter's own structures, such as call frames and copies of the
+/// script's values, as well as the values themselves.
+#[derive(Debug)]
+pub struct Budget {
+    /// The maximum number of evaluation steps.
+    fuel: Option<u64>,
+    /// The moment after which the script may not continue.
+    deadline: Option<Instant>,
+    /// The maximum number of bytes the script may have allocated at once.
+    max_heap: Option<usize>,
+    /// The evaluation steps taken so far.
+    steps: Cell<u64>,
+    /// The bytes allocated by the thread before the script started.
+    baseline: isize,
+    /// The thread's allocation ceiling before this budget set its own, and
+    /// the one it set, if it has a heap limit.
+    ceilings: Option<(isize, isize)>,
+}
+
+impl Budget {
+    /// Creates a budget with the given limits; `None` leaves a resource unlimited.
+    /// Memory is counted from this point on, on the current thread.
+    /// While the budget exists, the thread cannot allocate far beyond the limit;
+    /// see `CountingAllocator`.
+    pub fn new(fuel: Option<u64>, deadline: Option<Instant>, max_heap: Option<usize>) -> Self {
+        let baseline = allocated();
+        let ceilings = max_heap.map(|max_heap| {
+            let allowance = max_heap.saturating_add(max_heap.max(HEADROOM)).min(isize::MAX as usize) as isize;
+            let outer = CEILING.with(Cell::get);
+            let inner = outer.min(baseline.saturating_add(allowance));
+            CEILING.with(|ceiling| ceiling.set(inner));
+            (outer, inner)
+        });
+        Budget {
+            fuel,
+            deadline,
+            max_heap,
+            steps: Cell::new(0),
+            baseline,
+            ceilings,
+        }
+    }
+
+    /// Creates a budget without limits.
+    pub fn unlimited() -> Self {
+        Budget::new(None, None, None)
+    }
+
+    /// Returns the bytes the script currently has allocated.
+    pub fn heap(&self) -> usize {
+        allocated().saturating_sub(self.baseline).max(0) as usize
+    }
+
+    /// Checks that the script may allocate `bytes` more without exceeding its
+    /// heap limit. Builtins whose allocations grow with their arguments call
+    /// this before allocating, as `step` is only checked between them.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())`, or an `OutOfMemory` error if the allocation would exceed the limit.
+    pub fn reserve(&self, bytes: usize) -> Result<(), RuntimeError> {
+        match self.max_heap {
+            Some(max_heap) if self.heap().saturating_add(bytes) > max_heap => Err(out_of_memory(max_heap)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Formats a value the way `print` shows it, failing with an `OutOfMemory`
+    /// error as soon as the text would exceed the heap limit. A collection
+    /// that holds the same list many times over can be far larger as text than
+    /// in memory.
+    pub fn format(&self, value: &dyn fmt::Display) -> Result<String, RuntimeError> {
+        let mut text = BoundedString::new(self);
+        text.push(value)?;
+        Ok(text.into_string())
+    }
+
+    /// Reads `input` up to and including the next `delimiter`, or to its end
+    /// if there is none or `delimiter` is `None`. Each buffer is reserved
+    /// before it is kept, so that input larger than the heap limit is refused
+    /// before it has all been read into memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The source to read from.
+    /// * `delimiter` - The byte to stop after, such as a line feed.
+    /// * `failed` - Turns a failed read into the error to report.
+    ///
+    /// # Returns
+    ///
+    /// The bytes read, or the read's error, or an `OutOfMemory` error.
+    pub fn read_until(
+        &self,
+        input: &mut dyn BufRead,
+        delimiter: Option<u8>,
+        failed: impl Fn(io::Error) -> RuntimeError,
+    ) -> Result<Vec<u8>, RuntimeError> {
+        let mut bytes = Vec::new();
+        loop {
+            let chunk = input.fill_buf().map_err(&failed)?;
+            if chunk.is_empty() {
+                break;
+            }
+            let (length, found) = match delimiter.and_then(|delimiter| chunk.iter().position(|byte| *byte == delimiter)) {
+                Some(position) => (position + 1, true),
+                None => (chunk.len(), false),
+            };
+            self.reserve(length)?;
+            bytes.extend_from_slice(&chunk[..length]);
+            input.consume(length);
+            if found {
+                break;
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Accounts for one evaluation step, checking every limit.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())`, or the error for the first limit that has been exceeded.
+    pub fn step(&self) -> Result<(), RuntimeError> {
+        let steps = self.steps.get() + 1;
+        self.steps.set(steps);
+        if let Some(fuel) = self.fuel {
+            if steps > fuel {
+                return Err(RuntimeError::new(
+                    ErrorKind::OutOfFuel,
+                    format!("Exceeded the limit of {} evaluation steps", fuel),
+                ));
+            }
+        }
+        if let Some(max_heap) = self.max_heap {
+            if self.heap() > max_heap {
+                return Err(out_of_memory(max_heap));
+            }
+        }
+        if let Some(deadline) = self.deadline {
+            if steps.is_multiple_of(CLOCK_INTERVAL) && Instant::now() >= deadline {
+                return Err(RuntimeError::new(ErrorKind::Timeout, "Exceeded the time limit"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Budget {
+    fn drop(&mut self) {
+        // Budgets without a heap limit, such as the one a new `Context` starts
+        // with, leave the ceiling alone
+        if let Some((outer, inner)) = self.ceilings {
+            let _ = CEILING.try_with(|ceiling| {
+                if ceiling.get() == inner {
+                    ceiling.set(outer);
+                }
+            });
+        }
+    }
+}
+
+/// A string built from text whose size the script controls, which asks its
+/// budget before every allocation, so that building it past the heap limit
+/// fails with an `OutOfMemory` error rather than reaching the allocator's ceiling.
+pub struct BoundedString<'a> {
+    /// The budget the text is allocated from.
+    budget: &'a Budget,
+    /// The text so far.
+    text: String,
+    /// Why the last write failed, as `fmt::Error` cannot say.
+    error: Option<RuntimeError>,
+}
+
+impl<'a> BoundedString<'a> {
+    /// Creates an empty string allocated from `budget`.
+    pub fn new(budget: &'a Budget) -> Self {
+        BoundedString {
+            budget,
+            text: String::new(),
+            error: None,
+        }
+    }
+
+    /// Appends a value formatted for display.
+    pub fn push(&mut self, value: &dyn fmt::Display) -> Result<(), RuntimeError> {
+        write!(self, "{}", value).map_err(|_| {
+            self.error
+                .take()
+                .unwrap_or_else(|| RuntimeError::new(ErrorKind::Runtime, "Failed to format a value"))
+        })
+    }
+
+    /// Returns the text built.
+    pub fn into_string(self) -> String {
+        self.text
+    }
+}
+
+impl Write for BoundedString<'_> {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        let needed = self.text.len().saturating_add(text.len());
+        if needed > self.text.capacity() {
+            // Grow the way `String` does, once the budget allows it
+            let capacity = needed.max(self.text.capacity() * 2);
+            if let Err(error) = self.budget.reserve(capacity - self.text.capacity()) {
+                self.error = Some(error);
+                return Err(fmt::Error);
+            }
+            self.text.reserve_exact(capacity - self.text.len());
+        }
+        self.text.push_str(text);
+        Ok(())
+    }
+}
+
+/// The error for a script that has used more than `max_heap` bytes.
+fn out_of_memory(max_heap: usize) -> RuntimeError {
+    RuntimeError::new(ErrorKind::OutOfMemory, format!("Exceeded the limit of {} bytes of memory", max_heap))
+}
@@ -0,0 +1,175 @@
+use crate::lib::Asteva::asteva::call_value;
+use crate::lib::Asteva::context::Context;
+use crate::lib::Asteva::error::{ErrorKind, RuntimeError};
+use crate::lib::Enums::value::{MapKey, Value};
+
+/// Registers every builtin function in the given context.
+pub fn register(context: &mut Context) {
+    context.set_native("len", len);
+    context.set_native("push", push);
+    context.set_native("pop", pop);
+    context.set_native("insert", insert);
+    context.set_native("remove", remove);
+    context.set_native("contains", contains);
+    context.set_native("keys", keys);
+    context.set_native("values", values);
+    context.set_native("map", map);
+    context.set_native("filter", filter);
+    context.set_native("reduce", reduce);
+}
+
+/// Checks that a builtin received exactly `expected` arguments.
+pub fn expect_arity(name: &str, args: &[Value], expected: usize) -> Result<(), RuntimeError> {
+    if args.len() != expected {
+        return Err(format!(
+            "Function '{}' expects {} argument(s), got {}",
+            name,
+            expected,
+            args.len()
+        ).into());
+    }
+    Ok(())
+}
+
+/// `len(list | map | string) -> integer`: returns the number of elements in a
+/// list, entries in a map or characters in a string.
+fn len(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("len", &args, 1)?;
+    match &args[0] {
+        Value::String(text) => Ok(Value::Integer(text.chars().count() as i32)),
+        Value::List(items) => Ok(Value::Integer(items.borrow().len() as i32)),
+        Value::Map(map) => Ok(Value::Integer(map.borrow().len() as i32)),
+        other => Err(format!("len() expects a list, map or string, got {}", other.type_name()).into()),
+    }
+}
+
+/// `push(list, value) -> void`: appends a value to the end of a list.
+fn push(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("push", &args, 2)?;
+    match &args[0] {
+        Value::List(items) => {
+            let mut items = items.borrow_mut();
+            if items.len() == items.capacity() {
+                // The list is full, so pushing doubles its storage
+                context.budget.reserve(items.capacity().max(4) * size_of::<Value>())?;
+            }
+            items.push(args[1].clone());
+            Ok(Value::Void)
+        }
+        other => Err(format!("push() expects a list, got {}", other.type_name()).into()),
+    }
+}
+
+/// `pop(list) -> value`: removes and returns the last element of a list.
+fn pop(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("pop", &args, 1)?;
+    match &args[0] {
+        Value::List(items) => items
+            .borrow_mut()
+            .pop()
+            .ok_or_else(|| RuntimeError::new(ErrorKind::IndexOutOfBounds, "pop() called on an empty list")),
+        other => Err(format!("pop() expects a list, got {}", other.type_name()).into()),
+    }
+}
+
+/// `insert(map, key, value) -> void`: stores a value under a key, replacing any previous value.
+fn insert(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("insert", &args, 3)?;
+    match &args[0] {
+        Value::Map(map) => {
+            let key = MapKey::from_value(&args[1])?;
+            map.borrow_mut().insert(key, args[2].clone());
+            Ok(Value::Void)
+        }
+        other => Err(format!("insert() expects a map, got {}", other.type_name()).into()),
+    }
+}
+
+/// `remove(map, key) -> value`: removes a key from a map and returns its value.
+fn remove(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("remove", &args, 2)?;
+    match &args[0] {
+        Value::Map(map) => {
+            let key = MapKey::from_value(&args[1])?;
+            let removed = map.borrow_mut().remove(&key);
+            removed.ok_or_else(|| RuntimeError::new(ErrorKind::KeyNotFound, format!("Key {} not found in map", key)))
+        }
+        other => Err(format!("remove() expects a map, got {}", other.type_name()).into()),
+    }
+}
+
+/// `contains(map, key) -> boolean`: returns true if the map has an entry for the key.
+fn contains(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("contains", &args, 2)?;
+    match &args[0] {
+        Value::Map(map) => {
+            let key = MapKey::from_value(&args[1])?;
+            Ok(Value::Boolean(map.borrow().contains(&key)))
+        }
+        other => Err(format!("contains() expects a map, got {}", other.type_name()).into()),
+    }
+}
+
+/// `keys(map) -> list`: returns the keys of a map in insertion order.
+fn keys(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("keys", &args, 1)?;
+    match &args[0] {
+        Value::Map(map) => Ok(Value::list(map.borrow().iter().map(|(key, _)| key.to_value()).collect())),
+        other => Err(format!("keys() expects a map, got {}", other.type_name()).into()),
+    }
+}
+
+/// `values(map) -> list`: returns the values of a map in insertion order.
+fn values(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("values", &args, 1)?;
+    match &args[0] {
+        Value::Map(map) => Ok(Value::list(map.borrow().iter().map(|(_, value)| value.clone()).collect())),
+        other => Err(format!("values() expects a map, got {}", other.type_name()).into()),
+    }
+}
+
+/// `map(list, function(T) -> U) -> list`: applies a function to every element of a list.
+fn map(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("map", &args, 2)?;
+    let items = match &args[0] {
+        Value::List(items) => items.borrow().clone(),
+        other => return Err(format!("map() expects a list, got {}", other.type_name()).into()),
+    };
+    let mut mapped = Vec::with_capacity(items.len());
+    for item in items {
+        mapped.push(call_value(&args[1], vec![item], context)?);
+    }
+    Ok(Value::list(mapped))
+}
+
+/// `filter(list, function(T) -> boolean) -> list`: keeps the elements for which a function returns true.
+fn filter(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("filter", &args, 2)?;
+    let items = match &args[0] {
+        Value::List(items) => items.borrow().clone(),
+        other => return Err(format!("filter() expects a list, got {}", other.type_name()).into()),
+    };
+    let mut kept = Vec::new();
+    for item in items {
+        match call_value(&args[1], vec![item.clone()], context)? {
+            Value::Boolean(true) => kept.push(item),
+            Value::Boolean(false) => {}
+            other => return Err(format!("filter() expects its function to return boolean, got {}", other.type_name()).into()),
+        }
+    }
+    Ok(Value::list(kept))
+}
+
+/// `reduce(list, initial, function(A, T) -> A) -> A`: folds a list into a single value.
+fn reduce(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("reduce", &args, 3)?;
+    let items = match &args[0] {
+        Value::List(items) => items.borrow().clone(),
+        other => return Err(format!("reduce() expects a list, got {}", other.type_name()).into()),
+    };
+    let mut accumulator = args[1].clone();
+    for item in items {
+        accumulator = call_value(&args[2], vec![accumulator, item], context)?;
+    }
+    Ok(accumulator)
+}
@@ -0,0 +1,70 @@
+use std::io;
+use crate::lib::Asteva::builtins::expect_arity;
+use crate::lib::Asteva::context::Context;
+use crate::lib::Asteva::error::{ErrorKind, RuntimeError};
+use crate::lib::Enums::value::Value;
+
+/// Registers the console library in the given context.
+///
+/// The input functions read from `Context::input`, which is stdin unless the
+/// embedder supplies another source.
+pub fn register(context: &mut Context) {
+    context.set_native("read_line", read_line);
+    context.set_native("read_int", read_int);
+    context.set_native("read_all", read_all);
+    context.set_native("args", args);
+}
+
+/// Reads the next line of input without its line ending, failing at the end of
+/// input. A line longer than the heap limit allows is an `OutOfMemory` error.
+fn next_line(name: &str, context: &mut Context) -> Result<String, RuntimeError> {
+    let failed = |err: io::Error| RuntimeError::new(ErrorKind::Io, format!("{}() failed to read input: {}", name, err));
+    let bytes = context.budget.read_until(&mut *context.input.borrow_mut(), Some(b'\n'), failed)?;
+    if bytes.is_empty() {
+        return Err(RuntimeError::new(ErrorKind::Io, format!("{}() reached the end of input", name)));
+    }
+    let mut line = String::from_utf8(bytes).map_err(|_| {
+        let message = format!("{}() failed to read input: stream did not contain valid UTF-8", name);
+        RuntimeError::new(ErrorKind::Io, message)
+    })?;
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(line)
+}
+
+/// `read_line() -> string`: reads the next line of input, without its line ending.
+fn read_line(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("read_line", &args, 0)?;
+    next_line("read_line", context).map(Value::String)
+}
+
+/// `read_int() -> integer`: reads the next line of input as a decimal integer,
+/// ignoring surrounding whitespace.
+fn read_int(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("read_int", &args, 0)?;
+    let line = next_line("read_int", context)?;
+    line.trim()
+        .parse()
+        .map(Value::Integer)
+        .map_err(|_| format!("read_int() cannot read \"{}\" as an integer", line).into())
+}
+
+/// `read_all() -> string`: reads everything left in the input, which may be nothing.
+fn read_all(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("read_all", &args, 0)?;
+    let failed = |err: io::Error| RuntimeError::new(ErrorKind::Io, format!("read_all() failed to read input: {}", err));
+    let bytes = context.budget.read_until(&mut *context.input.borrow_mut(), None, failed)?;
+    String::from_utf8(bytes)
+        .map(Value::String)
+        .map_err(|_| "read_all() failed to read input: stream did not contain valid UTF-8".to_string().into())
+}
+
+/// `args() -> list<string>`: returns the command-line arguments given after the script path.
+fn args(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("args", &args, 0)?;
+    Ok(Value::list(context.args.iter().cloned().map(Value::String).collect()))
+}
@@ -1,101 +1,306 @@
+use std::cell::{OnceCell, RefCell};
 use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader};
+use std::rc::Rc;
 use crate::lib::Enums::astnode::ASTNode;
-use crate::lib::Enums::value::Value;
+use crate::lib::Enums::span::Span;
+use crate::lib::Enums::value::{Binding, FunctionValue, Value, Variables};
+use crate::lib::Asteva::budget::Budget;
+use crate::lib::Asteva::builtins;
+use crate::lib::Asteva::console;
+use crate::lib::Asteva::error::{ErrorKind, RuntimeError};
+use crate::lib::Asteva::files::{self, Capabilities};
+use crate::lib::Asteva::math;
+use crate::lib::Asteva::strings;
 
 #[derive(Debug, Clone)]
 pub struct FunctionDefinition {
     pub return_type: String,
     pub parameters: Vec<(String, String)>, // (parameter_type, parameter_name)
-    pub body: Vec<ASTNode>,
+    pub body: Rc<Vec<ASTNode>>, // Shared, as every call copies the context
 }
 
+#[derive(Debug, Clone)]
+pub struct StructDefinition {
+    pub fields: Vec<(String, String)>, // (field_type, field_name)
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumDefinition {
+    pub variants: Vec<(String, Vec<String>)>, // (variant_name, payload_types)
+}
+
+/// A pending change of control flow, raised by `break`, `continue` or `return`
+/// and consumed by the enclosing loop or function call.
+#[derive(Debug, Clone)]
+pub enum ControlFlow {
+    Break,
+    Continue,
+    Return(Value),
+    /// A `return` of a call in tail position: the function ends and the call
+    /// runs in its place, so that the call stack does not grow.
+    TailCall(Rc<FunctionValue>, Vec<Value>, Span),
+}
+
+/// A function call in progress, recorded on the call stack.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// The name of the called function, or `<anonymous>` for a closure.
+    pub function: String,
+    /// The position of the call. A function called by a native, such as the
+    /// closure given to `map`, is recorded at the native's call.
+    pub call_site: Span,
+}
+
+/// The default maximum number of nested function calls.
+///
+/// Every call recurses on the native stack, so the thread running a script
+/// needs `STACK_PER_CALL` bytes of stack per level to reach this depth: about
+/// 2.5 GiB in debug builds and 320 MiB in release builds. See `default_max_depth`
+/// for the limit used on a thread with less.
+pub const DEFAULT_MAX_DEPTH: usize = 10_000;
+
+/// The native stack reserved for each level of script function calls, with
+/// room for calls nested inside expressions. Debug builds need far more, as
+/// their evaluator frames are many times larger.
+pub const STACK_PER_CALL: usize = if cfg!(debug_assertions) { 256 * 1024 } else { 32 * 1024 };
+
+/// Returns the maximum call depth the current thread's stack can hold, up to
+/// `DEFAULT_MAX_DEPTH`, so that a script on a small stack fails with a
+/// `StackOverflow` error rather than overflowing the native stack. Where the
+/// stack's size cannot be found, this is `DEFAULT_MAX_DEPTH`.
+pub fn default_max_depth() -> usize {
+    match remaining_stack() {
+        Some(remaining) => (remaining / STACK_PER_CALL).clamp(1, DEFAULT_MAX_DEPTH),
+        None => DEFAULT_MAX_DEPTH,
+    }
+}
+
+thread_local! {
+    /// The lowest address of the current thread's stack, found on first use.
+    static STACK_END: OnceCell<Option<usize>> = const { OnceCell::new() };
+}
+
+/// Returns how many bytes of the current thread's stack are left below this call.
+pub fn remaining_stack() -> Option<usize> {
+    let end = STACK_END.with(|end| *end.get_or_init(stack_end))?;
+    // The stack grows down, so what is left lies between here and its lowest address
+    let here = 0u8;
+    Some((&here as *const u8 as usize).saturating_sub(end))
+}
+
+/// Returns a `StackOverflow` error if less than `STACK_PER_CALL` bytes of the
+/// current thread's stack are left, saying what was being done with `doing`.
+pub fn check_stack(doing: impl FnOnce() -> String) -> Result<(), RuntimeError> {
+    match remaining_stack() {
+        Some(remaining) if remaining < STACK_PER_CALL => {
+            Err(RuntimeError::new(ErrorKind::StackOverflow, format!("Ran out of stack {}", doing())))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns the lowest address of the current thread's stack.
+#[cfg(target_os = "linux")]
+fn stack_end() -> Option<usize> {
+    let mut attributes = std::mem::MaybeUninit::<libc::pthread_attr_t>::uninit();
+    let mut lowest = std::ptr::null_mut();
+    let mut size = 0;
+    // SAFETY: the attributes are only read after `pthread_getattr_np`
+    // initialises them, and are destroyed before returning
+    let found = unsafe {
+        if libc::pthread_getattr_np(libc::pthread_self(), attributes.as_mut_ptr()) != 0 {
+            return None;
+        }
+        let found = libc::pthread_attr_getstack(attributes.as_ptr(), &mut lowest, &mut size) == 0;
+        libc::pthread_attr_destroy(attributes.as_mut_ptr());
+        found
+    };
+    found.then_some(lowest as usize)
+}
+
+/// Returns the lowest address of the current thread's stack.
+#[cfg(not(target_os = "linux"))]
+fn stack_end() -> Option<usize> {
+    None
+}
+
+/// The source the input builtins read from, shared by every copy of a context.
+pub type Input = Rc<RefCell<dyn BufRead>>;
+
+/// A function implemented in Rust and callable from scripts.
+pub type NativeFunction = fn(&mut Context, Vec<Value>) -> Result<Value, RuntimeError>;
+
 #[derive(Clone)]
 pub struct Context {
-    pub variables: HashMap<String, i32>, // Map of variable names to their integer values
+    pub variables: Variables, // Map of variable names to their values, shared with the closures that captured them
     pub functions: HashMap<String, FunctionDefinition>,
-    pub current_function: Option<String>,
+    pub natives: Rc<HashMap<String, NativeFunction>>, // Shared, as every call copies the context
+    pub structs: HashMap<String, StructDefinition>,
+    pub enums: HashMap<String, EnumDefinition>,
+    pub frames: Rc<RefCell<Vec<Frame>>>, // The calls in progress, outermost first, shared by every copy
+    pub max_depth: usize, // How many calls may be in progress before a StackOverflow error
+    pub control: Option<ControlFlow>, // Set while a break, continue or return unwinds
+    pub tail_calls: bool, // Whether a returned call can replace the running function
+    pub input: Input, // Where read_line, read_int and read_all read from
+    pub args: Rc<Vec<String>>, // The command-line arguments passed to the script
+    pub capabilities: Rc<Capabilities>, // The files the script may read and write
+    pub budget: Rc<Budget>, // The steps, time and memory the script may use
 }
 
 impl Context {
-    /// Create a new context.
+    /// Create a new context with the builtin functions and the math, string,
+    /// console and file libraries registered, reading input from stdin and
+    /// without filesystem access.
     pub fn new() -> Self {
-        Context {
+        let mut context = Context {
             variables: HashMap::new(),
             functions: HashMap::new(),
-            current_function: Some(String::new()),
+            natives: Rc::new(HashMap::new()),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            frames: Rc::new(RefCell::new(Vec::new())),
+            max_depth: DEFAULT_MAX_DEPTH,
+            control: None,
+            tail_calls: false,
+            input: Rc::new(RefCell::new(BufReader::new(io::stdin()))),
+            args: Rc::new(Vec::new()),
+            capabilities: Rc::new(Capabilities::none()),
+            budget: Rc::new(Budget::unlimited()),
+        };
+        builtins::register(&mut context);
+        math::register(&mut context);
+        strings::register(&mut context);
+        console::register(&mut context);
+        files::register(&mut context);
+        context
+    }
+
+    /// Replaces the source the input builtins read from, which is stdin by default.
+    pub fn with_input(mut self, input: Input) -> Self {
+        self.input = input;
+        self
+    }
+
+    /// Grants the file builtins access to the given parts of the filesystem.
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = Rc::new(capabilities);
+        self
+    }
+
+    /// Limits the steps, time and memory the script may use.
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = Rc::new(budget);
+        self
+    }
+
+    /// Sets the command-line arguments returned by `args()`.
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = Rc::new(args);
+        self
+    }
+
+    /// Sets how many function calls may be nested before the innermost one
+    /// fails with a `StackOverflow` error.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Records the start of a function call, or returns a `StackOverflow`
+    /// error if the maximum call depth has been reached or less than
+    /// `STACK_PER_CALL` bytes of native stack are left, as calls nested
+    /// deep inside expressions and blocks use more than one level's share.
+    pub fn push_frame(&mut self, function: String, call_site: Span) -> Result<(), RuntimeError> {
+        let mut frames = self.frames.borrow_mut();
+        if frames.len() >= self.max_depth {
+            return Err(RuntimeError::new(
+                ErrorKind::StackOverflow,
+                format!("Maximum call depth of {} exceeded calling '{}'", self.max_depth, function),
+            ));
         }
+        check_stack(|| format!("calling '{}' at a call depth of {}", function, frames.len()))?;
+        frames.push(Frame { function, call_site });
+        Ok(())
     }
 
-    /// Clone the context.
-    pub fn clone(&self) -> Context {
-        Context {
-            variables: self.variables.clone(),
-            functions: self.functions.clone(),
-            current_function: self.current_function.clone(),
+    /// Replaces the innermost function call with a call it made in tail position.
+    pub fn replace_frame(&mut self, function: String, call_site: Span) {
+        if let Some(frame) = self.frames.borrow_mut().last_mut() {
+            *frame = Frame { function, call_site };
         }
     }
 
-    /// Enter a function context.
-    pub fn enter_function(&mut self, function_name: String) {
-        self.current_function = Some(function_name); // Set the current function
+    /// Records the end of the innermost function call.
+    pub fn pop_frame(&mut self) {
+        self.frames.borrow_mut().pop();
     }
 
-    /// Exit a function context.
-    pub fn exit_function(&mut self) {
-        self.current_function = None; // Reset the current function after exiting
+    /// Returns the position of the innermost call in progress, if any.
+    pub fn current_call_site(&self) -> Option<Span> {
+        self.frames.borrow().last().map(|frame| frame.call_site)
     }
 
-    /// Add a variable binding to the context.
-    pub fn add_variable(&mut self, name: &str, value: i32) {
-        self.variables.insert(name.to_string(), value);
+    /// Returns the calls in progress, innermost first.
+    pub fn backtrace(&self) -> Vec<Frame> {
+        self.frames.borrow().iter().rev().cloned().collect()
     }
 
-    /// Set the value of a variable in the context.
+    /// Assigns a value to a variable in the context. The assignment is seen by
+    /// every closure that captured the variable. A variable that does not exist
+    /// yet is declared.
     pub fn set_variable(&mut self, name: &str, value: Value) {
         match value {
-            Value::Integer(i) => {
-                self.variables.insert(name.to_string(), i);
-            }
-            Value::Boolean(b) => {
-                let int_value = if b { 1 } else { 0 }; // Convert bool to i32
-                self.variables.insert(name.to_string(), int_value);
-            }
             Value::Void => {
                 // Do nothing or handle Void as a special case
             }
-            _ => panic!("Unexpected value type"),
+            value => match self.variables.get(name) {
+                Some(binding) => *binding.borrow_mut() = value,
+                None => self.declare_variable(name, value),
+            },
         }
     }
 
-    /// Get the value of a variable from the context.
-    pub fn get_variable(&self, name: &str) -> Option<&i32> {
-        self.variables.get(name)
+    /// Declares a variable with a binding of its own, replacing any variable of
+    /// the same name. Closures that captured the replaced variable keep it.
+    pub fn declare_variable(&mut self, name: &str, value: Value) {
+        if !matches!(value, Value::Void) {
+            self.bind_variable(name, value);
+        }
     }
 
-    /// Update the value of a variable in the context.
-    pub fn update_variable(&mut self, name: &str, value: i32) {
-        self.variables.insert(name.to_string(), value);
+    /// Binds a name for the duration of a block, such as a loop variable or a
+    /// match binding, and returns the binding it shadows for `restore_variable`.
+    pub fn bind_variable(&mut self, name: &str, value: Value) -> Option<Binding> {
+        self.variables.insert(name.to_string(), Rc::new(RefCell::new(value)))
     }
 
-    /// Remove a variable from the context.
-    pub fn remove_variable(&mut self, name: &str) {
-        self.variables.remove(name);
+    /// Restores the binding shadowed by `bind_variable`, or removes the name if
+    /// it had none.
+    pub fn restore_variable(&mut self, name: &str, shadowed: Option<Binding>) {
+        match shadowed {
+            Some(binding) => self.variables.insert(name.to_string(), binding),
+            None => self.variables.remove(name),
+        };
     }
 
-    /// Set a function definition in the context.
-    pub fn set_function(&mut self, name: String, definition: FunctionDefinition) {
-        self.functions.insert(name, definition);
+    /// Gives every variable a binding of its own holding its current value, so
+    /// that assignments in this context are not seen by the context it was
+    /// copied from.
+    pub fn detach_variables(&mut self) {
+        for binding in self.variables.values_mut() {
+            let value = binding.borrow().clone();
+            *binding = Rc::new(RefCell::new(value));
+        }
     }
 
-    /// Get the value of a function parameter from the context.
-    pub fn get_parameter(&self, name: &str) -> Option<Value> {
-        if let Some(function) = self.functions.get("calculate") {
-            if let Some((_param_type, _)) = function.parameters.iter().find(|(_param_type, param_name)| *param_name == name) {
-                if let Some(param_value) = self.variables.get(name) {
-                    return Some(Value::Integer(*param_value));
-                }
-            }
-        }
-        None
+    /// Get the value of a variable from the context.
+    pub fn get_variable(&self, name: &str) -> Option<Value> {
+        self.variables.get(name).map(|binding| binding.borrow().clone())
+    }
+
+    /// Register a native function under the given name.
+    pub fn set_native(&mut self, name: &str, function: NativeFunction) {
+        Rc::make_mut(&mut self.natives).insert(name.to_string(), function);
     }
 }
@@ -0,0 +1,208 @@
+use std::fmt;
+use crate::lib::Asteva::budget::Budget;
+use crate::lib::Asteva::context::Frame;
+use crate::lib::Checker::diagnostic::Diagnostic;
+use crate::lib::Enums::span::Span;
+use crate::lib::Enums::value::Value;
+
+/// The category of a runtime error. Scripts see it as the `kind` field of a
+/// caught error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// An integer or float divided by zero.
+    DivisionByZero,
+    /// An integer operation whose result does not fit in an integer.
+    Overflow,
+    /// A list index outside the list.
+    IndexOutOfBounds,
+    /// A map lookup of a key that is not in the map.
+    KeyNotFound,
+    /// A chain of function calls deeper than the configured maximum call depth.
+    StackOverflow,
+    /// A file access outside the directories the script was granted.
+    PermissionDenied,
+    /// A failed read or write of a file or of the console.
+    Io,
+    /// A value raised by a `throw` statement.
+    Thrown,
+    /// Any other runtime error, such as a type mismatch or an unknown name.
+    Runtime,
+    /// More evaluation steps than the script's budget allows.
+    OutOfFuel,
+    /// Evaluation still running after the script's deadline.
+    Timeout,
+    /// More memory allocated than the script's budget allows.
+    OutOfMemory,
+}
+
+impl ErrorKind {
+    /// Every kind a script can catch, used to recognise the kind of an error
+    /// value being rethrown.
+    const ALL: [ErrorKind; 9] = [
+        ErrorKind::DivisionByZero,
+        ErrorKind::Overflow,
+        ErrorKind::IndexOutOfBounds,
+        ErrorKind::KeyNotFound,
+        ErrorKind::StackOverflow,
+        ErrorKind::PermissionDenied,
+        ErrorKind::Io,
+        ErrorKind::Thrown,
+        ErrorKind::Runtime,
+    ];
+
+    /// Returns the name scripts see for this kind.
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorKind::DivisionByZero => "DivisionByZero",
+            ErrorKind::Overflow => "Overflow",
+            ErrorKind::IndexOutOfBounds => "IndexOutOfBounds",
+            ErrorKind::KeyNotFound => "KeyNotFound",
+            ErrorKind::StackOverflow => "StackOverflow",
+            ErrorKind::PermissionDenied => "PermissionDenied",
+            ErrorKind::Io => "IoError",
+            ErrorKind::Thrown => "Thrown",
+            ErrorKind::Runtime => "RuntimeError",
+            ErrorKind::OutOfFuel => "OutOfFuel",
+            ErrorKind::Timeout => "Timeout",
+            ErrorKind::OutOfMemory => "OutOfMemory",
+        }
+    }
+
+    /// Returns true for the kinds raised when a script exhausts its budget.
+    /// They stop the script: neither `catch` nor `finally` blocks run.
+    pub fn is_fatal(self) -> bool {
+        matches!(self, ErrorKind::OutOfFuel | ErrorKind::Timeout | ErrorKind::OutOfMemory)
+    }
+
+    /// Returns the kind with the given name, if there is one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    /// Formats the kind as the name scripts see.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// An error raised while a script runs, either by the interpreter or by a
+/// `throw` statement. It unwinds until a `catch` block handles it, or surfaces
+/// to the host through `Interpreter::interpret`.
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    /// What went wrong.
+    pub kind: ErrorKind,
+    /// A human readable description of the problem.
+    pub message: String,
+    /// The value given to `throw`, which a `catch` block receives unchanged.
+    pub value: Option<Value>,
+    /// The innermost node with a known position that was being evaluated.
+    pub span: Option<Span>,
+    /// The function calls in progress when the error was raised, innermost
+    /// first. Empty for an error raised outside any function.
+    pub trace: Vec<Frame>,
+}
+
+impl RuntimeError {
+    /// Creates an error of the given kind.
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        RuntimeError {
+            kind,
+            message: message.into(),
+            value: None,
+            span: None,
+            trace: Vec::new(),
+        }
+    }
+
+    /// Creates the error raised by `throw value`.
+    ///
+    /// Throwing a caught error value raises it again with its original kind
+    /// and message; any other value is raised as a `Thrown` error, with the
+    /// value as its message, or as an `OutOfMemory` error if that message
+    /// would not fit in the script's `budget`.
+    pub fn thrown(value: Value, budget: &Budget) -> Self {
+        let field = |name: &str| match &value {
+            Value::Struct(instance) if instance.borrow().name == "Error" => instance.borrow().get(name).cloned(),
+            _ => None,
+        };
+        let (kind, message) = match (field("kind"), field("message")) {
+            (Some(Value::String(kind)), Some(Value::String(message))) => {
+                (ErrorKind::from_name(&kind).unwrap_or(ErrorKind::Thrown), message)
+            }
+            _ => match budget.format(&value) {
+                Ok(message) => (ErrorKind::Thrown, message),
+                Err(error) => return error,
+            },
+        };
+        RuntimeError {
+            value: Some(value),
+            ..RuntimeError::new(kind, message)
+        }
+    }
+
+    /// Records where the error happened, unless a more precise position is already known.
+    pub fn at(mut self, span: Option<Span>) -> Self {
+        if self.span.is_none() {
+            self.span = span;
+        }
+        self
+    }
+
+    /// Returns the value a `catch` block binds: the thrown value, or an `Error`
+    /// struct with the fields `kind`, `message`, `line` and `column`.
+    pub fn to_value(&self) -> Value {
+        if let Some(value) = &self.value {
+            return value.clone();
+        }
+        let span = self.span.unwrap_or_default();
+        Value::structure("Error", vec![
+            ("kind".to_string(), Value::String(self.kind.name().to_string())),
+            ("message".to_string(), Value::String(self.message.clone())),
+            ("line".to_string(), Value::Integer(span.line as i32)),
+            ("column".to_string(), Value::Integer(span.column as i32)),
+        ])
+    }
+
+    /// Formats the error for display, followed by one line per function call
+    /// it unwound through, prefixing each location with the name of its file.
+    /// Consecutive identical calls, as made by recursion, share one line.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - The name of every source file, indexed by `Span::file`.
+    pub fn render(&self, files: &[String]) -> String {
+        let message = match self.kind {
+            ErrorKind::Runtime => self.message.clone(),
+            ErrorKind::Thrown => format!("Uncaught exception: {}", self.message),
+            kind => format!("{}: {}", kind, self.message),
+        };
+        let mut diagnostic = Diagnostic::error(message);
+        diagnostic.span = self.span;
+        // Recursion repeats the same call many times; show each run of identical frames once
+        for run in self.trace.chunk_by(|a, b| a == b) {
+            let note = match run.len() {
+                1 => format!("in '{}', called here", run[0].function),
+                count => format!("in '{}', called here ({} times)", run[0].function, count),
+            };
+            diagnostic = diagnostic.with_note(run[0].call_site, note);
+        }
+        diagnostic.render(files)
+    }
+}
+
+impl From<String> for RuntimeError {
+    /// Wraps an error message as a `Runtime` error.
+    fn from(message: String) -> Self {
+        RuntimeError::new(ErrorKind::Runtime, message)
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    /// Formats the error for display, with positions but no file names.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(&[]))
+    }
+}
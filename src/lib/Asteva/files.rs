@@ -0,0 +1,210 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+use crate::lib::Asteva::builtins::expect_arity;
+use crate::lib::Asteva::context::Context;
+use crate::lib::Asteva::error::{ErrorKind, RuntimeError};
+use crate::lib::Enums::value::Value;
+
+/// The parts of the filesystem a script may touch.
+///
+/// Access is denied by default. Each granted directory covers everything
+/// beneath it; paths are resolved, following symbolic links, before they are
+/// compared, so `data/../secret` or a link out of `data` is not inside `data`.
+/// A link that cannot be resolved, such as one to a file that does not exist
+/// yet, is refused rather than followed when the file is created.
+#[derive(Debug, Clone, Default)]
+pub struct Capabilities {
+    /// Directories the script may read from.
+    read: Vec<PathBuf>,
+    /// Directories the script may write to.
+    write: Vec<PathBuf>,
+}
+
+/// The kind of access a file builtin needs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Access {
+    Read,
+    Write,
+}
+
+impl Capabilities {
+    /// Creates a configuration that grants no filesystem access.
+    pub fn none() -> Self {
+        Capabilities::default()
+    }
+
+    /// Allows reading files and listing directories under `path`.
+    pub fn allow_read(mut self, path: impl Into<PathBuf>) -> Self {
+        self.read.push(path.into());
+        self
+    }
+
+    /// Allows creating and changing files under `path`.
+    pub fn allow_write(mut self, path: impl Into<PathBuf>) -> Self {
+        self.write.push(path.into());
+        self
+    }
+
+    /// Checks that a builtin may access a path.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The builtin asking for access, named in the error.
+    /// * `access` - Whether the builtin reads or writes.
+    /// * `path` - The path as written in the script.
+    ///
+    /// # Returns
+    ///
+    /// The resolved path, which the builtin should use instead of `path` so the
+    /// file it touches is the one that was checked, or a `PermissionDenied`
+    /// error if it is outside every granted directory.
+    fn check(&self, name: &str, access: Access, path: &str) -> Result<PathBuf, RuntimeError> {
+        let (roots, flag) = match access {
+            Access::Read => (&self.read, "--allow-read"),
+            Access::Write => (&self.write, "--allow-write"),
+        };
+        let resolved = resolve(Path::new(path)).filter(|resolved| {
+            roots
+                .iter()
+                .filter_map(|root| resolve(root))
+                .any(|root| resolved.starts_with(root))
+        });
+        resolved.ok_or_else(|| {
+            let verb = if access == Access::Read { "read" } else { "write" };
+            RuntimeError::new(
+                ErrorKind::PermissionDenied,
+                format!("{}() may not {} '{}' (grant access with {}=<dir>)", name, verb, path, flag),
+            )
+        })
+    }
+}
+
+/// Resolves a path to an absolute one without symbolic links.
+///
+/// The path need not exist: its longest existing ancestor is resolved and the
+/// remaining components are appended. Returns `None` if a missing part of the
+/// path contains `..`, or if a part exists but cannot be resolved, such as a
+/// link to a missing file, as neither can be resolved safely.
+fn resolve(path: &Path) -> Option<PathBuf> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(mut resolved) = existing.canonicalize() {
+            resolved.extend(missing.iter().rev());
+            return Some(resolved);
+        }
+        if fs::symlink_metadata(existing).is_ok() {
+            return None;
+        }
+        missing.push(existing.file_name()?);
+        existing = match existing.parent()? {
+            parent if parent.as_os_str().is_empty() => Path::new("."),
+            parent => parent,
+        };
+    }
+}
+
+/// Registers the file library in the given context.
+///
+/// Every function checks `Context::capabilities` first, so a script only sees
+/// the directories it was granted.
+pub fn register(context: &mut Context) {
+    context.set_native("read_file", read_file);
+    context.set_native("write_file", write_file);
+    context.set_native("append_file", append_file);
+    context.set_native("file_exists", file_exists);
+    context.set_native("list_dir", list_dir);
+}
+
+/// Opens a checked path without following a symbolic link in its last
+/// component, so a link made between `Capabilities::check` and the open cannot
+/// lead outside the granted directories.
+fn open(path: &Path, options: &mut OpenOptions) -> io::Result<File> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NOFOLLOW);
+    }
+    options.open(path)
+}
+
+/// Wraps a failed file operation as an `Io` error.
+fn io_error(message: String) -> RuntimeError {
+    RuntimeError::new(ErrorKind::Io, message)
+}
+
+/// Reads a string argument.
+fn string<'a>(name: &str, value: &'a Value) -> Result<&'a str, RuntimeError> {
+    match value {
+        Value::String(value) => Ok(value),
+        other => Err(format!("{}() expects a string, got {}", name, other.type_name()).into()),
+    }
+}
+
+/// `read_file(string path) -> string`: returns the contents of a text file.
+/// A file larger than the heap limit allows is an `OutOfMemory` error, raised
+/// before it is read where its size is known in advance.
+fn read_file(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("read_file", &args, 1)?;
+    let name = string("read_file", &args[0])?;
+    let path = context.capabilities.check("read_file", Access::Read, name)?;
+    let failed = |err: io::Error| io_error(format!("read_file() cannot read '{}': {}", name, err));
+    let file = open(&path, OpenOptions::new().read(true)).map_err(failed)?;
+    let size = file.metadata().map_err(failed)?.len();
+    context.budget.reserve(usize::try_from(size).unwrap_or(usize::MAX))?;
+    // Files such as those in /proc grow as they are read, so each buffer is
+    // reserved as well
+    let bytes = context.budget.read_until(&mut BufReader::new(file), None, failed)?;
+    String::from_utf8(bytes)
+        .map(Value::String)
+        .map_err(|_| io_error(format!("read_file() cannot read '{}': stream did not contain valid UTF-8", name)))
+}
+
+/// `write_file(string path, string contents)`: replaces the contents of a file,
+/// creating it if needed.
+fn write_file(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("write_file", &args, 2)?;
+    let name = string("write_file", &args[0])?;
+    let path = context.capabilities.check("write_file", Access::Write, name)?;
+    let contents = string("write_file", &args[1])?;
+    open(&path, OpenOptions::new().write(true).create(true).truncate(true))
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map(|_| Value::Void)
+        .map_err(|err| io_error(format!("write_file() cannot write '{}': {}", name, err)))
+}
+
+/// `append_file(string path, string contents)`: adds to the end of a file,
+/// creating it if needed.
+fn append_file(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("append_file", &args, 2)?;
+    let name = string("append_file", &args[0])?;
+    let path = context.capabilities.check("append_file", Access::Write, name)?;
+    let contents = string("append_file", &args[1])?;
+    open(&path, OpenOptions::new().create(true).append(true))
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map(|_| Value::Void)
+        .map_err(|err| io_error(format!("append_file() cannot write '{}': {}", name, err)))
+}
+
+/// `file_exists(string path) -> boolean`: returns true if a file or directory exists at `path`.
+fn file_exists(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("file_exists", &args, 1)?;
+    let path = context.capabilities.check("file_exists", Access::Read, string("file_exists", &args[0])?)?;
+    Ok(Value::Boolean(path.exists()))
+}
+
+/// `list_dir(string path) -> list<string>`: returns the names of the entries in
+/// a directory, sorted.
+fn list_dir(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("list_dir", &args, 1)?;
+    let name = string("list_dir", &args[0])?;
+    let path = context.capabilities.check("list_dir", Access::Read, name)?;
+    let error = |err: io::Error| io_error(format!("list_dir() cannot list '{}': {}", name, err));
+    let mut names = Vec::new();
+    for entry in fs::read_dir(path).map_err(error)? {
+        names.push(entry.map_err(error)?.file_name().to_string_lossy().into_owned());
+    }
+    names.sort();
+    Ok(Value::list(names.into_iter().map(Value::String).collect()))
+}
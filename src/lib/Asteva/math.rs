@@ -0,0 +1,198 @@
+use crate::lib::Asteva::builtins::expect_arity;
+use crate::lib::Asteva::context::Context;
+use crate::lib::Asteva::error::{ErrorKind, RuntimeError};
+use crate::lib::Enums::value::Value;
+
+/// Registers the math library in the given context.
+///
+/// Where a signature says `number`, both integers and floats are accepted. The
+/// functions taking two or three numbers return an integer when every argument
+/// is an integer, and a float otherwise.
+pub fn register(context: &mut Context) {
+    context.set_native("abs", abs);
+    context.set_native("min", min);
+    context.set_native("max", max);
+    context.set_native("pow", pow);
+    context.set_native("sqrt", sqrt);
+    context.set_native("gcd", gcd);
+    context.set_native("clamp", clamp);
+    context.set_native("floor", floor);
+    context.set_native("ceil", ceil);
+    context.set_native("round", round);
+    context.set_native("sin", sin);
+    context.set_native("cos", cos);
+    context.set_native("log", log);
+}
+
+/// Reads a numeric argument as a float.
+fn number(name: &str, value: &Value) -> Result<f64, RuntimeError> {
+    match value {
+        Value::Integer(value) => Ok(*value as f64),
+        Value::Float(value) => Ok(*value),
+        other => Err(format!("{}() expects a number, got {}", name, other.type_name()).into()),
+    }
+}
+
+/// Reads an integer argument.
+fn integer(name: &str, value: &Value) -> Result<i32, RuntimeError> {
+    match value {
+        Value::Integer(value) => Ok(*value),
+        other => Err(format!("{}() expects an integer, got {}", name, other.type_name()).into()),
+    }
+}
+
+/// Converts a float to an integer, failing if it is out of range or not a number.
+fn float_to_integer(name: &str, value: f64) -> Result<Value, RuntimeError> {
+    if value.is_nan() || value < i32::MIN as f64 || value > i32::MAX as f64 {
+        return Err(RuntimeError::new(
+            ErrorKind::Overflow,
+            format!("{}() result {} does not fit in an integer", name, value),
+        ));
+    }
+    Ok(Value::Integer(value as i32))
+}
+
+/// `abs(number) -> number`: returns the absolute value.
+fn abs(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("abs", &args, 1)?;
+    match &args[0] {
+        Value::Integer(value) => value
+            .checked_abs()
+            .map(Value::Integer)
+            .ok_or_else(|| RuntimeError::new(ErrorKind::Overflow, "Integer overflow in abs()")),
+        Value::Float(value) => Ok(Value::Float(value.abs())),
+        other => Err(format!("abs() expects a number, got {}", other.type_name()).into()),
+    }
+}
+
+/// Picks one of two numbers, keeping integers as integers.
+fn pick(name: &str, args: &[Value], prefer_left: fn(f64, f64) -> bool) -> Result<Value, RuntimeError> {
+    expect_arity(name, args, 2)?;
+    let (a, b) = (number(name, &args[0])?, number(name, &args[1])?);
+    match (&args[0], &args[1]) {
+        (Value::Integer(_), Value::Integer(_)) => Ok(if prefer_left(a, b) { args[0].clone() } else { args[1].clone() }),
+        _ => Ok(Value::Float(if prefer_left(a, b) { a } else { b })),
+    }
+}
+
+/// `min(number, number) -> number`: returns the smaller of two numbers.
+fn min(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    pick("min", &args, |a, b| a <= b)
+}
+
+/// `max(number, number) -> number`: returns the larger of two numbers.
+fn max(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    pick("max", &args, |a, b| a >= b)
+}
+
+/// `pow(number base, number exponent) -> number`: raises `base` to the power
+/// `exponent`. With integer arguments the exponent must not be negative.
+fn pow(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("pow", &args, 2)?;
+    match (&args[0], &args[1]) {
+        (Value::Integer(_), Value::Integer(exponent)) if *exponent < 0 => {
+            Err("pow() expects a non-negative integer exponent".to_string().into())
+        }
+        (Value::Integer(base), Value::Integer(exponent)) => base
+            .checked_pow(*exponent as u32)
+            .map(Value::Integer)
+            .ok_or_else(|| RuntimeError::new(ErrorKind::Overflow, "Integer overflow in pow()")),
+        (base, exponent) => Ok(Value::Float(number("pow", base)?.powf(number("pow", exponent)?))),
+    }
+}
+
+/// `sqrt(integer) -> integer`: returns the square root rounded down.
+/// `sqrt(float) -> float`: returns the square root.
+/// Negative arguments are an error.
+fn sqrt(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("sqrt", &args, 1)?;
+    match &args[0] {
+        Value::Integer(value) if *value < 0 => Err(format!("sqrt() of negative number {}", value).into()),
+        Value::Integer(value) => {
+            // Start from the float estimate and correct it, so large values round down exactly
+            let value = *value as i64;
+            let mut root = (value as f64).sqrt() as i64;
+            while root * root > value {
+                root -= 1;
+            }
+            while (root + 1) * (root + 1) <= value {
+                root += 1;
+            }
+            Ok(Value::Integer(root as i32))
+        }
+        Value::Float(value) if *value < 0.0 => Err(format!("sqrt() of negative number {:?}", value).into()),
+        Value::Float(value) => Ok(Value::Float(value.sqrt())),
+        other => Err(format!("sqrt() expects a number, got {}", other.type_name()).into()),
+    }
+}
+
+/// `gcd(integer, integer) -> integer`: returns the greatest common divisor,
+/// which is never negative. `gcd(0, 0)` is 0.
+fn gcd(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("gcd", &args, 2)?;
+    let mut a = integer("gcd", &args[0])?.unsigned_abs();
+    let mut b = integer("gcd", &args[1])?.unsigned_abs();
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    i32::try_from(a)
+        .map(Value::Integer)
+        .map_err(|_| RuntimeError::new(ErrorKind::Overflow, "Integer overflow in gcd()"))
+}
+
+/// `clamp(number value, number low, number high) -> number`: limits `value` to
+/// the range from `low` to `high`, which must not be empty.
+fn clamp(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("clamp", &args, 3)?;
+    let value = number("clamp", &args[0])?;
+    let low = number("clamp", &args[1])?;
+    let high = number("clamp", &args[2])?;
+    if low > high {
+        return Err(format!("clamp() called with low {} greater than high {}", args[1], args[2]).into());
+    }
+    let chosen = if value < low { 1 } else if value > high { 2 } else { 0 };
+    match args.iter().all(|arg| matches!(arg, Value::Integer(_))) {
+        true => Ok(args[chosen].clone()),
+        false => Ok(Value::Float(number("clamp", &args[chosen])?)),
+    }
+}
+
+/// `floor(number) -> integer`: rounds down to the nearest integer.
+fn floor(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("floor", &args, 1)?;
+    float_to_integer("floor", number("floor", &args[0])?.floor())
+}
+
+/// `ceil(number) -> integer`: rounds up to the nearest integer.
+fn ceil(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("ceil", &args, 1)?;
+    float_to_integer("ceil", number("ceil", &args[0])?.ceil())
+}
+
+/// `round(number) -> integer`: rounds to the nearest integer, halves away from zero.
+fn round(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("round", &args, 1)?;
+    float_to_integer("round", number("round", &args[0])?.round())
+}
+
+/// `sin(number radians) -> float`: returns the sine of an angle.
+fn sin(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("sin", &args, 1)?;
+    Ok(Value::Float(number("sin", &args[0])?.sin()))
+}
+
+/// `cos(number radians) -> float`: returns the cosine of an angle.
+fn cos(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("cos", &args, 1)?;
+    Ok(Value::Float(number("cos", &args[0])?.cos()))
+}
+
+/// `log(number) -> float`: returns the natural logarithm of a positive number.
+fn log(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("log", &args, 1)?;
+    let value = number("log", &args[0])?;
+    if value <= 0.0 {
+        return Err(format!("log() of non-positive number {}", args[0]).into());
+    }
+    Ok(Value::Float(value.ln()))
+}
@@ -1,2 +1,9 @@
 pub mod asteva;
-pub mod context;
\ No newline at end of file
+pub mod budget;
+pub mod builtins;
+pub mod console;
+pub mod context;
+pub mod error;
+pub mod files;
+pub mod math;
+pub mod strings;
\ No newline at end of file
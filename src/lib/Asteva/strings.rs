@@ -0,0 +1,354 @@
+use crate::lib::Asteva::builtins::expect_arity;
+use crate::lib::Asteva::budget::Budget;
+use crate::lib::Asteva::context::Context;
+use crate::lib::Asteva::error::RuntimeError;
+use crate::lib::Enums::value::Value;
+
+/// Registers the string library in the given context.
+///
+/// Positions and lengths count characters, not bytes, in the same way as the
+/// lexer reads source code, so `substring("héllo", 1, 2)` is `"é"`.
+pub fn register(context: &mut Context) {
+    context.set_native("substring", substring);
+    context.set_native("index_of", index_of);
+    context.set_native("split", split);
+    context.set_native("join", join);
+    context.set_native("trim", trim);
+    context.set_native("to_upper", to_upper);
+    context.set_native("to_lower", to_lower);
+    context.set_native("replace", replace);
+    context.set_native("starts_with", starts_with);
+    context.set_native("ends_with", ends_with);
+    context.set_native("to_string", to_string);
+    context.set_native("parse_int", parse_int);
+    context.set_native("format", format);
+}
+
+/// Reads a string argument.
+fn string<'a>(name: &str, value: &'a Value) -> Result<&'a str, RuntimeError> {
+    match value {
+        Value::String(value) => Ok(value),
+        other => Err(format!("{}() expects a string, got {}", name, other.type_name()).into()),
+    }
+}
+
+/// Reads an integer argument.
+fn integer(name: &str, value: &Value) -> Result<i32, RuntimeError> {
+    match value {
+        Value::Integer(value) => Ok(*value),
+        other => Err(format!("{}() expects an integer, got {}", name, other.type_name()).into()),
+    }
+}
+
+/// `substring(string, integer start, integer end) -> string`: returns the
+/// characters from `start` up to but not including `end`.
+fn substring(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("substring", &args, 3)?;
+    let text = string("substring", &args[0])?;
+    let start = integer("substring", &args[1])?;
+    let end = integer("substring", &args[2])?;
+    let length = text.chars().count();
+    if start < 0 || end < start || end as usize > length {
+        return Err(format!(
+            "substring() range {}..{} is out of bounds for a string of length {}",
+            start, end, length
+        ).into());
+    }
+    let slice = text.chars().skip(start as usize).take((end - start) as usize).collect();
+    Ok(Value::String(slice))
+}
+
+/// `index_of(string, string needle) -> integer`: returns the position of the
+/// first occurrence of `needle`, or -1 if it does not occur.
+fn index_of(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("index_of", &args, 2)?;
+    let text = string("index_of", &args[0])?;
+    let needle = string("index_of", &args[1])?;
+    let position = match text.find(needle) {
+        Some(byte_index) => text[..byte_index].chars().count() as i32,
+        None => -1,
+    };
+    Ok(Value::Integer(position))
+}
+
+/// `split(string, string separator) -> list<string>`: splits a string at every
+/// occurrence of `separator`. An empty separator splits it into characters.
+fn split(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("split", &args, 2)?;
+    let text = string("split", &args[0])?;
+    let separator = string("split", &args[1])?;
+    let count = if separator.is_empty() { text.chars().count() } else { text.matches(separator).count() + 1 };
+    context.budget.reserve(count * size_of::<Value>() + text.len())?;
+    let parts = if separator.is_empty() {
+        text.chars().map(|c| Value::String(c.to_string())).collect()
+    } else {
+        text.split(separator).map(|part| Value::String(part.to_string())).collect()
+    };
+    Ok(Value::list(parts))
+}
+
+/// `join(list<string>, string separator) -> string`: joins the strings of a
+/// list, placing `separator` between them.
+fn join(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("join", &args, 2)?;
+    let items = match &args[0] {
+        Value::List(items) => items.borrow(),
+        other => return Err(format!("join() expects a list, got {}", other.type_name()).into()),
+    };
+    let separator = string("join", &args[1])?;
+    let mut parts = Vec::with_capacity(items.len());
+    for item in items.iter() {
+        match item {
+            Value::String(part) => parts.push(part.as_str()),
+            other => return Err(format!("join() expects a list of strings, found {}", other.type_name()).into()),
+        }
+    }
+    let length = parts.iter().map(|part| part.len()).sum::<usize>();
+    context.budget.reserve(length + separator.len() * parts.len().saturating_sub(1))?;
+    Ok(Value::String(parts.join(separator)))
+}
+
+/// `trim(string) -> string`: removes leading and trailing whitespace.
+fn trim(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("trim", &args, 1)?;
+    Ok(Value::String(string("trim", &args[0])?.trim().to_string()))
+}
+
+/// `to_upper(string) -> string`: converts every character to upper case.
+fn to_upper(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("to_upper", &args, 1)?;
+    let text = string("to_upper", &args[0])?;
+    context.budget.reserve(text.len())?;
+    Ok(Value::String(text.to_uppercase()))
+}
+
+/// `to_lower(string) -> string`: converts every character to lower case.
+fn to_lower(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("to_lower", &args, 1)?;
+    let text = string("to_lower", &args[0])?;
+    context.budget.reserve(text.len())?;
+    Ok(Value::String(text.to_lowercase()))
+}
+
+/// `replace(string, string from, string to) -> string`: replaces every
+/// occurrence of `from`, which must not be empty, with `to`.
+fn replace(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("replace", &args, 3)?;
+    let text = string("replace", &args[0])?;
+    let from = string("replace", &args[1])?;
+    let to = string("replace", &args[2])?;
+    if from.is_empty() {
+        return Err("replace() expects a non-empty string to replace".to_string().into());
+    }
+    let count = text.matches(from).count();
+    context.budget.reserve((text.len() - count * from.len()).saturating_add(count.saturating_mul(to.len())))?;
+    Ok(Value::String(text.replace(from, to)))
+}
+
+/// `starts_with(string, string prefix) -> boolean`: returns true if the string begins with `prefix`.
+fn starts_with(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("starts_with", &args, 2)?;
+    let text = string("starts_with", &args[0])?;
+    Ok(Value::Boolean(text.starts_with(string("starts_with", &args[1])?)))
+}
+
+/// `ends_with(string, string suffix) -> boolean`: returns true if the string ends with `suffix`.
+fn ends_with(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("ends_with", &args, 2)?;
+    let text = string("ends_with", &args[0])?;
+    Ok(Value::Boolean(text.ends_with(string("ends_with", &args[1])?)))
+}
+
+/// `to_string(any) -> string`: formats any value the way `print` shows it.
+fn to_string(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("to_string", &args, 1)?;
+    context.budget.format(&args[0]).map(Value::String)
+}
+
+/// `parse_int(string) -> integer`: reads a decimal integer, with an optional
+/// sign and surrounding whitespace.
+fn parse_int(_context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    expect_arity("parse_int", &args, 1)?;
+    let text = string("parse_int", &args[0])?;
+    text.trim()
+        .parse()
+        .map(Value::Integer)
+        .map_err(|_| format!("parse_int() cannot read \"{}\" as an integer", text).into())
+}
+
+/// `format(string template, any...) -> string`: replaces each `{}` in the
+/// template with the next value, formatted the way `print` shows it. `{{` and
+/// `}}` stand for literal braces.
+///
+/// A placeholder may carry a specification after a colon, `{:[[fill]align][0][width][.precision][radix]}`:
+/// `align` is `<`, `^` or `>` and `fill` the character to pad with (a space by
+/// default); `0` pads numbers with zeros after their sign; `precision` sets the
+/// number of decimals of a number; and `radix` is `x`, `X`, `o` or `b` to show
+/// an integer in hexadecimal, octal or binary. Numbers are right aligned and
+/// everything else left aligned unless an alignment is given. Neither `width`
+/// nor `precision` may exceed `MAX_FORMAT_WIDTH`.
+fn format(context: &mut Context, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let template = match args.first() {
+        Some(template) => string("format", template)?,
+        None => return Err("Function 'format' expects at least 1 argument(s), got 0".to_string().into()),
+    };
+    let mut values = args[1..].iter();
+    let mut output = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push('}');
+            }
+            '{' => {
+                let mut spec = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => spec.push(c),
+                        None => return Err("format() template has an unclosed '{'".to_string().into()),
+                    }
+                }
+                let value = values
+                    .next()
+                    .ok_or_else(|| "format() template has more placeholders than values".to_string())?;
+                let formatted = FormatSpec::parse(&spec)?.apply(value, &context.budget)?;
+                context.budget.reserve(formatted.len())?;
+                output.push_str(&formatted);
+            }
+            '}' => return Err("format() template has an unmatched '}'".to_string().into()),
+            c => output.push(c),
+        }
+    }
+    if values.next().is_some() {
+        return Err("format() was given more values than its template has placeholders".to_string().into());
+    }
+    Ok(Value::String(output))
+}
+
+/// The largest width or precision a `format` placeholder may ask for, the same
+/// bound Rust's own formatting places on them.
+const MAX_FORMAT_WIDTH: usize = u16::MAX as usize;
+
+/// How a `format` placeholder shows its value.
+#[derive(Debug, Default)]
+struct FormatSpec {
+    /// The character used to pad the value to `width`.
+    fill: Option<char>,
+    /// `<`, `^` or `>`, if the placeholder chooses an alignment.
+    align: Option<char>,
+    /// Whether numbers are padded with zeros after their sign.
+    zero: bool,
+    /// The minimum number of characters to produce.
+    width: usize,
+    /// The number of decimals to show a number with.
+    precision: Option<usize>,
+    /// `x`, `X`, `o` or `b` to show an integer in another base.
+    radix: Option<char>,
+}
+
+impl FormatSpec {
+    /// Parses the text between the braces of a placeholder.
+    fn parse(spec: &str) -> Result<Self, RuntimeError> {
+        let mut parsed = FormatSpec::default();
+        if spec.is_empty() {
+            return Ok(parsed);
+        }
+        let invalid = || RuntimeError::from(format!("format() placeholder '{{{}}}' is not a valid specification", spec));
+        let body: Vec<char> = spec.strip_prefix(':').ok_or_else(invalid)?.chars().collect();
+        let is_align = |c: Option<&char>| matches!(c, Some('<') | Some('^') | Some('>'));
+        let mut i = 0;
+        if is_align(body.get(1)) {
+            parsed.fill = Some(body[0]);
+            parsed.align = Some(body[1]);
+            i = 2;
+        } else if is_align(body.first()) {
+            parsed.align = Some(body[0]);
+            i = 1;
+        }
+        if body.get(i) == Some(&'0') {
+            parsed.zero = true;
+            i += 1;
+        }
+        let digits = |i: &mut usize| {
+            let start = *i;
+            while body.get(*i).is_some_and(|c| c.is_ascii_digit()) {
+                *i += 1;
+            }
+            body[start..*i].iter().collect::<String>()
+        };
+        let width = digits(&mut i);
+        if !width.is_empty() {
+            parsed.width = width.parse().map_err(|_| invalid())?;
+        }
+        if body.get(i) == Some(&'.') {
+            i += 1;
+            parsed.precision = Some(digits(&mut i).parse().map_err(|_| invalid())?);
+        }
+        if let Some(radix @ ('x' | 'X' | 'o' | 'b')) = body.get(i) {
+            parsed.radix = Some(*radix);
+            i += 1;
+        }
+        if i != body.len() {
+            return Err(invalid());
+        }
+        if parsed.width.max(parsed.precision.unwrap_or(0)) > MAX_FORMAT_WIDTH {
+            return Err(format!(
+                "format() placeholder '{{{}}}' asks for more than {} characters",
+                spec, MAX_FORMAT_WIDTH
+            ).into());
+        }
+        Ok(parsed)
+    }
+
+    /// Formats a value according to the specification, allocating its text from `budget`.
+    fn apply(&self, value: &Value, budget: &Budget) -> Result<String, RuntimeError> {
+        let numeric = matches!(value, Value::Integer(_) | Value::Float(_));
+        let text = match (self.radix, self.precision, value) {
+            (Some(radix), _, Value::Integer(n)) => {
+                let magnitude = n.unsigned_abs();
+                let digits = match radix {
+                    'x' => format!("{:x}", magnitude),
+                    'X' => format!("{:X}", magnitude),
+                    'o' => format!("{:o}", magnitude),
+                    _ => format!("{:b}", magnitude),
+                };
+                if *n < 0 { format!("-{}", digits) } else { digits }
+            }
+            (Some(radix), _, other) => {
+                return Err(format!("format() radix '{}' expects an integer, got {}", radix, other.type_name()).into());
+            }
+            (None, Some(precision), Value::Integer(n)) => format!("{:.*}", precision, *n as f64),
+            (None, Some(precision), Value::Float(n)) => format!("{:.*}", precision, n),
+            (None, Some(_), other) => {
+                return Err(format!("format() precision expects a number, got {}", other.type_name()).into());
+            }
+            (None, None, value) => budget.format(value)?,
+        };
+        let length = text.chars().count();
+        if length >= self.width {
+            return Ok(text);
+        }
+        let padding = self.width - length;
+        if self.zero && numeric && self.align.is_none() {
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", text.as_str()),
+            };
+            return Ok(format!("{}{}{}", sign, "0".repeat(padding), digits));
+        }
+        let fill = self.fill.unwrap_or(' ');
+        let pad = |count: usize| fill.to_string().repeat(count);
+        let align = self.align.unwrap_or(if numeric { '>' } else { '<' });
+        Ok(match align {
+            '<' => format!("{}{}", text, pad(padding)),
+            '>' => format!("{}{}", pad(padding), text),
+            _ => format!("{}{}{}", pad(padding / 2), text, pad(padding - padding / 2)),
+        })
+    }
+}
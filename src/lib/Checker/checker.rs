@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use crate::lib::Enums::astnode::{ASTNode, MatchArm, Pattern};
+use crate::lib::Checker::constants::pattern_bindings;
+use crate::lib::Checker::diagnostic::Diagnostic;
+use crate::lib::Checker::returns::check_returns;
+use crate::lib::Enums::span::Span;
+
+/// Static checks run over the whole AST before evaluation starts.
+pub struct Checker {
+    /// Declared enums, mapping each enum name to its variants and payload types.
+    enums: HashMap<String, Vec<(String, Vec<String>)>>,
+    /// The number of loops enclosing the node being checked, within the current function.
+    loop_depth: usize,
+    /// The names bound in each enclosing scope, innermost last. A constant maps to
+    /// the span of its declaration; parameters, loop variables, match bindings and
+    /// caught errors, which may legally shadow a constant, map to `None`.
+    scopes: Vec<HashMap<String, Option<Span>>>,
+    /// The problems found so far.
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    /// Creates a new `Checker` with no known declarations.
+    pub fn new() -> Self {
+        Checker {
+            enums: HashMap::new(),
+            loop_depth: 0,
+            scopes: vec![HashMap::new()],
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Checks the program, returning every diagnostic found if it must be rejected.
+    pub fn check(mut self, ast: &[ASTNode]) -> Result<(), Vec<Diagnostic>> {
+        for node in ast {
+            self.collect_declarations(node);
+        }
+        for node in ast {
+            self.check_node(node);
+        }
+        self.diagnostics.extend(check_returns(ast));
+        if self.diagnostics.is_empty() {
+            Ok(())
+        } else {
+            Err(self.diagnostics)
+        }
+    }
+
+    /// Records every enum declared anywhere in the tree.
+    fn collect_declarations(&mut self, node: &ASTNode) {
+        if let ASTNode::EnumDeclaration { name, variants } = node {
+            if self.enums.insert(name.clone(), variants.clone()).is_some() {
+                self.diagnostics.push(Diagnostic::error(format!("Enum '{}' is declared more than once", name)));
+            }
+        }
+        for child in node.children() {
+            self.collect_declarations(child);
+        }
+    }
+
+    /// Checks a node and everything nested inside it.
+    fn check_node(&mut self, node: &ASTNode) {
+        match node {
+            ASTNode::EnumVariant { enum_name, variant, arguments, span } => {
+                self.check_variant(enum_name, variant, arguments.len(), *span);
+            }
+            ASTNode::Match { subject, arms, span } => {
+                self.check_node(subject);
+                for arm in arms {
+                    self.check_pattern(&arm.pattern);
+                    if let Some(guard) = &arm.guard {
+                        self.check_boolean(guard, "Match guard", None);
+                    }
+                    let mut names = Vec::new();
+                    pattern_bindings(&arm.pattern, &mut names);
+                    self.scopes.push(names.into_iter().map(|name| (name, None)).collect());
+                    for child in arm.guard.iter().chain(arm.body.iter()) {
+                        self.check_node(child);
+                    }
+                    self.scopes.pop();
+                }
+                self.check_exhaustive(arms, *span);
+                return;
+            }
+            ASTNode::ConstantDeclaration { name, span, .. } => {
+                if let Some(declared) = self.constant_span(name) {
+                    let diagnostic = Diagnostic::error(format!("Constant '{}' is already declared", name))
+                        .at(*span)
+                        .with_note(declared, format!("'{}' is first declared here", name));
+                    self.diagnostics.push(diagnostic);
+                }
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.insert(name.clone(), Some(*span));
+                }
+            }
+            ASTNode::VariableDeclaration { name, span, .. } => {
+                if let Some(declared) = self.constant_span(name) {
+                    let diagnostic = Diagnostic::error(format!("Cannot redeclare constant '{}' as a variable", name))
+                        .at(*span)
+                        .with_note(declared, format!("'{}' is declared as a constant here", name));
+                    self.diagnostics.push(diagnostic);
+                }
+            }
+            ASTNode::Assignment { identifier, span, .. } => self.check_write(identifier, *span),
+            ASTNode::CompoundAssignment { target, span, .. } => {
+                if let ASTNode::Identifier(name) = target.as_ref() {
+                    self.check_write(name, *span);
+                }
+            }
+            ASTNode::Conditional { branches, span, .. } => {
+                for (condition, _) in branches {
+                    self.check_boolean(condition, "Condition of 'if'", Some(*span));
+                }
+            }
+            ASTNode::BinaryOp { op, left, right, span } if op == "And" || op == "Or" => {
+                let description = if op == "And" { "Operand of '&&'" } else { "Operand of '||'" };
+                self.check_boolean(left, description, Some(*span));
+                self.check_boolean(right, description, Some(*span));
+            }
+            ASTNode::UnaryOp { op, operand, span } if op == "Not" => {
+                self.check_boolean(operand, "Operand of '!'", Some(*span));
+            }
+            ASTNode::Break { span } if self.loop_depth == 0 => {
+                self.diagnostics.push(Diagnostic::error("'break' used outside of a loop").at(*span));
+            }
+            ASTNode::Continue { span } if self.loop_depth == 0 => {
+                self.diagnostics.push(Diagnostic::error("'continue' used outside of a loop").at(*span));
+            }
+            ASTNode::Loop { .. } | ASTNode::For { .. } => {
+                if let ASTNode::Loop { condition, span, .. } = node {
+                    self.check_boolean(condition, "Condition of 'while'", Some(*span));
+                }
+                self.loop_depth += 1;
+                if let ASTNode::For { variable, iterable, body } = node {
+                    self.check_node(iterable);
+                    self.scopes.push(HashMap::from([(variable.clone(), None)]));
+                    for statement in body {
+                        self.check_node(statement);
+                    }
+                    self.scopes.pop();
+                } else {
+                    self.check_children(node);
+                }
+                self.loop_depth -= 1;
+                return;
+            }
+            ASTNode::Try { body, catch, finally } => {
+                for statement in body {
+                    self.check_node(statement);
+                }
+                if let Some((name, block)) = catch {
+                    self.scopes.push(HashMap::from([(name.clone(), None)]));
+                    for statement in block {
+                        self.check_node(statement);
+                    }
+                    self.scopes.pop();
+                }
+                for statement in finally.iter().flatten() {
+                    self.check_node(statement);
+                }
+                return;
+            }
+            ASTNode::FunctionDeclaration { parameters, .. } | ASTNode::FunctionExpression { parameters, .. } => {
+                // A loop around a function definition does not enclose its body
+                let outer_depth = std::mem::replace(&mut self.loop_depth, 0);
+                self.scopes.push(parameters.iter().map(|(_, name)| (name.clone(), None)).collect());
+                self.check_children(node);
+                self.scopes.pop();
+                self.loop_depth = outer_depth;
+                return;
+            }
+            _ => {}
+        }
+        self.check_children(node);
+    }
+
+    /// Checks everything nested directly inside a node.
+    fn check_children(&mut self, node: &ASTNode) {
+        for child in node.children() {
+            self.check_node(child);
+        }
+    }
+
+    /// Returns where `name` is declared as a constant, if the innermost binding
+    /// of that name is a constant.
+    fn constant_span(&self, name: &str) -> Option<Span> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).copied().flatten()
+    }
+
+    /// Reports an assignment at `span` if `name` refers to a constant.
+    fn check_write(&mut self, name: &str, span: Span) {
+        if let Some(declared) = self.constant_span(name) {
+            let diagnostic = Diagnostic::error(format!("Cannot assign to constant '{}'", name))
+                .at(span)
+                .with_note(declared, format!("'{}' is declared as a constant here", name));
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Reports `node` if it is used where a boolean is required but its type is
+    /// evidently something else. Values have no truthiness, so `if (1)` is an error.
+    /// The diagnostic points at the expression, or at `fallback` for expressions
+    /// without a position of their own, such as literals.
+    fn check_boolean(&mut self, node: &ASTNode, description: &str, fallback: Option<Span>) {
+        if let Some(found) = Self::non_boolean_type(node) {
+            let message = format!("{} must be a boolean, found {}", description, found);
+            let mut diagnostic = Diagnostic::error(message);
+            if let Some(span) = node.span().or(fallback) {
+                diagnostic = diagnostic.at(span);
+            }
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Returns the type of an expression whose type is known without running it
+    /// and is not boolean, or `None` if it may be boolean.
+    fn non_boolean_type(node: &ASTNode) -> Option<String> {
+        match node {
+            ASTNode::IntegerLiteral(_) => Some("integer".to_string()),
+            ASTNode::FloatLiteral(_) => Some("float".to_string()),
+            ASTNode::StringLiteral(_) | ASTNode::InterpolatedString(_) => Some("string".to_string()),
+            ASTNode::ListLiteral(_) | ASTNode::Range { .. } => Some("list".to_string()),
+            ASTNode::MapLiteral(_) => Some("map".to_string()),
+            ASTNode::StructLiteral { name, .. } => Some(name.clone()),
+            ASTNode::EnumVariant { enum_name, .. } => Some(enum_name.clone()),
+            ASTNode::FunctionExpression { .. } => Some("function".to_string()),
+            ASTNode::UnaryOp { op, .. } if op == "Minus" => Some("number".to_string()),
+            ASTNode::BinaryOp { op, .. } => match op.as_str() {
+                "Plus" | "Minus" | "Multiply" | "Divide" | "Modulo" => Some("number".to_string()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Checks that `enum_name::variant` exists and takes `arity` payload values,
+    /// reporting any problem at `span`.
+    fn check_variant(&mut self, enum_name: &str, variant: &str, arity: usize, span: Span) {
+        let variants = match self.enums.get(enum_name) {
+            Some(variants) => variants,
+            None => {
+                self.diagnostics.push(Diagnostic::error(format!("Enum '{}' not defined", enum_name)).at(span));
+                return;
+            }
+        };
+        match variants.iter().find(|(name, _)| name == variant) {
+            Some((_, payload)) if payload.len() != arity => {
+                let message = format!(
+                    "Variant '{}::{}' expects {} value(s), got {}",
+                    enum_name,
+                    variant,
+                    payload.len(),
+                    arity
+                );
+                self.diagnostics.push(Diagnostic::error(message).at(span));
+            }
+            Some(_) => {}
+            None => {
+                let message = format!("Enum '{}' has no variant '{}'", enum_name, variant);
+                self.diagnostics.push(Diagnostic::error(message).at(span));
+            }
+        }
+    }
+
+    /// Checks that every variant named in a pattern exists with the right arity.
+    fn check_pattern(&mut self, pattern: &Pattern) {
+        if let Pattern::Variant { enum_name, variant, fields, span } = pattern {
+            self.check_variant(enum_name, variant, fields.len(), *span);
+            for field in fields {
+                self.check_pattern(field);
+            }
+        }
+    }
+
+    /// Checks that the arms of a match cover every possible value of the subject.
+    ///
+    /// Only arms without a guard count towards coverage. A variant is covered by
+    /// an arm whose payload patterns are all wildcards or bindings; arms that
+    /// refine a payload further (e.g. `Shape::Circle(0)`) need a catch-all arm.
+    /// Missing cases are reported at `span`, the `match` keyword.
+    fn check_exhaustive(&mut self, arms: &[MatchArm], span: Span) {
+        let unguarded: Vec<&Pattern> = arms
+            .iter()
+            .filter(|arm| arm.guard.is_none())
+            .map(|arm| &arm.pattern)
+            .collect();
+        if unguarded.iter().any(|pattern| pattern.is_irrefutable()) {
+            return;
+        }
+
+        let enum_name = arms.iter().find_map(|arm| match &arm.pattern {
+            Pattern::Variant { enum_name, .. } => Some(enum_name.clone()),
+            _ => None,
+        });
+
+        if let Some(enum_name) = enum_name {
+            let variants = match self.enums.get(&enum_name) {
+                Some(variants) => variants,
+                None => return, // Already reported by check_pattern
+            };
+            let missing: Vec<String> = variants
+                .iter()
+                .map(|(variant, _)| variant)
+                .filter(|variant| {
+                    !unguarded.iter().any(|pattern| match pattern {
+                        Pattern::Variant { enum_name: name, variant: covered, fields, .. } => {
+                            *name == enum_name
+                                && covered == *variant
+                                && fields.iter().all(Pattern::is_irrefutable)
+                        }
+                        _ => false,
+                    })
+                })
+                .map(|variant| format!("{}::{}", enum_name, variant))
+                .collect();
+            if !missing.is_empty() {
+                let message = format!(
+                    "Non-exhaustive match on enum '{}': missing variant(s) {}",
+                    enum_name,
+                    missing.join(", ")
+                );
+                self.diagnostics.push(Diagnostic::error(message).at(span));
+            }
+            return;
+        }
+
+        if !arms.is_empty() && arms.iter().all(|arm| matches!(arm.pattern, Pattern::Boolean(_))) {
+            let missing: Vec<&str> = [true, false]
+                .iter()
+                .filter(|value| !unguarded.contains(&&Pattern::Boolean(**value)))
+                .map(|value| if *value { "true" } else { "false" })
+                .collect();
+            if !missing.is_empty() {
+                let message = format!("Non-exhaustive match on boolean: missing {}", missing.join(", "));
+                self.diagnostics.push(Diagnostic::error(message).at(span));
+            }
+            return;
+        }
+
+        self.diagnostics.push(
+            Diagnostic::error("Non-exhaustive match: add a '_' arm to cover the remaining values").at(span),
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use crate::lib::Asteva::asteva::{apply_binary_op, apply_unary_op};
+use crate::lib::Checker::diagnostic::Diagnostic;
+use crate::lib::Enums::astnode::{ASTNode, Pattern};
+use crate::lib::Enums::value::Value;
+use crate::lib::Optimizer::optimizer::literal;
+
+/// Evaluates the initialiser of every constant declaration before the program
+/// runs and replaces it with the resulting literal.
+///
+/// A constant expression is built from literals, previously declared constants,
+/// parentheses and the unary and binary operators. Anything else, such as a
+/// variable or a function call, is reported, as is an initialiser that fails to
+/// evaluate (e.g. `1 / 0`) or whose value does not match the declared type.
+pub fn fold_constants(ast: &mut [ASTNode]) -> Result<(), Vec<Diagnostic>> {
+    let mut folder = ConstantFolder {
+        constants: HashMap::new(),
+        diagnostics: Vec::new(),
+    };
+    for node in ast.iter_mut() {
+        folder.fold_node(node);
+    }
+    if folder.diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(folder.diagnostics)
+    }
+}
+
+/// Walks the tree, keeping track of which constants are visible.
+struct ConstantFolder {
+    /// The folded value of every constant visible at the current node.
+    constants: HashMap<String, Value>,
+    /// The problems found so far.
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ConstantFolder {
+    /// Folds every constant declaration in a node and the nodes nested inside it.
+    fn fold_node(&mut self, node: &mut ASTNode) {
+        match node {
+            ASTNode::ConstantDeclaration { name, value, const_type, span, .. } => {
+                let folded = match self.evaluate(value) {
+                    Ok(folded) => folded,
+                    Err(reason) => {
+                        let message = format!("Cannot evaluate constant '{}' before execution: {}", name, reason);
+                        self.diagnostics.push(Diagnostic::error(message).at(*span));
+                        return;
+                    }
+                };
+                if !folded.matches_type(const_type) {
+                    let message = format!(
+                        "Constant '{}' is declared as {} but its value is {}",
+                        name, const_type, folded.type_name()
+                    );
+                    self.diagnostics.push(Diagnostic::error(message).at(*span));
+                    return;
+                }
+                **value = literal(&folded).expect("constant expressions only produce numbers, booleans and strings");
+                self.constants.insert(name.clone(), folded);
+            }
+            // Parameters, loop variables, match bindings and caught errors hide constants of the same name
+            ASTNode::FunctionDeclaration { parameters, body, .. } | ASTNode::FunctionExpression { parameters, body, .. } => {
+                let names: Vec<String> = parameters.iter().map(|(_, name)| name.clone()).collect();
+                self.with_hidden(&names, |folder| {
+                    for statement in body.iter_mut() {
+                        folder.fold_node(statement);
+                    }
+                });
+            }
+            ASTNode::For { variable, iterable, body } => {
+                self.fold_node(iterable);
+                let names = vec![variable.clone()];
+                self.with_hidden(&names, |folder| {
+                    for statement in body.iter_mut() {
+                        folder.fold_node(statement);
+                    }
+                });
+            }
+            ASTNode::Match { subject, arms, .. } => {
+                self.fold_node(subject);
+                for arm in arms.iter_mut() {
+                    let mut names = Vec::new();
+                    pattern_bindings(&arm.pattern, &mut names);
+                    self.with_hidden(&names, |folder| {
+                        for child in arm.guard.iter_mut().chain(arm.body.iter_mut()) {
+                            folder.fold_node(child);
+                        }
+                    });
+                }
+            }
+            ASTNode::Try { body, catch, finally } => {
+                for statement in body.iter_mut() {
+                    self.fold_node(statement);
+                }
+                if let Some((name, block)) = catch {
+                    let names = vec![name.clone()];
+                    self.with_hidden(&names, |folder| {
+                        for statement in block.iter_mut() {
+                            folder.fold_node(statement);
+                        }
+                    });
+                }
+                for statement in finally.iter_mut().flatten() {
+                    self.fold_node(statement);
+                }
+            }
+            _ => {
+                for child in node.children_mut() {
+                    self.fold_node(child);
+                }
+            }
+        }
+    }
+
+    /// Runs `fold` with the given names hidden from the visible constants.
+    fn with_hidden(&mut self, names: &[String], fold: impl FnOnce(&mut Self)) {
+        let outer = self.constants.clone();
+        for name in names {
+            self.constants.remove(name);
+        }
+        fold(self);
+        self.constants = outer;
+    }
+
+    /// Evaluates a constant expression.
+    ///
+    /// # Returns
+    ///
+    /// The value of the expression, or the reason it is not a valid constant expression.
+    fn evaluate(&self, node: &ASTNode) -> Result<Value, String> {
+        match node {
+            ASTNode::IntegerLiteral(value) => Ok(Value::Integer(*value)),
+            ASTNode::FloatLiteral(value) => Ok(Value::Float(*value)),
+            ASTNode::BooleanLiteral(value) => Ok(Value::Boolean(*value)),
+            ASTNode::StringLiteral(value) => Ok(Value::String(value.clone())),
+            ASTNode::Identifier(name) => match self.constants.get(name) {
+                Some(value) => Ok(value.clone()),
+                None => Err(format!("'{}' is not a constant", name)),
+            },
+            ASTNode::UnaryOp { op, operand, .. } => {
+                apply_unary_op(op, self.evaluate(operand)?).map_err(|error| error.message)
+            }
+            ASTNode::BinaryOp { op, left, right, .. } if op == "And" || op == "Or" => {
+                match (self.evaluate(left)?, self.evaluate(right)?) {
+                    (Value::Boolean(a), Value::Boolean(b)) => {
+                        Ok(Value::Boolean(if op == "And" { a && b } else { a || b }))
+                    }
+                    _ => Err("Operands of a logical operator must be booleans".to_string()),
+                }
+            }
+            ASTNode::BinaryOp { op, left, right, .. } => {
+                apply_binary_op(op, self.evaluate(left)?, self.evaluate(right)?).map_err(|error| error.message)
+            }
+            _ => Err("the initialiser is not a constant expression".to_string()),
+        }
+    }
+}
+
+/// Collects the names bound by a match pattern.
+pub fn pattern_bindings(pattern: &Pattern, names: &mut Vec<String>) {
+    match pattern {
+        Pattern::Binding(name) => names.push(name.clone()),
+        Pattern::Variant { fields, .. } => {
+            for field in fields {
+                pattern_bindings(field, names);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt;
+use crate::lib::Enums::span::Span;
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The program is rejected.
+    Error,
+    /// The program runs, but probably not as intended.
+    Warning,
+}
+
+/// A problem found by static analysis before the program runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Whether the problem stops the program from running.
+    pub severity: Severity,
+    /// The code of the lint that reported the problem, if it came from one.
+    pub code: Option<&'static str>,
+    /// A human readable description of the problem.
+    pub message: String,
+    /// Where the problem was found, if known.
+    pub span: Option<Span>,
+    /// Related locations that help explain the problem, each with a short note.
+    pub notes: Vec<(Span, String)>,
+}
+
+impl Diagnostic {
+    /// Creates an error diagnostic with the given message.
+    pub fn error(message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: None,
+            message: message.into(),
+            span: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Creates a warning diagnostic with the given message.
+    pub fn warning(message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(message)
+        }
+    }
+
+    /// Records the code of the lint that reported the diagnostic.
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Returns true if the diagnostic stops the program from running.
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Points the diagnostic at the code that caused it.
+    pub fn at(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Adds a note pointing at a related location.
+    pub fn with_note(mut self, span: Span, note: impl Into<String>) -> Self {
+        self.notes.push((span, note.into()));
+        self
+    }
+}
+
+impl Diagnostic {
+    /// Formats the diagnostic for display, one line for the problem and one per
+    /// note, prefixing each location with the name of its file. The severity is
+    /// followed by the lint code, if any, as in `warning[unused_variable]: ...`.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - The name of every source file, indexed by `Span::file`. Files
+    ///   without a name, or missing from the list, are shown by position only.
+    pub fn render(&self, files: &[String]) -> String {
+        let location = |span: &Span| {
+            let file = files.get(span.file).filter(|file| !file.is_empty());
+            match (file, span.line) {
+                (Some(file), 0) => format!("{}: ", file),
+                (Some(file), _) => format!("{}:{}: ", file, span),
+                (None, 0) => String::new(),
+                (None, _) => format!("{}: ", span),
+            }
+        };
+        let mut label = match self.severity {
+            Severity::Error => "error".to_string(),
+            Severity::Warning => "warning".to_string(),
+        };
+        if let Some(code) = self.code {
+            label.push_str(&format!("[{}]", code));
+        }
+        let mut rendered = match &self.span {
+            Some(span) => format!("{}{}: {}", location(span), label, self.message),
+            None => format!("{}: {}", label, self.message),
+        };
+        for (span, note) in &self.notes {
+            rendered.push_str(&format!("\n{}note: {}", location(span), note));
+        }
+        rendered
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    /// Formats the diagnostic for display, one line for the problem and one per note.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(&[]))
+    }
+}
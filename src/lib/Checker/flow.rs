@@ -0,0 +1,47 @@
+use crate::lib::Enums::astnode::ASTNode;
+
+// How control flows out of statements, shared by the return checker and the
+// unreachable code lint so that both agree on which statements end a block.
+
+/// Returns true if running a block can reach its end, rather than always
+/// leaving it with a `return`, `throw`, `break` or `continue`.
+pub fn completes(block: &[ASTNode]) -> bool {
+    block.iter().all(statement_completes)
+}
+
+/// Returns true if running a statement can be followed by the next statement
+/// of its block.
+///
+/// Conditions are not evaluated, so an `if` without an `else` may always
+/// complete. The exception is `while (true)`, which only completes if its body
+/// can `break` out of it.
+pub fn statement_completes(statement: &ASTNode) -> bool {
+    match statement {
+        ASTNode::ReturnStatement { .. } | ASTNode::Throw { .. } | ASTNode::Break { .. } | ASTNode::Continue { .. } => false,
+        ASTNode::Conditional { branches, else_block: Some(else_block), .. } => {
+            branches.iter().any(|(_, block)| completes(block)) || completes(else_block)
+        }
+        ASTNode::Loop { condition, body, .. } => {
+            !matches!(**condition, ASTNode::BooleanLiteral(true)) || body.iter().any(breaks_out)
+        }
+        // The checker makes sure that one of the arms always matches
+        ASTNode::Match { arms, .. } => arms.iter().any(|arm| completes(&arm.body)),
+        ASTNode::Try { body, catch, finally } => {
+            let handled = completes(body) || catch.as_ref().is_some_and(|(_, block)| completes(block));
+            handled && finally.as_deref().is_none_or(completes)
+        }
+        _ => true,
+    }
+}
+
+/// Returns true if a statement contains a `break` that leaves the loop the
+/// statement is in, rather than a loop nested inside it.
+fn breaks_out(statement: &ASTNode) -> bool {
+    match statement {
+        ASTNode::Break { .. } => true,
+        ASTNode::Loop { .. } | ASTNode::For { .. } | ASTNode::FunctionDeclaration { .. } | ASTNode::FunctionExpression { .. } => {
+            false
+        }
+        _ => statement.children().into_iter().any(breaks_out),
+    }
+}
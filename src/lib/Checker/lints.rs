@@ -0,0 +1,414 @@
+use std::collections::{HashMap, HashSet};
+use crate::lib::Asteva::asteva::{apply_binary_op, apply_unary_op};
+use crate::lib::Checker::diagnostic::Diagnostic;
+use crate::lib::Checker::flow::statement_completes;
+use crate::lib::Enums::astnode::ASTNode;
+use crate::lib::Enums::span::Span;
+use crate::lib::Enums::value::Value;
+
+/// A kind of suspicious code the lint pass looks for. Unlike the checks of
+/// `Checker`, lints do not stop the program from running unless denied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// A statement after one that never completes, such as a `return`, `throw`,
+    /// `break` or `continue`, which can never run.
+    UnreachableCode,
+    /// A function that is never called or referred to.
+    UnusedFunction,
+    /// A variable that is assigned but never read.
+    UnusedVariable,
+    /// An `if` or `while` condition built only from literals, which always has the same value.
+    ConstantCondition,
+}
+
+impl Lint {
+    /// Every lint, in the order they are documented.
+    pub const ALL: [Lint; 4] = [
+        Lint::UnreachableCode,
+        Lint::UnusedFunction,
+        Lint::UnusedVariable,
+        Lint::ConstantCondition,
+    ];
+
+    /// Returns the stable code that names the lint in diagnostics and on the command line.
+    pub fn code(self) -> &'static str {
+        match self {
+            Lint::UnreachableCode => "unreachable_code",
+            Lint::UnusedFunction => "unused_function",
+            Lint::UnusedVariable => "unused_variable",
+            Lint::ConstantCondition => "constant_condition",
+        }
+    }
+
+    /// Returns the lint with the given code, if there is one.
+    pub fn from_code(code: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.code() == code)
+    }
+}
+
+/// What to do when a lint finds a problem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Say nothing.
+    Allow,
+    /// Report a warning and run the program anyway.
+    Warn,
+    /// Report an error and refuse to run the program.
+    Deny,
+}
+
+/// The level of every lint. Lints warn unless configured otherwise.
+#[derive(Debug, Clone)]
+pub struct LintLevels {
+    /// The lints whose level has been changed from `Level::Warn`.
+    levels: HashMap<Lint, Level>,
+}
+
+impl LintLevels {
+    /// Creates a configuration in which every lint warns.
+    pub fn new() -> Self {
+        LintLevels { levels: HashMap::new() }
+    }
+
+    /// Sets the level of a lint.
+    pub fn set(&mut self, lint: Lint, level: Level) {
+        self.levels.insert(lint, level);
+    }
+
+    /// Returns the level of a lint.
+    pub fn level(&self, lint: Lint) -> Level {
+        self.levels.get(&lint).copied().unwrap_or(Level::Warn)
+    }
+}
+
+/// Runs every lint that is not allowed over the program.
+///
+/// Lints run before constants are folded, so a condition that refers to a
+/// constant (`if (DEBUG)`) is not reported as constant.
+///
+/// # Arguments
+///
+/// * `ast` - The combined program.
+/// * `levels` - The level of each lint.
+///
+/// # Returns
+///
+/// A warning for each problem found by a lint at `Level::Warn`, and an error
+/// for each problem found by a lint at `Level::Deny`, in the order the lints
+/// are listed in `Lint::ALL`.
+pub fn lint(ast: &[ASTNode], levels: &LintLevels) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        levels,
+        diagnostics: Vec::new(),
+    };
+    linter.lint_block(ast);
+    linter.lint_functions(ast);
+    linter.lint_variables(ast);
+
+    // Keep the lints apart, so that the problems of one lint are listed together
+    let mut diagnostics = std::mem::take(&mut linter.diagnostics);
+    diagnostics.sort_by_key(|(lint, _)| Lint::ALL.iter().position(|other| other == lint));
+    diagnostics.into_iter().map(|(_, diagnostic)| diagnostic).collect()
+}
+
+/// Walks the tree, collecting what the lints report.
+struct Linter<'a> {
+    /// The level of each lint.
+    levels: &'a LintLevels,
+    /// The problems found so far, with the lint that found each one.
+    diagnostics: Vec<(Lint, Diagnostic)>,
+}
+
+impl Linter<'_> {
+    /// Records a problem found by `lint` at the severity configured for it.
+    fn report(&mut self, lint: Lint, message: String, span: Option<Span>) -> Option<&mut Diagnostic> {
+        let diagnostic = match self.levels.level(lint) {
+            Level::Allow => return None,
+            Level::Warn => Diagnostic::warning(message),
+            Level::Deny => Diagnostic::error(message),
+        };
+        let diagnostic = match span {
+            Some(span) => diagnostic.at(span),
+            None => diagnostic,
+        };
+        self.diagnostics.push((lint, diagnostic.with_code(lint.code())));
+        self.diagnostics.last_mut().map(|(_, diagnostic)| diagnostic)
+    }
+
+    /// Reports the first statement of a block that follows a statement ending
+    /// the block, then lints every statement.
+    fn lint_block(&mut self, block: &[ASTNode]) {
+        if let Some(position) = block.iter().position(|statement| !statement_completes(statement)) {
+            if let Some(unreachable) = block.get(position + 1) {
+                let terminator = &block[position];
+                let span = first_span(unreachable).or_else(|| first_span(terminator));
+                let note = format!("{} makes the rest of the block unreachable", describe(terminator));
+                if let Some(diagnostic) = self.report(Lint::UnreachableCode, "Unreachable statement".to_string(), span) {
+                    if let Some(span) = first_span(terminator) {
+                        diagnostic.notes.push((span, note));
+                    }
+                }
+            }
+        }
+        for statement in block {
+            self.lint_node(statement);
+        }
+    }
+
+    /// Lints a node and the nodes nested inside it.
+    fn lint_node(&mut self, node: &ASTNode) {
+        match node {
+            ASTNode::Conditional { branches, else_block, span } => {
+                for (condition, block) in branches {
+                    self.lint_condition(condition, "'if'", *span);
+                    self.lint_node(condition);
+                    self.lint_block(block);
+                }
+                if let Some(block) = else_block {
+                    self.lint_block(block);
+                }
+            }
+            ASTNode::Loop { condition, body, span } => {
+                // `while (true)` is how a loop that ends with `break` is written
+                if !matches!(**condition, ASTNode::BooleanLiteral(true)) {
+                    self.lint_condition(condition, "'while'", *span);
+                }
+                self.lint_node(condition);
+                self.lint_block(body);
+            }
+            ASTNode::For { iterable, body, .. } => {
+                self.lint_node(iterable);
+                self.lint_block(body);
+            }
+            ASTNode::FunctionDeclaration { body, .. } | ASTNode::FunctionExpression { body, .. } => {
+                self.lint_block(body);
+            }
+            ASTNode::Match { subject, arms, .. } => {
+                self.lint_node(subject);
+                for arm in arms {
+                    if let Some(guard) = &arm.guard {
+                        self.lint_node(guard);
+                    }
+                    self.lint_block(&arm.body);
+                }
+            }
+            ASTNode::Try { body, catch, finally } => {
+                self.lint_block(body);
+                if let Some((_, block)) = catch {
+                    self.lint_block(block);
+                }
+                if let Some(block) = finally {
+                    self.lint_block(block);
+                }
+            }
+            _ => {
+                for child in node.children() {
+                    self.lint_node(child);
+                }
+            }
+        }
+    }
+
+    /// Reports a condition whose value is decided by literals alone.
+    fn lint_condition(&mut self, condition: &ASTNode, statement: &str, span: Span) {
+        if let Some(Value::Boolean(value)) = constant_value(condition) {
+            let message = format!("Condition of {} is always {}", statement, value);
+            self.report(Lint::ConstantCondition, message, condition.span().or(Some(span)));
+        }
+    }
+
+    /// Reports every function that is declared but never called or referred to.
+    ///
+    /// Public functions are exported from their module and are not reported.
+    /// A function that only refers to itself is still unused.
+    fn lint_functions(&mut self, ast: &[ASTNode]) {
+        let mut declared = Vec::new();
+        let mut referenced = HashSet::new();
+        for node in ast {
+            collect_functions(node, &mut Vec::new(), &mut declared, &mut referenced);
+        }
+        for (name, span) in declared {
+            if !referenced.contains(&name) {
+                let message = format!("Function '{}' is never called", display_name(&name));
+                self.report(Lint::UnusedFunction, message, Some(span));
+            }
+        }
+    }
+
+    /// Reports every variable that is assigned but never read.
+    ///
+    /// Variables belong to the function they are declared in, or to the top level
+    /// of the program. A named function runs with its caller's variables visible,
+    /// so a name a function reads without declaring it counts as a read of every
+    /// variable of that name.
+    fn lint_variables(&mut self, ast: &[ASTNode]) {
+        let mut scopes = Vec::new();
+        let mut free = HashSet::new();
+        collect_scope(ast, &[], &mut scopes, &mut free);
+        for scope in scopes {
+            for (name, span) in scope.declared {
+                if !scope.read.contains(&name) && !free.contains(&name) {
+                    let message = format!("Variable '{}' is assigned but never read", display_name(&name));
+                    self.report(Lint::UnusedVariable, message, Some(span));
+                }
+            }
+        }
+    }
+}
+
+/// The variables of a function body, or of the top level of the program.
+struct VariableScope {
+    /// Each variable declared in the scope, with its first declaration.
+    declared: Vec<(String, Span)>,
+    /// Every name read in the scope, outside nested functions.
+    read: HashSet<String>,
+}
+
+/// Collects the variables declared and read in a function body, and then those
+/// of every function nested inside it.
+///
+/// # Arguments
+///
+/// * `body` - The statements of the function, or of the program.
+/// * `parameters` - The names of the function's parameters.
+/// * `scopes` - Receives the scope of the body and of every nested function.
+/// * `free` - Receives every name a function reads without declaring it.
+fn collect_scope(body: &[ASTNode], parameters: &[String], scopes: &mut Vec<VariableScope>, free: &mut HashSet<String>) {
+    let mut scope = VariableScope {
+        declared: Vec::new(),
+        read: HashSet::new(),
+    };
+    let mut functions = Vec::new();
+    for statement in body {
+        collect_reads(statement, &mut scope, &mut functions);
+    }
+    free.extend(
+        scope
+            .read
+            .iter()
+            .filter(|name| !parameters.contains(name) && !scope.declared.iter().any(|(declared, _)| declared == *name))
+            .cloned(),
+    );
+    scopes.push(scope);
+    for (parameters, body) in functions {
+        collect_scope(body, &parameters, scopes, free);
+    }
+}
+
+/// Records the variables a node declares and the names it reads, leaving the
+/// bodies of nested functions to be collected as scopes of their own.
+fn collect_reads<'a>(node: &'a ASTNode, scope: &mut VariableScope, functions: &mut Vec<(Vec<String>, &'a [ASTNode])>) {
+    match node {
+        ASTNode::Identifier(name) => {
+            scope.read.insert(name.clone());
+        }
+        // Calling a variable that holds a function reads it
+        ASTNode::FunctionCall { name, arguments, .. } => {
+            scope.read.insert(name.clone());
+            for argument in arguments {
+                collect_reads(argument, scope, functions);
+            }
+        }
+        ASTNode::VariableDeclaration { name, value, span, .. } => {
+            collect_reads(value, scope, functions);
+            if !scope.declared.iter().any(|(declared, _)| declared == name) {
+                scope.declared.push((name.clone(), *span));
+            }
+        }
+        // Updating a variable in place does not read it on behalf of the program
+        ASTNode::CompoundAssignment { target, value, .. } if matches!(**target, ASTNode::Identifier(_)) => {
+            collect_reads(value, scope, functions);
+        }
+        ASTNode::FunctionDeclaration { parameters, body, .. } | ASTNode::FunctionExpression { parameters, body, .. } => {
+            functions.push((parameters.iter().map(|(_, name)| name.clone()).collect(), body));
+        }
+        _ => {
+            for child in node.children() {
+                collect_reads(child, scope, functions);
+            }
+        }
+    }
+}
+
+/// Collects the functions declared in a node and the names it refers to.
+///
+/// # Arguments
+///
+/// * `node` - The node to search.
+/// * `enclosing` - The named functions whose bodies contain the node; a
+///   reference to one of them from inside itself does not count.
+/// * `declared` - Receives each function declaration that is not public.
+/// * `referenced` - Receives each name called or used as a value.
+fn collect_functions(
+    node: &ASTNode,
+    enclosing: &mut Vec<String>,
+    declared: &mut Vec<(String, Span)>,
+    referenced: &mut HashSet<String>,
+) {
+    match node {
+        ASTNode::FunctionDeclaration { name, body, public, span, .. } => {
+            if !public {
+                declared.push((name.clone(), *span));
+            }
+            enclosing.push(name.clone());
+            for statement in body {
+                collect_functions(statement, enclosing, declared, referenced);
+            }
+            enclosing.pop();
+            return;
+        }
+        ASTNode::FunctionCall { name, .. } | ASTNode::Identifier(name) if !enclosing.contains(name) => {
+            referenced.insert(name.clone());
+        }
+        _ => {}
+    }
+    for child in node.children() {
+        collect_functions(child, enclosing, declared, referenced);
+    }
+}
+
+/// Names a statement that ends its block, for a note.
+fn describe(statement: &ASTNode) -> &'static str {
+    match statement {
+        ASTNode::ReturnStatement { .. } => "this 'return'",
+        ASTNode::Throw { .. } => "this 'throw'",
+        ASTNode::Break { .. } => "this 'break'",
+        ASTNode::Continue { .. } => "this 'continue'",
+        ASTNode::Loop { .. } => "this endless loop",
+        ASTNode::Match { .. } => "every arm of this 'match'",
+        ASTNode::Try { .. } => "this 'try'",
+        _ => "every branch of this 'if'",
+    }
+}
+
+/// Returns the position of a node, or else of the first node nested inside it
+/// that has one.
+fn first_span(node: &ASTNode) -> Option<Span> {
+    node.span().or_else(|| node.children().into_iter().find_map(first_span))
+}
+
+/// Returns the value of an expression built only from literals and operators,
+/// or `None` if it depends on anything else or fails to evaluate.
+fn constant_value(node: &ASTNode) -> Option<Value> {
+    match node {
+        ASTNode::IntegerLiteral(value) => Some(Value::Integer(*value)),
+        ASTNode::FloatLiteral(value) => Some(Value::Float(*value)),
+        ASTNode::BooleanLiteral(value) => Some(Value::Boolean(*value)),
+        ASTNode::StringLiteral(value) => Some(Value::String(value.clone())),
+        ASTNode::UnaryOp { op, operand, .. } => apply_unary_op(op, constant_value(operand)?).ok(),
+        ASTNode::BinaryOp { op, left, right, .. } if op == "And" || op == "Or" => {
+            match (constant_value(left)?, constant_value(right)?) {
+                (Value::Boolean(a), Value::Boolean(b)) => Some(Value::Boolean(if op == "And" { a && b } else { a || b })),
+                _ => None,
+            }
+        }
+        ASTNode::BinaryOp { op, left, right, .. } => apply_binary_op(op, constant_value(left)?, constant_value(right)?).ok(),
+        _ => None,
+    }
+}
+
+/// Returns a name as written in its module: the module loader renames the
+/// top-level items of a module to `module::name`.
+pub fn display_name(name: &str) -> &str {
+    name.rsplit("::").next().unwrap_or(name)
+}
@@ -0,0 +1,6 @@
+pub mod checker;
+pub mod constants;
+pub mod diagnostic;
+pub mod flow;
+pub mod lints;
+pub mod returns;
\ No newline at end of file
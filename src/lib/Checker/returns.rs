@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use crate::lib::Checker::diagnostic::Diagnostic;
+use crate::lib::Checker::flow::completes;
+use crate::lib::Checker::lints::display_name;
+use crate::lib::Enums::astnode::ASTNode;
+use crate::lib::Enums::span::Span;
+
+/// Checks every function's `return` statements against its declared return type.
+///
+/// * A function with a return type other than `void` must end every path
+///   through its body with a `return` of a value, or a `throw`, so it can never
+///   fall off the end and produce whatever its last statement happened to.
+/// * A `void` function may only use a bare `return;`.
+/// * A returned expression whose type is evident without running it, such as a
+///   literal, an operator or a call of a declared function, must have the
+///   declared type.
+///
+/// # Returns
+///
+/// A diagnostic for each problem found.
+pub fn check_returns(ast: &[ASTNode]) -> Vec<Diagnostic> {
+    let mut checker = ReturnChecker {
+        functions: HashMap::new(),
+        diagnostics: Vec::new(),
+    };
+    for node in ast {
+        checker.collect_functions(node);
+    }
+    for node in ast {
+        checker.check_node(node);
+    }
+    checker.diagnostics
+}
+
+/// Walks the tree, checking each function it finds.
+struct ReturnChecker {
+    /// The declared return type of every named function, or `None` for a name
+    /// declared more than once with different types.
+    functions: HashMap<String, Option<String>>,
+    /// The problems found so far.
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ReturnChecker {
+    /// Records the return type of every function declared anywhere in the tree.
+    fn collect_functions(&mut self, node: &ASTNode) {
+        if let ASTNode::FunctionDeclaration { name, return_type, .. } = node {
+            self.functions
+                .entry(name.clone())
+                .and_modify(|declared| {
+                    if declared.as_ref() != Some(return_type) {
+                        *declared = None;
+                    }
+                })
+                .or_insert_with(|| Some(return_type.clone()));
+        }
+        for child in node.children() {
+            self.collect_functions(child);
+        }
+    }
+
+    /// Checks every function in a node and the nodes nested inside it.
+    fn check_node(&mut self, node: &ASTNode) {
+        match node {
+            ASTNode::FunctionDeclaration { name, return_type, body, span, .. } => {
+                let description = format!("Function '{}'", display_name(name));
+                self.check_function(&description, return_type, body, *span);
+            }
+            ASTNode::FunctionExpression { return_type, body, span, .. } => {
+                self.check_function("Anonymous function", return_type, body, *span);
+            }
+            _ => {}
+        }
+        for child in node.children() {
+            self.check_node(child);
+        }
+    }
+
+    /// Checks the returns of one function.
+    ///
+    /// # Arguments
+    ///
+    /// * `description` - How the function is named in messages.
+    /// * `return_type` - The declared return type.
+    /// * `body` - The statements of the function.
+    /// * `span` - The position of the declaration.
+    fn check_function(&mut self, description: &str, return_type: &str, body: &[ASTNode], span: Span) {
+        let mut returns = Vec::new();
+        for statement in body {
+            collect_returns(statement, &mut returns);
+        }
+        let void = return_type == "void";
+        for (value, span) in returns {
+            let message = match value {
+                Some(_) if void => format!("{} is declared void and cannot return a value", description),
+                None if !void => format!("{} must return a value of type {}", description, return_type),
+                Some(value) => match self.static_type(value) {
+                    Some(found) if !conforms(&found, return_type) => {
+                        format!("{} is declared to return {}, but returns {}", description, return_type, found)
+                    }
+                    _ => continue,
+                },
+                None => continue,
+            };
+            self.diagnostics.push(Diagnostic::error(message).at(span));
+        }
+        if !void && completes(body) {
+            let message = format!(
+                "{} must return {}, but can reach the end of its body without returning",
+                description, return_type
+            );
+            self.diagnostics.push(Diagnostic::error(message).at(span));
+        }
+    }
+
+    /// Returns the type of an expression if it can be told without running it.
+    fn static_type(&self, node: &ASTNode) -> Option<String> {
+        let known = |name: &str| Some(name.to_string());
+        match node {
+            ASTNode::IntegerLiteral(_) => known("integer"),
+            ASTNode::FloatLiteral(_) => known("float"),
+            ASTNode::BooleanLiteral(_) => known("boolean"),
+            ASTNode::StringLiteral(_) | ASTNode::InterpolatedString(_) => known("string"),
+            ASTNode::ListLiteral(_) => known("list"),
+            ASTNode::MapLiteral(_) => known("map"),
+            ASTNode::StructLiteral { name, .. } => Some(name.clone()),
+            ASTNode::EnumVariant { enum_name, .. } => Some(enum_name.clone()),
+            ASTNode::FunctionExpression { .. } => known("function"),
+            ASTNode::FunctionCall { name, .. } => self.functions.get(name).cloned().flatten(),
+            ASTNode::UnaryOp { op, operand, .. } => match op.as_str() {
+                "Not" => known("boolean"),
+                _ => self.static_type(operand).filter(|found| found == "integer" || found == "float"),
+            },
+            ASTNode::BinaryOp { op, left, right, .. } => match op.as_str() {
+                "Plus" | "Minus" | "Multiply" | "Divide" | "Modulo" => {
+                    match (self.static_type(left)?.as_str(), self.static_type(right)?.as_str()) {
+                        ("integer", "integer") => known("integer"),
+                        ("float", "integer" | "float") | ("integer", "float") => known("float"),
+                        _ => None,
+                    }
+                }
+                _ => known("boolean"),
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Returns true if a value whose type is `found`, as told by `static_type`,
+/// conforms to the declared type. Collections and functions are only known
+/// by their kind, not by their element or parameter types.
+fn conforms(found: &str, declared: &str) -> bool {
+    match found {
+        "list" => declared.starts_with("list<"),
+        "map" => declared.starts_with("map<"),
+        "function" => declared.starts_with("function("),
+        found => found == declared,
+    }
+}
+
+/// Collects the value and position of every `return` in a node that belongs to
+/// the function being checked, leaving out those of nested functions.
+fn collect_returns<'a>(node: &'a ASTNode, returns: &mut Vec<(Option<&'a ASTNode>, Span)>) {
+    match node {
+        ASTNode::ReturnStatement { value, span } => returns.push((value.as_deref(), *span)),
+        ASTNode::FunctionDeclaration { .. } | ASTNode::FunctionExpression { .. } => return,
+        _ => {}
+    }
+    for child in node.children() {
+        collect_returns(child, returns);
+    }
+}
@@ -1,14 +1,34 @@
+use crate::lib::Enums::span::Span;
+
 /// Represents a node in the abstract syntax tree (AST).
 #[derive(Debug)]
 pub enum ASTNode {
     /// An integer literal node.
     IntegerLiteral(i32),
+    /// A float literal node.
+    FloatLiteral(f64),
     /// A boolean literal node.
     BooleanLiteral(bool),
     /// A string literal node.
     StringLiteral(String),
     /// An identifier node.
     Identifier(String),
+    /// A list literal node (`[1, 2, 3]`).
+    ListLiteral(Vec<ASTNode>),
+    /// An interpolated string literal (`"total: ${a + b}"`), holding its text
+    /// segments as string literals and its embedded expressions, in order.
+    InterpolatedString(Vec<ASTNode>),
+    /// A map literal node (`{ "a": 1 }`), holding key and value expressions in order.
+    MapLiteral(Vec<(ASTNode, ASTNode)>),
+    /// An indexing node (`xs[i]` or `m["key"]`).
+    Index {
+        /// The expression being indexed.
+        target: Box<ASTNode>,
+        /// The index expression.
+        index: Box<ASTNode>,
+        /// The position of the opening bracket.
+        span: Span,
+    },
     /// A binary operation node.
     BinaryOp {
         /// The operator.
@@ -17,6 +37,17 @@ pub enum ASTNode {
         left: Box<ASTNode>,
         /// The right operand.
         right: Box<ASTNode>,
+        /// The position of the operator.
+        span: Span,
+    },
+    /// A unary operation node (`-x` or `!done`).
+    UnaryOp {
+        /// The operator ("Minus" or "Not").
+        op: String,
+        /// The operand.
+        operand: Box<ASTNode>,
+        /// The position of the operator.
+        span: Span,
     },
     /// A variable declaration node.
     VariableDeclaration {
@@ -26,22 +57,80 @@ pub enum ASTNode {
         value: Box<ASTNode>,
         /// The variable type.
         var_type: String,
+        /// Where the declaration starts in the source.
+        span: Span,
+    },
+    /// A constant declaration node (`constant integer MAX = 10 * 10;`). The
+    /// initialiser must be a constant expression and is folded before the
+    /// program runs.
+    ConstantDeclaration {
+        /// The constant name.
+        name: String,
+        /// The initialiser; a literal once constants have been folded.
+        value: Box<ASTNode>,
+        /// The constant type.
+        const_type: String,
+        /// Whether the constant is declared `public` and so exported from its module.
+        public: bool,
+        /// Where the declaration starts in the source.
+        span: Span,
     },
-    /// A conditional node (if statement).
+    /// An import of another file (`import "math.known";` or
+    /// `import util::strings as s;`). Imports are resolved by the module loader
+    /// before the program runs, and do not remain in the tree it produces.
+    Import {
+        /// The path of the imported file, relative to the importing file.
+        path: String,
+        /// The namespace through which the module's public items are used (`s::trim`).
+        alias: String,
+        /// Where the import starts in the source.
+        span: Span,
+    },
+    /// A conditional node: `if (c) { } else if (c2) { } else { }`. Used both as
+    /// a statement and as an expression, whose value is that of the block taken.
     Conditional {
-        /// The condition.
-        condition: Box<ASTNode>,
-        /// The block of code to execute if the condition is true.
-        if_block: Vec<ASTNode>,
-        /// The block of code to execute if the condition is false (optional).
+        /// The condition and block of the `if` and of each `else if`, tried in order.
+        branches: Vec<(ASTNode, Vec<ASTNode>)>,
+        /// The block of code to execute if no condition holds (optional).
         else_block: Option<Vec<ASTNode>>,
+        /// The position of the first `if` keyword.
+        span: Span,
     },
-    /// A loop node (while loop).
+    /// A loop node (`while (cond) { }`, or the older `loop (cond) { }` spelling).
     Loop {
         /// The loop condition.
         condition: Box<ASTNode>,
         /// The body of the loop.
         body: Vec<ASTNode>,
+        /// The position of the `while` or `loop` keyword.
+        span: Span,
+    },
+    /// A for loop node (`for i in 0..n { }` or `for x in xs { }`).
+    For {
+        /// The loop variable.
+        variable: String,
+        /// The values iterated over: a range, a list, or a map (whose keys are visited).
+        iterable: Box<ASTNode>,
+        /// The body of the loop.
+        body: Vec<ASTNode>,
+    },
+    /// A half-open integer range node (`start..end`), which only appears as the
+    /// iterable of a `for` loop.
+    Range {
+        /// The first value of the range.
+        start: Box<ASTNode>,
+        /// The value one past the last value of the range.
+        end: Box<ASTNode>,
+    },
+    /// A break statement node.
+    Break {
+        /// The position of the `break` keyword.
+        span: Span,
+    },
+    /// A continue statement node.
+    Continue {
+        /// The position of the `continue` keyword.
+        span: Span,
     },
     /// A function declaration node.
     FunctionDeclaration {
@@ -53,100 +142,564 @@ pub enum ASTNode {
         parameters: Vec<(String, String)>,
         /// The body of the function.
         body: Vec<ASTNode>,
+        /// Whether the function is declared `public` and so exported from its module.
+        public: bool,
+        /// The position of the `function` keyword.
+        span: Span,
+    },
+    /// An anonymous function expression (`function(integer a) -> integer { ... }`).
+    FunctionExpression {
+        /// The return type of the function.
+        return_type: String,
+        /// The parameters of the function (type and name).
+        parameters: Vec<(String, String)>,
+        /// The body of the function.
+        body: Vec<ASTNode>,
+        /// The position of the `function` keyword.
+        span: Span,
     },
     /// A function call node.
     FunctionCall {
         /// The name of the function to call.
         name: String,
         /// The arguments to pass to the function.
-        arguments: Vec<Box<ASTNode>>,
+        arguments: Vec<ASTNode>,
+        /// The position of the opening parenthesis.
+        span: Span,
+    },
+    /// A call of the value of an arbitrary expression (`make_adder(1)(2)`).
+    CallExpression {
+        /// The expression producing the function to call.
+        callee: Box<ASTNode>,
+        /// The arguments to pass to the function.
+        arguments: Vec<ASTNode>,
+        /// The position of the opening parenthesis.
+        span: Span,
     },
     /// A return statement node.
-    ReturnStatement(Box<ASTNode>),
+    ReturnStatement {
+        /// The returned value, or `None` for a bare `return;` from a `void` function.
+        value: Option<Box<ASTNode>>,
+        /// The position of the `return` keyword.
+        span: Span,
+    },
     /// An assignment node.
     Assignment {
         /// The identifier (variable) being assigned to.
         identifier: String,
         /// The value being assigned.
         value: Box<ASTNode>,
+        /// Where the assignment starts in the source.
+        span: Span,
     },
-    /// An if-else node.
-    IfElse {
-        /// The condition.
-        condition: Box<ASTNode>,
-        /// The block of code to execute if the condition is true.
-        if_block: Vec<ASTNode>,
-        /// The block of code to execute if the condition is false (optional).
-        else_block: Option<Vec<ASTNode>>,
+    /// A compound assignment node (`x += v`, `xs[i] *= v`, `p.x -= v`), which
+    /// evaluates its target only once. `x++` and `x--` are parsed into this node
+    /// with a value of 1.
+    CompoundAssignment {
+        /// The variable, indexed element or field being updated.
+        target: Box<ASTNode>,
+        /// The name of the binary operator applied (e.g. "Plus").
+        op: String,
+        /// The right-hand operand.
+        value: Box<ASTNode>,
+        /// Where the assignment starts in the source.
+        span: Span,
     },
-    /// An else statement node.
-    ElseStatement {
-        /// The body of the else statement.
-        body: Vec<ASTNode>,
+    /// A struct declaration node (`struct Point { integer x; integer y; }`).
+    StructDeclaration {
+        /// The struct name.
+        name: String,
+        /// The fields of the struct (type and name), in declaration order.
+        fields: Vec<(String, String)>,
+    },
+    /// A struct construction node (`Point { x: 1, y: 2 }`).
+    StructLiteral {
+        /// The name of the struct being constructed.
+        name: String,
+        /// The field initialisers (name and value expression).
+        fields: Vec<(String, ASTNode)>,
+        /// The position of the struct name.
+        span: Span,
+    },
+    /// A field access node (`p.x`).
+    FieldAccess {
+        /// The expression whose field is read.
+        target: Box<ASTNode>,
+        /// The field name.
+        field: String,
+        /// The position of the dot.
+        span: Span,
+    },
+    /// A field assignment node (`p.x = v`).
+    FieldAssignment {
+        /// The expression whose field is written.
+        target: Box<ASTNode>,
+        /// The field name.
+        field: String,
+        /// The value being assigned.
+        value: Box<ASTNode>,
+        /// The position of the dot.
+        span: Span,
+    },
+    /// An enum declaration node (`enum Shape { Circle(integer), Rect(integer, integer) }`).
+    EnumDeclaration {
+        /// The enum name.
+        name: String,
+        /// The variants of the enum (name and payload types), in declaration order.
+        variants: Vec<(String, Vec<String>)>,
+    },
+    /// An enum variant construction node (`Shape::Circle(5)`).
+    EnumVariant {
+        /// The name of the enum.
+        enum_name: String,
+        /// The name of the variant.
+        variant: String,
+        /// The payload expressions.
+        arguments: Vec<ASTNode>,
+        /// The position of the enum name.
+        span: Span,
+    },
+    /// A match node, which evaluates the body of the first arm whose pattern
+    /// matches the subject and whose guard (if any) holds.
+    Match {
+        /// The value being matched.
+        subject: Box<ASTNode>,
+        /// The arms, tried in order.
+        arms: Vec<MatchArm>,
+        /// The position of the `match` keyword.
+        span: Span,
     },
-    /// A print statement node.
+    /// An indexed assignment node (`xs[i] = v` or `m["key"] = v`).
+    IndexAssignment {
+        /// The expression being indexed.
+        target: Box<ASTNode>,
+        /// The index expression.
+        index: Box<ASTNode>,
+        /// The value being assigned.
+        value: Box<ASTNode>,
+    },
+    /// A print statement node (`print a, b;`).
     PrintStatement {
-        /// The context to print.
-        context: Box<ASTNode>,
+        /// The values to print, separated by spaces.
+        arguments: Vec<ASTNode>,
+        /// The position of the `print` keyword.
+        span: Span,
+    },
+    /// A `throw value;` statement, which raises `value` as a runtime error.
+    Throw {
+        /// The value to raise.
+        value: Box<ASTNode>,
+        /// The position of the `throw` keyword.
+        span: Span,
+    },
+    /// A `try { } catch (e) { } finally { }` statement. At least one of the
+    /// `catch` and `finally` blocks is present.
+    Try {
+        /// The statements whose runtime errors are handled.
+        body: Vec<ASTNode>,
+        /// The name bound to the error and the statements that handle it.
+        catch: Option<(String, Vec<ASTNode>)>,
+        /// The statements run after the others, whether or not an error occurred.
+        finally: Option<Vec<ASTNode>>,
     },
     /// A no-operation node (empty statement).
     NoOp,
 }
 
+/// A single arm of a match node.
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    /// The pattern the subject is tested against.
+    pub pattern: Pattern,
+    /// An optional guard that must evaluate to true for the arm to be taken.
+    pub guard: Option<ASTNode>,
+    /// The body evaluated when the arm is taken.
+    pub body: Vec<ASTNode>,
+}
+
+/// A pattern in a match arm.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// `_`, which matches any value.
+    Wildcard,
+    /// A name, which matches any value and binds it.
+    Binding(String),
+    /// An integer literal pattern.
+    Integer(i32),
+    /// A boolean literal pattern.
+    Boolean(bool),
+    /// A string literal pattern.
+    String(String),
+    /// An enum variant pattern with patterns for its payload (`Shape::Rect(w, _)`).
+    Variant {
+        /// The name of the enum.
+        enum_name: String,
+        /// The name of the variant.
+        variant: String,
+        /// The patterns for the payload values.
+        fields: Vec<Pattern>,
+        /// The position of the enum name.
+        span: Span,
+    },
+}
+
+impl Pattern {
+    /// Returns true if the pattern matches every value (a wildcard or a binding).
+    pub fn is_irrefutable(&self) -> bool {
+        matches!(self, Pattern::Wildcard | Pattern::Binding(_))
+    }
+}
+
 impl Clone for ASTNode {
     /// Clones the ASTNode.
     fn clone(&self) -> Self {
         match self {
             ASTNode::IntegerLiteral(value) => ASTNode::IntegerLiteral(*value),
+            ASTNode::FloatLiteral(value) => ASTNode::FloatLiteral(*value),
             ASTNode::BooleanLiteral(value) => ASTNode::BooleanLiteral(*value),
             ASTNode::StringLiteral(value) => ASTNode::StringLiteral(value.clone()),
             ASTNode::Identifier(name) => ASTNode::Identifier(name.clone()),
-            ASTNode::BinaryOp { op, left, right } => ASTNode::BinaryOp {
+            ASTNode::ListLiteral(elements) => ASTNode::ListLiteral(elements.clone()),
+            ASTNode::InterpolatedString(parts) => ASTNode::InterpolatedString(parts.clone()),
+            ASTNode::MapLiteral(entries) => ASTNode::MapLiteral(entries.clone()),
+            ASTNode::Index { target, index, span } => ASTNode::Index {
+                target: Box::new((**target).clone()),
+                index: Box::new((**index).clone()),
+                span: *span,
+            },
+            ASTNode::UnaryOp { op, operand, span } => ASTNode::UnaryOp {
+                op: op.clone(),
+                operand: Box::new((**operand).clone()),
+                span: *span,
+            },
+            ASTNode::BinaryOp { op, left, right, span } => ASTNode::BinaryOp {
                 op: op.clone(),
                 left: Box::new((**left).clone()),
                 right: Box::new((**right).clone()),
+                span: *span,
             },
-            ASTNode::VariableDeclaration { name, value, var_type } => ASTNode::VariableDeclaration {
+            ASTNode::VariableDeclaration { name, value, var_type, span } => ASTNode::VariableDeclaration {
                 name: name.clone(),
                 value: Box::new((**value).clone()),
                 var_type: var_type.clone(),
+                span: *span,
             },
-            ASTNode::Conditional { condition, if_block, else_block } => ASTNode::Conditional {
-                condition: Box::new((**condition).clone()),
-                if_block: if_block.clone(),
-                else_block: else_block.clone().map_or(None, |block| Some(block.clone())),
+            ASTNode::ConstantDeclaration { name, value, const_type, public, span } => ASTNode::ConstantDeclaration {
+                name: name.clone(),
+                value: Box::new((**value).clone()),
+                const_type: const_type.clone(),
+                public: *public,
+                span: *span,
+            },
+            ASTNode::Import { path, alias, span } => ASTNode::Import {
+                path: path.clone(),
+                alias: alias.clone(),
+                span: *span,
+            },
+            ASTNode::Conditional { branches, else_block, span } => ASTNode::Conditional {
+                branches: branches.clone(),
+                else_block: else_block.clone(),
+                span: *span,
             },
-            ASTNode::Loop { condition, body } => ASTNode::Loop {
+            ASTNode::Loop { condition, body, span } => ASTNode::Loop {
                 condition: Box::new((**condition).clone()),
                 body: body.clone(),
+                span: *span,
+            },
+            ASTNode::For { variable, iterable, body } => ASTNode::For {
+                variable: variable.clone(),
+                iterable: Box::new((**iterable).clone()),
+                body: body.clone(),
+            },
+            ASTNode::Range { start, end } => ASTNode::Range {
+                start: Box::new((**start).clone()),
+                end: Box::new((**end).clone()),
             },
-            ASTNode::FunctionDeclaration { name, return_type, parameters, body } => ASTNode::FunctionDeclaration {
+            ASTNode::Break { span } => ASTNode::Break { span: *span },
+            ASTNode::Continue { span } => ASTNode::Continue { span: *span },
+            ASTNode::FunctionDeclaration { name, return_type, parameters, body, public, span } => ASTNode::FunctionDeclaration {
                 name: name.clone(),
                 return_type: return_type.clone(),
                 parameters: parameters.clone(),
                 body: body.clone(),
+                public: *public,
+                span: *span,
             },
-            ASTNode::FunctionCall { name, arguments } => ASTNode::FunctionCall {
+            ASTNode::FunctionExpression { return_type, parameters, body, span } => ASTNode::FunctionExpression {
+                return_type: return_type.clone(),
+                parameters: parameters.clone(),
+                body: body.clone(),
+                span: *span,
+            },
+            ASTNode::FunctionCall { name, arguments, span } => ASTNode::FunctionCall {
                 name: name.clone(),
-                arguments: arguments.iter().map(|arg| arg.clone()).collect(),
+                arguments: arguments.to_vec(),
+                span: *span,
             },
-            ASTNode::ReturnStatement(value) => ASTNode::ReturnStatement(Box::new((**value).clone())),
-            ASTNode::Assignment { identifier, value } => ASTNode::Assignment {
+            ASTNode::CallExpression { callee, arguments, span } => ASTNode::CallExpression {
+                callee: Box::new((**callee).clone()),
+                arguments: arguments.clone(),
+                span: *span,
+            },
+            ASTNode::ReturnStatement { value, span } => ASTNode::ReturnStatement {
+                value: value.as_ref().map(|value| Box::new((**value).clone())),
+                span: *span,
+            },
+            ASTNode::Assignment { identifier, value, span } => ASTNode::Assignment {
                 identifier: identifier.clone(),
                 value: Box::new((**value).clone()),
+                span: *span,
             },
-            ASTNode::IfElse { condition, if_block, else_block } => ASTNode::IfElse {
-                condition: Box::new((**condition).clone()),
-                if_block: if_block.clone(),
-                else_block: else_block.clone().map_or(None, |block| Some(block.clone())),
+            ASTNode::CompoundAssignment { target, op, value, span } => ASTNode::CompoundAssignment {
+                target: Box::new((**target).clone()),
+                op: op.clone(),
+                value: Box::new((**value).clone()),
+                span: *span,
             },
-            ASTNode::ElseStatement { body } => ASTNode::ElseStatement {
-                body: body.clone(),
+            ASTNode::IndexAssignment { target, index, value } => ASTNode::IndexAssignment {
+                target: Box::new((**target).clone()),
+                index: Box::new((**index).clone()),
+                value: Box::new((**value).clone()),
+            },
+            ASTNode::StructDeclaration { name, fields } => ASTNode::StructDeclaration {
+                name: name.clone(),
+                fields: fields.clone(),
             },
-            ASTNode::PrintStatement { context } => ASTNode::PrintStatement {
-                context: Box::new((**context).clone()),
+            ASTNode::StructLiteral { name, fields, span } => ASTNode::StructLiteral {
+                name: name.clone(),
+                fields: fields.clone(),
+                span: *span,
+            },
+            ASTNode::FieldAccess { target, field, span } => ASTNode::FieldAccess {
+                target: Box::new((**target).clone()),
+                field: field.clone(),
+                span: *span,
+            },
+            ASTNode::FieldAssignment { target, field, value, span } => ASTNode::FieldAssignment {
+                target: Box::new((**target).clone()),
+                field: field.clone(),
+                value: Box::new((**value).clone()),
+                span: *span,
+            },
+            ASTNode::EnumDeclaration { name, variants } => ASTNode::EnumDeclaration {
+                name: name.clone(),
+                variants: variants.clone(),
+            },
+            ASTNode::EnumVariant { enum_name, variant, arguments, span } => ASTNode::EnumVariant {
+                enum_name: enum_name.clone(),
+                variant: variant.clone(),
+                arguments: arguments.clone(),
+                span: *span,
+            },
+            ASTNode::Match { subject, arms, span } => ASTNode::Match {
+                subject: Box::new((**subject).clone()),
+                arms: arms.clone(),
+                span: *span,
+            },
+            ASTNode::PrintStatement { arguments, span } => ASTNode::PrintStatement {
+                arguments: arguments.clone(),
+                span: *span,
+            },
+            ASTNode::Throw { value, span } => ASTNode::Throw {
+                value: Box::new((**value).clone()),
+                span: *span,
+            },
+            ASTNode::Try { body, catch, finally } => ASTNode::Try {
+                body: body.clone(),
+                catch: catch.clone(),
+                finally: finally.clone(),
             },
             ASTNode::NoOp => ASTNode::NoOp,
         }
     }
 }
+
+impl ASTNode {
+    /// Returns the nodes directly nested inside this node, in source order.
+    /// Analysis passes use this to walk the whole tree.
+    pub fn children(&self) -> Vec<&ASTNode> {
+        match self {
+            ASTNode::IntegerLiteral(_)
+            | ASTNode::FloatLiteral(_)
+            | ASTNode::BooleanLiteral(_)
+            | ASTNode::StringLiteral(_)
+            | ASTNode::Identifier(_)
+            | ASTNode::StructDeclaration { .. }
+            | ASTNode::EnumDeclaration { .. }
+            | ASTNode::Import { .. }
+            | ASTNode::Break { .. }
+            | ASTNode::Continue { .. }
+            | ASTNode::NoOp => Vec::new(),
+            ASTNode::ListLiteral(elements) | ASTNode::InterpolatedString(elements) => elements.iter().collect(),
+            ASTNode::MapLiteral(entries) => entries.iter().flat_map(|(key, value)| [key, value]).collect(),
+            ASTNode::Index { target, index, .. } => vec![target, index],
+            ASTNode::BinaryOp { left, right, .. } => vec![left, right],
+            ASTNode::UnaryOp { operand, .. } => vec![operand],
+            ASTNode::VariableDeclaration { value, .. } => vec![value],
+            ASTNode::ConstantDeclaration { value, .. } => vec![value],
+            ASTNode::Conditional { branches, else_block, .. } => {
+                let mut children = Vec::new();
+                for (condition, block) in branches {
+                    children.push(condition);
+                    children.extend(block);
+                }
+                children.extend(else_block.iter().flatten());
+                children
+            }
+            ASTNode::Loop { condition, body, .. } => {
+                let mut children = vec![&**condition];
+                children.extend(body);
+                children
+            }
+            ASTNode::For { iterable, body, .. } => {
+                let mut children = vec![&**iterable];
+                children.extend(body);
+                children
+            }
+            ASTNode::Range { start, end } => vec![start, end],
+            ASTNode::FunctionDeclaration { body, .. } | ASTNode::FunctionExpression { body, .. } => {
+                body.iter().collect()
+            }
+            ASTNode::FunctionCall { arguments, .. } => arguments.iter().collect(),
+            ASTNode::CallExpression { callee, arguments, .. } => {
+                let mut children = vec![&**callee];
+                children.extend(arguments);
+                children
+            }
+            ASTNode::ReturnStatement { value, .. } => value.iter().map(|value| &**value).collect(),
+            ASTNode::Assignment { value, .. } => vec![value],
+            ASTNode::CompoundAssignment { target, value, .. } => vec![target, value],
+            ASTNode::StructLiteral { fields, .. } => fields.iter().map(|(_, value)| value).collect(),
+            ASTNode::FieldAccess { target, .. } => vec![target],
+            ASTNode::FieldAssignment { target, value, .. } => vec![target, value],
+            ASTNode::EnumVariant { arguments, .. } => arguments.iter().collect(),
+            ASTNode::Match { subject, arms, .. } => {
+                let mut children = vec![&**subject];
+                for arm in arms {
+                    children.extend(arm.guard.iter());
+                    children.extend(arm.body.iter());
+                }
+                children
+            }
+            ASTNode::IndexAssignment { target, index, value } => vec![target, index, value],
+            ASTNode::PrintStatement { arguments, .. } => arguments.iter().collect(),
+            ASTNode::Throw { value, .. } => vec![value],
+            ASTNode::Try { body, catch, finally } => {
+                let mut children: Vec<&ASTNode> = body.iter().collect();
+                children.extend(catch.iter().flat_map(|(_, block)| block));
+                children.extend(finally.iter().flatten());
+                children
+            }
+        }
+    }
+
+    /// Returns mutable references to the nodes nested directly inside this one,
+    /// in the same order as `children`.
+    pub fn children_mut(&mut self) -> Vec<&mut ASTNode> {
+        match self {
+            ASTNode::IntegerLiteral(_)
+            | ASTNode::FloatLiteral(_)
+            | ASTNode::BooleanLiteral(_)
+            | ASTNode::StringLiteral(_)
+            | ASTNode::Identifier(_)
+            | ASTNode::StructDeclaration { .. }
+            | ASTNode::EnumDeclaration { .. }
+            | ASTNode::Import { .. }
+            | ASTNode::Break { .. }
+            | ASTNode::Continue { .. }
+            | ASTNode::NoOp => Vec::new(),
+            ASTNode::ListLiteral(elements) | ASTNode::InterpolatedString(elements) => elements.iter_mut().collect(),
+            ASTNode::MapLiteral(entries) => entries.iter_mut().flat_map(|(key, value)| [key, value]).collect(),
+            ASTNode::Index { target, index, .. } => vec![target, index],
+            ASTNode::BinaryOp { left, right, .. } => vec![left, right],
+            ASTNode::UnaryOp { operand, .. } => vec![operand],
+            ASTNode::VariableDeclaration { value, .. } => vec![value],
+            ASTNode::ConstantDeclaration { value, .. } => vec![value],
+            ASTNode::Conditional { branches, else_block, .. } => {
+                let mut children = Vec::new();
+                for (condition, block) in branches {
+                    children.push(condition);
+                    children.extend(block);
+                }
+                children.extend(else_block.iter_mut().flatten());
+                children
+            }
+            ASTNode::Loop { condition, body, .. } => {
+                let mut children = vec![&mut **condition];
+                children.extend(body);
+                children
+            }
+            ASTNode::For { iterable, body, .. } => {
+                let mut children = vec![&mut **iterable];
+                children.extend(body);
+                children
+            }
+            ASTNode::Range { start, end } => vec![start, end],
+            ASTNode::FunctionDeclaration { body, .. } | ASTNode::FunctionExpression { body, .. } => {
+                body.iter_mut().collect()
+            }
+            ASTNode::FunctionCall { arguments, .. } => arguments.iter_mut().collect(),
+            ASTNode::CallExpression { callee, arguments, .. } => {
+                let mut children = vec![&mut **callee];
+                children.extend(arguments);
+                children
+            }
+            ASTNode::ReturnStatement { value, .. } => value.iter_mut().map(|value| &mut **value).collect(),
+            ASTNode::Assignment { value, .. } => vec![value],
+            ASTNode::CompoundAssignment { target, value, .. } => vec![target, value],
+            ASTNode::StructLiteral { fields, .. } => fields.iter_mut().map(|(_, value)| value).collect(),
+            ASTNode::FieldAccess { target, .. } => vec![target],
+            ASTNode::FieldAssignment { target, value, .. } => vec![target, value],
+            ASTNode::EnumVariant { arguments, .. } => arguments.iter_mut().collect(),
+            ASTNode::Match { subject, arms, .. } => {
+                let mut children = vec![&mut **subject];
+                for arm in arms.iter_mut() {
+                    children.extend(arm.guard.iter_mut());
+                    children.extend(arm.body.iter_mut());
+                }
+                children
+            }
+            ASTNode::IndexAssignment { target, index, value } => vec![target, index, value],
+            ASTNode::PrintStatement { arguments, .. } => arguments.iter_mut().collect(),
+            ASTNode::Throw { value, .. } => vec![value],
+            ASTNode::Try { body, catch, finally } => {
+                let mut children: Vec<&mut ASTNode> = body.iter_mut().collect();
+                children.extend(catch.iter_mut().flat_map(|(_, block)| block));
+                children.extend(finally.iter_mut().flatten());
+                children
+            }
+        }
+    }
+
+    /// Returns the position recorded for this node, if it has one. Runtime
+    /// errors are reported at the innermost node with a position.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ASTNode::Index { span, .. }
+            | ASTNode::StructLiteral { span, .. }
+            | ASTNode::FieldAccess { span, .. }
+            | ASTNode::FieldAssignment { span, .. }
+            | ASTNode::EnumVariant { span, .. }
+            | ASTNode::Match { span, .. }
+            | ASTNode::BinaryOp { span, .. }
+            | ASTNode::UnaryOp { span, .. }
+            | ASTNode::VariableDeclaration { span, .. }
+            | ASTNode::ConstantDeclaration { span, .. }
+            | ASTNode::Import { span, .. }
+            | ASTNode::FunctionCall { span, .. }
+            | ASTNode::CallExpression { span, .. }
+            | ASTNode::Assignment { span, .. }
+            | ASTNode::CompoundAssignment { span, .. }
+            | ASTNode::Conditional { span, .. }
+            | ASTNode::Loop { span, .. }
+            | ASTNode::FunctionDeclaration { span, .. }
+            | ASTNode::FunctionExpression { span, .. }
+            | ASTNode::ReturnStatement { span, .. }
+            | ASTNode::Break { span }
+            | ASTNode::Continue { span }
+            | ASTNode::PrintStatement { span, .. }
+            | ASTNode::Throw { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+}
@@ -1,3 +1,4 @@
 pub mod token;
 pub mod astnode;
-pub mod value;
\ No newline at end of file
+pub mod value;
+pub mod span;
\ No newline at end of file
@@ -0,0 +1,29 @@
+use std::fmt;
+
+/// A position in the source code, used to point diagnostics and runtime
+/// errors at the code that caused them. Lines and columns start at 1; the
+/// default span (0:0) marks a node that did not come from source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    /// The source file, as an index into the files loaded by the module loader.
+    /// The main script is file 0.
+    pub file: usize,
+    /// The line number.
+    pub line: usize,
+    /// The column number, counted in characters.
+    pub column: usize,
+}
+
+impl Span {
+    /// Creates a span at the given line and column of the main script.
+    pub fn new(line: usize, column: usize) -> Self {
+        Span { file: 0, line, column }
+    }
+}
+
+impl fmt::Display for Span {
+    /// Formats the span as `line:column`, without the file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
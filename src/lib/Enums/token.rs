@@ -1,20 +1,49 @@
+use std::fmt;
+
 /// Represents a token in the lexer.
 #[derive(Debug, PartialEq)]
 pub enum Token {
     // Keywords
     Variable,
+    Constant,
     Integer,
+    Float,
     Boolean,
     Loop,
+    While,
+    For,
+    In,
+    Break,
+    Continue,
     Function,
     Return,
     Call,
+    List,
+    String,
+    Void,
+    Struct,
+    Enum,
+    Match,
+    Import,
+    Public,
+    Try,
+    Catch,
+    Finally,
+    Throw,
+    As,
 
     // Identifiers
     Identifier(String),
 
     // Operators
     Assign,
+    PlusAssign,
+    MinusAssign,
+    MultiplyAssign,
+    DivideAssign,
+    ModuloAssign,
+    Increment,
+    Decrement,
     Plus,
     Minus,
     Multiply,
@@ -32,32 +61,36 @@ pub enum Token {
 
     // Delimiters
     Comma,
+    Colon,
+    DoubleColon,
+    FatArrow,
+    Arrow,
+    Dot,
+    DotDot,
     Semicolon,
     LParen,
     RParen,
     LBrace,
     RBrace,
+    LBracket,
+    RBracket,
 
     // Literals
     IntegerLiteral(i32),
+    FloatLiteral(f64),
     BooleanLiteral(bool),
     StringLiteral(String),
+    StringPart(String), // The text of an interpolated string before a `${`
+    StringEnd(String), // The text of an interpolated string after its last `}`
     // Function Call
     Print,
 
     If,
     Else,
-    // End of file
-    EOF,
-    True,
-    False,
-    Star,
-    Slash,
-    Percent
 }
 
-impl Token {
-    /// Converts the token to a string representation.
+impl fmt::Display for Token {
+    /// Formats the token as its string representation.
     ///
     /// # Examples
     ///
@@ -67,16 +100,30 @@ impl Token {
     /// let token = Token::IntegerLiteral(42);
     /// assert_eq!(token.to_string(), "IntegerLiteral(42)");
     /// ```
-    pub fn to_string(&self) -> String {
-        match self {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
             Token::Variable => "Variable".to_string(),
+            Token::Constant => "Constant".to_string(),
             Token::Integer => "Integer".to_string(),
+            Token::Float => "Float".to_string(),
             Token::Boolean => "Boolean".to_string(),
             Token::Loop => "Loop".to_string(),
+            Token::While => "While".to_string(),
+            Token::For => "For".to_string(),
+            Token::In => "In".to_string(),
+            Token::Break => "Break".to_string(),
+            Token::Continue => "Continue".to_string(),
             Token::Function => "Function".to_string(),
             Token::Return => "Return".to_string(),
             Token::Identifier(name) => format!("Identifier({})", name),
             Token::Assign => "Assign".to_string(),
+            Token::PlusAssign => "PlusAssign".to_string(),
+            Token::MinusAssign => "MinusAssign".to_string(),
+            Token::MultiplyAssign => "MultiplyAssign".to_string(),
+            Token::DivideAssign => "DivideAssign".to_string(),
+            Token::ModuloAssign => "ModuloAssign".to_string(),
+            Token::Increment => "Increment".to_string(),
+            Token::Decrement => "Decrement".to_string(),
             Token::Plus => "Plus".to_string(),
             Token::Minus => "Minus".to_string(),
             Token::Multiply => "Multiply".to_string(),
@@ -92,24 +139,44 @@ impl Token {
             Token::Or => "Or".to_string(),
             Token::Not => "Not".to_string(),
             Token::Comma => "Comma".to_string(),
+            Token::Colon => "Colon".to_string(),
+            Token::DoubleColon => "DoubleColon".to_string(),
+            Token::FatArrow => "FatArrow".to_string(),
+            Token::Arrow => "Arrow".to_string(),
+            Token::Dot => "Dot".to_string(),
+            Token::DotDot => "DotDot".to_string(),
             Token::Semicolon => "Semicolon".to_string(),
             Token::LParen => "LParen".to_string(),
             Token::RParen => "RParen".to_string(),
             Token::LBrace => "LBrace".to_string(),
             Token::RBrace => "RBrace".to_string(),
+            Token::LBracket => "LBracket".to_string(),
+            Token::RBracket => "RBracket".to_string(),
             Token::IntegerLiteral(value) => format!("IntegerLiteral({})", value),
+            Token::FloatLiteral(value) => format!("FloatLiteral({})", value),
             Token::BooleanLiteral(value) => format!("BooleanLiteral({})", value),
             Token::StringLiteral(value) => format!("StringLiteral({})", value),
+            Token::StringPart(value) => format!("StringPart({})", value),
+            Token::StringEnd(value) => format!("StringEnd({})", value),
             Token::Print => "Print".to_string(),
             Token::If => "If".to_string(),
             Token::Else => "Else".to_string(),
-            Token::EOF => "EOF".to_string(),
-            Token::True => "True".to_string(),
-            Token::False => "False".to_string(),
-            Token::Star => "Star".to_string(),
-            Token::Slash => "Slash".to_string(),
-            Token::Percent => "Percent".to_string(),
             Token::Call => "Call".to_string(),
-        }
+            Token::List => "List".to_string(),
+            Token::String => "String".to_string(),
+            Token::Void => "Void".to_string(),
+            Token::Struct => "Struct".to_string(),
+            Token::Enum => "Enum".to_string(),
+            Token::Match => "Match".to_string(),
+            Token::Import => "Import".to_string(),
+            Token::Public => "Public".to_string(),
+            Token::Try => "Try".to_string(),
+            Token::Catch => "Catch".to_string(),
+            Token::Finally => "Finally".to_string(),
+            Token::Throw => "Throw".to_string(),
+            Token::As => "As".to_string(),
+        };
+        write!(f, "{}", name)
     }
 }
+
@@ -1,52 +1,505 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt;
+use std::rc::Rc;
+use crate::lib::Enums::astnode::ASTNode;
 
 /// Represents a value in the custom language.
 #[derive(Debug, Clone)] // Add Clone trait here
 pub enum Value {
     /// An integer value.
     Integer(i32),
+    /// A floating point value.
+    Float(f64),
     /// A boolean value.
     Boolean(bool),
     /// A string value.
     String(String),
+    /// A list value. Lists are shared by reference, so mutating a list through
+    /// one binding is visible through every other binding of the same list.
+    List(Rc<RefCell<Vec<Value>>>),
+    /// A map value. Like lists, maps are shared by reference.
+    Map(Rc<RefCell<OrderedMap>>),
+    /// An instance of a user-defined struct, shared by reference.
+    Struct(Rc<RefCell<StructValue>>),
+    /// A variant of a user-defined enum together with its payload.
+    Enum(Rc<EnumValue>),
+    /// A function that can be stored, passed around and called.
+    Function(Rc<FunctionValue>),
     /// Represents the absence of a value (for statements like VariableDeclaration).
     Void,
 }
 
 impl fmt::Display for Value {
-    /// Formats the value for display.
+    /// Formats the value for display. A collection that contains itself is
+    /// shown as `[...]`, `{...}` or `Name {...}` where it recurs.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_seen(f, &mut Vec::new())
+    }
+}
+
+impl PartialEq for Value {
+    /// Implements the equality comparison for values. Collections are compared
+    /// by content; two collections that contain themselves are equal if no
+    /// difference is found before their cycles repeat.
+    fn eq(&self, other: &Self) -> bool {
+        self.equals(other, &mut Vec::new())
+    }
+}
+
+/// The address of a shared collection, used to notice when formatting or
+/// comparing reaches a collection it is already inside.
+type Address = *const ();
+
+impl Value {
+    /// Creates a new list value from the given items.
+    pub fn list(items: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(items)))
+    }
+
+    /// Creates a new map value from the given entries, in insertion order.
+    pub fn map(map: OrderedMap) -> Value {
+        Value::Map(Rc::new(RefCell::new(map)))
+    }
+
+    /// Creates a new struct instance with the given fields, in declaration order.
+    pub fn structure(name: &str, fields: Vec<(String, Value)>) -> Value {
+        Value::Struct(Rc::new(RefCell::new(StructValue { name: name.to_string(), fields })))
+    }
+
+    /// Formats the value for display.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The formatter to write to.
+    /// * `seen` - The collections being formatted, outermost first. A collection
+    ///   that is already among them is abbreviated instead of formatted again.
+    fn fmt_seen(&self, f: &mut fmt::Formatter<'_>, seen: &mut Vec<Address>) -> fmt::Result {
         match self {
             Value::Integer(value) => write!(f, "{}", value),
+            // Debug formatting keeps the decimal point, so 2.0 is not shown as 2
+            Value::Float(value) => write!(f, "{:?}", value),
             Value::Boolean(value) => write!(f, "{}", value),
             Value::String(value) => write!(f, "{}", value),
+            Value::List(items) => {
+                let address = Rc::as_ptr(items) as Address;
+                if seen.contains(&address) {
+                    return write!(f, "[...]");
+                }
+                seen.push(address);
+                write!(f, "[")?;
+                for (i, item) in items.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.fmt_nested(f, seen)?;
+                }
+                seen.pop();
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                let address = Rc::as_ptr(map) as Address;
+                if seen.contains(&address) {
+                    return write!(f, "{{...}}");
+                }
+                seen.push(address);
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", key)?;
+                    value.fmt_nested(f, seen)?;
+                }
+                seen.pop();
+                write!(f, "}}")
+            }
+            Value::Struct(instance) => {
+                let address = Rc::as_ptr(instance) as Address;
+                let instance = instance.borrow();
+                if seen.contains(&address) {
+                    return write!(f, "{} {{...}}", instance.name);
+                }
+                seen.push(address);
+                write!(f, "{} {{ ", instance.name)?;
+                for (i, (field, value)) in instance.fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", field)?;
+                    value.fmt_nested(f, seen)?;
+                }
+                seen.pop();
+                write!(f, " }}")
+            }
+            Value::Enum(value) => {
+                write!(f, "{}::{}", value.enum_name, value.variant)?;
+                if !value.values.is_empty() {
+                    write!(f, "(")?;
+                    for (i, item) in value.values.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        item.fmt_nested(f, seen)?;
+                    }
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            Value::Function(function) => match &**function {
+                FunctionValue::Named(name) | FunctionValue::Native(name) => write!(f, "<function {}>", name),
+                FunctionValue::Closure(_) => write!(f, "<function>"),
+            },
             Value::Void => write!(f, "void"),
         }
     }
-}
 
-impl PartialEq for Value {
-    /// Implements the equality comparison for values.
-    fn eq(&self, other: &Self) -> bool {
+    /// Formats the value as it appears inside a collection, where strings are quoted.
+    fn fmt_nested(&self, f: &mut fmt::Formatter<'_>, seen: &mut Vec<Address>) -> fmt::Result {
+        match self {
+            Value::String(value) => write!(f, "{:?}", value),
+            other => other.fmt_seen(f, seen),
+        }
+    }
+
+    /// Compares two values.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The value to compare with.
+    /// * `seen` - The pairs of collections being compared, outermost first. A
+    ///   pair that is already among them is taken as equal, as any difference
+    ///   between them is found where the comparison of that pair goes on.
+    fn equals(&self, other: &Value, seen: &mut Vec<(Address, Address)>) -> bool {
+        // Compares the contents of two collections, unless they are already being compared
+        fn within(
+            pair: (Address, Address),
+            seen: &mut Vec<(Address, Address)>,
+            compare: impl FnOnce(&mut Vec<(Address, Address)>) -> bool,
+        ) -> bool {
+            if pair.0 == pair.1 || seen.contains(&pair) {
+                return true;
+            }
+            seen.push(pair);
+            let equal = compare(seen);
+            seen.pop();
+            equal
+        }
         match (self, other) {
             (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
             (Value::Boolean(a), Value::Boolean(b)) => a == b,
             (Value::String(a), Value::String(b)) => a == b,
+            (Value::List(a), Value::List(b)) => within((Rc::as_ptr(a) as Address, Rc::as_ptr(b) as Address), seen, |seen| {
+                let (a, b) = (a.borrow(), b.borrow());
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.equals(b, seen))
+            }),
+            (Value::Map(a), Value::Map(b)) => within((Rc::as_ptr(a) as Address, Rc::as_ptr(b) as Address), seen, |seen| {
+                a.borrow().equals(&b.borrow(), seen)
+            }),
+            (Value::Struct(a), Value::Struct(b)) => within((Rc::as_ptr(a) as Address, Rc::as_ptr(b) as Address), seen, |seen| {
+                let (a, b) = (a.borrow(), b.borrow());
+                a.name == b.name
+                    && a.fields.len() == b.fields.len()
+                    && a.fields.iter().zip(b.fields.iter()).all(|((a_name, a), (b_name, b))| a_name == b_name && a.equals(b, seen))
+            }),
+            (Value::Enum(a), Value::Enum(b)) => {
+                a.enum_name == b.enum_name
+                    && a.variant == b.variant
+                    && a.values.len() == b.values.len()
+                    && a.values.iter().zip(b.values.iter()).all(|(a, b)| a.equals(b, seen))
+            }
+            (Value::Function(a), Value::Function(b)) => match (&**a, &**b) {
+                (FunctionValue::Named(a), FunctionValue::Named(b)) => a == b,
+                (FunctionValue::Native(a), FunctionValue::Native(b)) => a == b,
+                _ => Rc::ptr_eq(a, b),
+            },
             (Value::Void, Value::Void) => true,
             _ => false,
         }
     }
+
+    /// Converts the value to a boolean. Only booleans convert: integers and
+    /// other values have no truthiness and produce a runtime error.
+    pub fn as_bool(&self) -> Result<bool, String> {
+        match self {
+            Value::Boolean(b) => Ok(*b),
+            other => Err(format!("Expected a boolean, got {}", other.type_name())),
+        }
+    }
+
+    /// Returns the name of the value's type, as used in error messages.
+    pub fn type_name(&self) -> String {
+        match self {
+            Value::Integer(_) => "integer".to_string(),
+            Value::Float(_) => "float".to_string(),
+            Value::Boolean(_) => "boolean".to_string(),
+            Value::String(_) => "string".to_string(),
+            Value::List(_) => "list".to_string(),
+            Value::Map(_) => "map".to_string(),
+            Value::Struct(instance) => instance.borrow().name.clone(),
+            Value::Enum(value) => value.enum_name.clone(),
+            Value::Function(_) => "function".to_string(),
+            Value::Void => "void".to_string(),
+        }
+    }
+
+    /// Checks whether the value conforms to a type name as written in the source,
+    /// such as `integer`, `list<string>`, `map<string, integer>` or a struct name.
+    /// Collection element types are checked recursively.
+    pub fn matches_type(&self, type_name: &str) -> bool {
+        if let Some(element_type) = generic_arguments(type_name, "list") {
+            return match self {
+                Value::List(items) => items.borrow().iter().all(|item| item.matches_type(element_type)),
+                _ => false,
+            };
+        }
+        if let Some(arguments) = generic_arguments(type_name, "map") {
+            let (key_type, value_type) = match split_top_level_comma(arguments) {
+                Some(types) => types,
+                None => return false,
+            };
+            return match self {
+                Value::Map(map) => map.borrow().iter().all(|(key, value)| {
+                    key.to_value().matches_type(key_type) && value.matches_type(value_type)
+                }),
+                _ => false,
+            };
+        }
+        match (self, type_name) {
+            (Value::Integer(_), "integer") => true,
+            (Value::Float(_), "float") => true,
+            (Value::Boolean(_), "boolean") => true,
+            (Value::String(_), "string") => true,
+            (Value::Void, "void") => true,
+            (Value::Function(_), name) => name.starts_with("function("),
+            (Value::Struct(instance), name) => instance.borrow().name == name,
+            (Value::Enum(value), name) => value.enum_name == name,
+            _ => false,
+        }
+    }
 }
 
-impl Eq for Value {}
+/// Returns the text between the angle brackets of a generic type such as
+/// `list<integer>`, if `type_name` is an instance of `generic`.
+fn generic_arguments<'a>(type_name: &'a str, generic: &str) -> Option<&'a str> {
+    type_name
+        .strip_prefix(generic)?
+        .strip_prefix('<')?
+        .strip_suffix('>')
+        .map(str::trim)
+}
 
-impl Value {
-    /// Converts the value to a boolean.
-    pub fn as_bool(&self) -> bool {
+/// Splits `K, V` at the comma that is not nested inside angle brackets.
+fn split_top_level_comma(arguments: &str) -> Option<(&str, &str)> {
+    let mut depth = 0;
+    for (i, c) in arguments.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => return Some((arguments[..i].trim(), arguments[i + 1..].trim())),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// An instance of a user-defined struct.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructValue {
+    /// The name of the struct type.
+    pub name: String,
+    /// The field values (name and value), in declaration order.
+    pub fields: Vec<(String, Value)>,
+}
+
+impl StructValue {
+    /// Returns the value of the given field.
+    pub fn get(&self, field: &str) -> Option<&Value> {
+        self.fields.iter().find(|(name, _)| name == field).map(|(_, value)| value)
+    }
+
+    /// Returns a mutable reference to the given field.
+    pub fn get_mut(&mut self, field: &str) -> Option<&mut Value> {
+        self.fields.iter_mut().find(|(name, _)| name == field).map(|(_, value)| value)
+    }
+}
+
+/// A key of a map value. Only integers and strings can be used as keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    /// An integer key.
+    Integer(i32),
+    /// A string key.
+    String(String),
+}
+
+impl MapKey {
+    /// Converts a value into a map key.
+    ///
+    /// # Returns
+    ///
+    /// The key, or an error message if the value cannot be used as a key.
+    pub fn from_value(value: &Value) -> Result<MapKey, String> {
+        match value {
+            Value::Integer(i) => Ok(MapKey::Integer(*i)),
+            Value::String(s) => Ok(MapKey::String(s.clone())),
+            other => Err(format!("Map keys must be integers or strings, got {}", other.type_name())),
+        }
+    }
+
+    /// Converts the key back into a value.
+    pub fn to_value(&self) -> Value {
+        match self {
+            MapKey::Integer(i) => Value::Integer(*i),
+            MapKey::String(s) => Value::String(s.clone()),
+        }
+    }
+}
+
+impl fmt::Display for MapKey {
+    /// Formats the key for display, quoting string keys.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Value::Boolean(b) => *b,
-            Value::Integer(i) => *i != 0, 
-            _ => panic!("Value cannot be converted to boolean"),
+            MapKey::Integer(i) => write!(f, "{}", i),
+            MapKey::String(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+/// An insertion-ordered map from keys to values.
+///
+/// Iteration always yields entries in the order their keys were first inserted,
+/// so programs that walk a map behave the same on every run.
+#[derive(Debug, Clone, Default)]
+pub struct OrderedMap {
+    /// The entries, in insertion order.
+    entries: Vec<(MapKey, Value)>,
+    /// The position of each key within `entries`.
+    index: HashMap<MapKey, usize>,
+}
+
+impl OrderedMap {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        OrderedMap::default()
+    }
+
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the value stored under the given key.
+    pub fn get(&self, key: &MapKey) -> Option<&Value> {
+        self.index.get(key).map(|&position| &self.entries[position].1)
+    }
+
+    /// Returns true if the map contains the given key.
+    pub fn contains(&self, key: &MapKey) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Inserts a value under the given key, returning the previous value if any.
+    /// Replacing an existing key keeps its original position.
+    pub fn insert(&mut self, key: MapKey, value: Value) -> Option<Value> {
+        match self.index.get(&key) {
+            Some(&position) => Some(std::mem::replace(&mut self.entries[position].1, value)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
         }
     }
+
+    /// Removes the given key, returning its value if it was present.
+    pub fn remove(&mut self, key: &MapKey) -> Option<Value> {
+        let position = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(position);
+        for (_, slot) in self.index.iter_mut() {
+            if *slot > position {
+                *slot -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    /// Iterates over the entries in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&MapKey, &Value)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
+
+impl OrderedMap {
+    /// Compares the entries of two maps, regardless of order, with the
+    /// collections being compared in `seen` as for `Value::equals`.
+    fn equals(&self, other: &Self, seen: &mut Vec<(Address, Address)>) -> bool {
+        self.len() == other.len()
+            && self.iter().all(|(key, value)| other.get(key).is_some_and(|other| value.equals(other, seen)))
+    }
+}
+
+impl PartialEq for OrderedMap {
+    /// Two maps are equal if they hold the same entries, regardless of order.
+    fn eq(&self, other: &Self) -> bool {
+        self.equals(other, &mut Vec::new())
+    }
+}
+
+/// A variant of a user-defined enum.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumValue {
+    /// The name of the enum type.
+    pub enum_name: String,
+    /// The name of the variant.
+    pub variant: String,
+    /// The payload values, in declaration order.
+    pub values: Vec<Value>,
+}
+
+/// A function used as a value.
+#[derive(Debug)]
+pub enum FunctionValue {
+    /// A function declared with `function <type> name(...)`, referred to by name.
+    Named(String),
+    /// A builtin function, referred to by name.
+    Native(String),
+    /// An anonymous function together with the environment it captured.
+    Closure(Closure),
+}
+
+/// A variable's value, held in a cell shared by every scope that can see the
+/// variable: a closure and the scope it was created in see each other's assignments.
+pub type Binding = Rc<RefCell<Value>>;
+
+/// The variables in scope, by name.
+pub type Variables = HashMap<String, Binding>;
+
+/// An anonymous function and the variables that were in scope where it was created.
+pub struct Closure {
+    /// The return type of the function.
+    pub return_type: String,
+    /// The parameters of the function (type and name).
+    pub parameters: Vec<(String, String)>,
+    /// The body of the function, shared by every call.
+    pub body: Rc<Vec<ASTNode>>,
+    /// The variables in scope when the function expression was evaluated,
+    /// shared with that scope rather than copied.
+    pub captured: Variables,
+}
+
+impl fmt::Debug for Closure {
+    /// Formats the closure with the names it captured but not their values,
+    /// which may include the closure itself.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut captured: Vec<&String> = self.captured.keys().collect();
+        captured.sort();
+        f.debug_struct("Closure")
+            .field("return_type", &self.return_type)
+            .field("parameters", &self.parameters)
+            .field("body", &self.body)
+            .field("captured", &captured)
+            .finish()
+    }
 }
@@ -1,49 +1,285 @@
 use crate::lib::Lexer::lexer::Lexer;
 use crate::lib::Parser::parser::Parser;
-use crate::lib::Asteva::context::Context;
+use crate::lib::Checker::checker::Checker;
+use crate::lib::Checker::constants::fold_constants;
+use crate::lib::Checker::diagnostic::Diagnostic;
+use crate::lib::Checker::lints::{lint, Level, Lint, LintLevels};
+use crate::lib::Asteva::context::{default_max_depth, Context, Input};
 use crate::lib::Asteva::asteva::evaluate_ast;
+use crate::lib::Asteva::budget::Budget;
+use crate::lib::Asteva::error::{ErrorKind, RuntimeError};
+use crate::lib::Asteva::files::Capabilities;
 use crate::lib::Enums::token::Token;
 use crate::lib::Enums::astnode::ASTNode;
+use crate::lib::Enums::span::Span;
+use crate::lib::Loader::loader::ModuleLoader;
+use crate::lib::Optimizer::optimizer::optimize;
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Instant;
+
+/// Why `interpret` did not run a program to the end.
+#[derive(Debug)]
+pub struct Failure {
+    /// The kind of the runtime error that stopped the program, or `None` if
+    /// the program did not compile.
+    pub kind: Option<ErrorKind>,
+    /// The diagnostics, or the runtime error with its position and backtrace.
+    pub message: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
 
 /// A struct representing an interpreter for a custom language.
 pub struct Interpreter<'a> {
     /// The code to be interpreted.
     code: &'a str,
+    /// The file the code was read from, against which imports are resolved.
+    path: Option<PathBuf>,
+    /// The source the script's input builtins read from.
+    input: Input,
+    /// The command-line arguments passed to the script.
+    args: Vec<String>,
+    /// The files the script may read and write; none by default.
+    capabilities: Capabilities,
+    /// How many function calls may be nested before a `StackOverflow` error,
+    /// or `None` for as many as the running thread's stack can hold.
+    max_depth: Option<usize>,
+    /// The maximum number of evaluation steps, if limited.
+    fuel: Option<u64>,
+    /// The moment the script must have finished by, if limited.
+    deadline: Option<Instant>,
+    /// The maximum number of bytes the script may have allocated, if limited.
+    max_heap: Option<usize>,
+    /// How much the program is optimized before it runs: 0 for not at all,
+    /// 1 for constant folding and simplification.
+    optimization_level: u8,
+    /// What each lint does when it finds a problem.
+    lint_levels: LintLevels,
 }
 
 impl<'a> Interpreter<'a> {
     /// Creates a new `Interpreter` instance with the given code.
     pub fn new(code: &'a str) -> Self {
-        Interpreter { code }
+        Interpreter {
+            code,
+            path: None,
+            input: Rc::new(RefCell::new(BufReader::new(io::stdin()))),
+            args: Vec::new(),
+            capabilities: Capabilities::none(),
+            max_depth: None,
+            fuel: None,
+            deadline: None,
+            max_heap: None,
+            optimization_level: 1,
+            lint_levels: LintLevels::new(),
+        }
+    }
+
+    /// Records the file the code was read from, so that imports are resolved
+    /// relative to it and diagnostics name it.
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Makes the script read its input from `input` instead of stdin.
+    #[allow(dead_code)] // For embedders; the command-line driver reads stdin
+    pub fn with_input(mut self, input: impl BufRead + 'static) -> Self {
+        self.input = Rc::new(RefCell::new(input));
+        self
+    }
+
+    /// Sets the command-line arguments the script receives from `args()`.
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Grants the script's file builtins access to parts of the filesystem.
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Limits how deeply the script's function calls may nest; deeper calls
+    /// fail with a catchable `StackOverflow` error.
+    ///
+    /// Each level uses up to `STACK_PER_CALL` bytes of native stack, so a
+    /// default 8 MiB main thread holds only a few hundred levels in release
+    /// builds and a few dozen in debug builds, well below `DEFAULT_MAX_DEPTH`.
+    /// A limit the stack cannot hold fails with a `StackOverflow` error once
+    /// the stack runs low, at a shallower depth; run the interpreter on a thread
+    /// built with `max_depth * STACK_PER_CALL` bytes of stack to reach it.
+    /// Without this setting the limit is `default_max_depth()`, which fits the
+    /// running thread.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Stops the script with an `OutOfFuel` error once it has taken `fuel`
+    /// evaluation steps. Every evaluated node and every loop iteration is a step.
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    /// Stops the script with a `Timeout` error if it is still running at `deadline`.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Stops the script with an `OutOfMemory` error once it has more than
+    /// `bytes` allocated. Memory is only counted when the program installs
+    /// `CountingAllocator` as its global allocator, and counts everything the
+    /// running thread allocates, the interpreter's own structures included.
+    pub fn with_max_heap(mut self, bytes: usize) -> Self {
+        self.max_heap = Some(bytes);
+        self
+    }
+
+    /// Sets how much the program is optimized before it runs: 0 runs it as
+    /// written, 1 (the default) folds constant expressions and removes
+    /// identities and code that can never run.
+    pub fn with_optimization_level(mut self, level: u8) -> Self {
+        self.optimization_level = level;
+        self
+    }
+
+    /// Sets whether a lint is silent, warns, or stops the program from running.
+    /// Every lint warns by default.
+    pub fn with_lint_level(mut self, lint: Lint, level: Level) -> Self {
+        self.lint_levels.set(lint, level);
+        self
     }
 
-    /// Tokenizes the code and returns a vector of tokens.
-    pub fn tokenize(&self) -> Vec<Token> {
+    /// Evaluates the AST in a fresh context configured from the interpreter's options.
+    ///
+    /// Unlike `evaluate`, a failure is returned as the runtime error itself, so
+    /// that its kind, position and backtrace (`RuntimeError::trace`) can be inspected.
+    pub fn execute(&self, ast: &[ASTNode]) -> Result<(), RuntimeError> {
+        let mut context = Context::new()
+            .with_input(Rc::clone(&self.input))
+            .with_args(self.args.clone())
+            .with_capabilities(self.capabilities.clone())
+            .with_max_depth(self.max_depth.unwrap_or_else(default_max_depth))
+            .with_budget(Budget::new(self.fuel, self.deadline, self.max_heap));
+        evaluate_ast(ast.to_vec(), &mut context)
+    }
+
+    /// Loads the code and every module it imports, checks and lints the combined
+    /// program, folds its constants and optimizes it as configured.
+    ///
+    /// # Returns
+    ///
+    /// The program ready to run, the loader that read its files and the
+    /// warnings of the lints, or an error message listing every diagnostic.
+    /// A denied lint that finds a problem is an error.
+    fn compile(&self) -> Result<(Vec<ASTNode>, ModuleLoader, Vec<Diagnostic>), String> {
+        let mut loader = ModuleLoader::new();
+        let mut ast = loader
+            .load(self.code, self.path.as_deref())
+            .map_err(|diagnostics| loader.render(&diagnostics))?;
+        Checker::new().check(&ast).map_err(|diagnostics| loader.render(&diagnostics))?;
+        let warnings = lint(&ast, &self.lint_levels);
+        if warnings.iter().any(Diagnostic::is_error) {
+            return Err(loader.render(&warnings));
+        }
+        fold_constants(&mut ast).map_err(|diagnostics| loader.render(&diagnostics))?;
+        if self.optimization_level > 0 {
+            optimize(&mut ast);
+        }
+        Ok((ast, loader, warnings))
+    }
+
+    /// Returns the program as it would run: loaded with its imports, checked
+    /// and optimized as configured. Returns an error message if it does not compile.
+    pub fn ast(&self) -> Result<Vec<ASTNode>, String> {
+        self.compile().map(|(ast, _, _)| ast)
+    }
+
+    /// Loads the code and every module it imports, checks and lints the combined
+    /// program, folds its constants, optimizes it as configured and evaluates it.
+    /// Lint warnings are printed to stderr before the program runs.
+    /// Returns a result indicating success or why the program failed. An
+    /// uncaught runtime error is shown with its position and the function
+    /// calls it unwound through.
+    pub fn interpret(&self) -> Result<(), Failure> {
+        let (ast, loader, warnings) = self.compile().map_err(|message| Failure { kind: None, message })?;
+        if !warnings.is_empty() {
+            eprintln!("{}", loader.render(&warnings));
+        }
+        self.execute(&ast).map_err(|error| Failure {
+            kind: Some(error.kind),
+            message: error.render(loader.files()),
+        })
+    }
+}
+
+/// The stages of `interpret`, for embedders that run them one at a time. The
+/// command-line driver only compiles and runs whole programs.
+#[allow(dead_code)]
+impl Interpreter<'_> {
+    /// Tokenizes the code and returns a vector of tokens, along with the
+    /// source position of each token, or an error message listing every diagnostic.
+    pub fn tokenize(&self) -> Result<(Vec<Token>, Vec<Span>), String> {
         let mut lexer = Lexer::new(self.code);
         let mut tokens = Vec::new();
-        while let Some(token) = lexer.next_token() {
+        let mut spans = Vec::new();
+        while let Some((token, span)) = lexer.next_spanned_token() {
             tokens.push(token);
+            spans.push(span);
         }
-        tokens
+        if !lexer.diagnostics.is_empty() {
+            return Err(Self::report(&lexer.diagnostics));
+        }
+        Ok((tokens, spans))
     }
 
-    /// Parses the tokens into an abstract syntax tree (AST) and returns it.
-    pub fn parse(&self, tokens: &[Token]) -> Vec<ASTNode> {
-        let mut parser = Parser::new(tokens);
-        parser.parse()
+    /// Parses the tokens into an abstract syntax tree (AST) and returns it,
+    /// or an error message describing the first syntax error.
+    pub fn parse(&self, tokens: &[Token], spans: &[Span]) -> Result<Vec<ASTNode>, String> {
+        let mut parser = Parser::with_spans(tokens, spans);
+        parser.parse().map_err(|diagnostic| diagnostic.to_string())
     }
 
-    /// Evaluates the AST and returns a result indicating success or an error message.
-    pub fn evaluate(&self, ast: &[ASTNode]) -> Result<(), String> {
-        let mut context = Context::new();
-        evaluate_ast(ast.to_vec(), &mut context)
+    /// Runs the static checks over the AST and returns a result indicating
+    /// success or an error message listing every diagnostic.
+    pub fn check(&self, ast: &[ASTNode]) -> Result<(), String> {
+        Checker::new().check(ast).map_err(|diagnostics| Self::report(&diagnostics))
+    }
+
+    /// Evaluates every constant initialiser in the AST ahead of execution,
+    /// returning an error message listing every diagnostic if one cannot be folded.
+    pub fn fold_constants(&self, ast: &mut [ASTNode]) -> Result<(), String> {
+        fold_constants(ast).map_err(|diagnostics| Self::report(&diagnostics))
     }
 
-    /// Tokenizes the code, parses the tokens into an AST, and evaluates the AST.
-    /// Returns a result indicating success or an error message.
-    pub fn interpret(&self) -> Result<(), String> {
-        let tokens = self.tokenize();
-        let ast = self.parse(&tokens);
-        self.evaluate(&ast)
+    /// Folds constant expressions and removes identities and code that can never run.
+    pub fn optimize(&self, ast: &mut Vec<ASTNode>) {
+        optimize(ast);
+    }
+
+    /// Formats diagnostics for display, one after another.
+    fn report(diagnostics: &[Diagnostic]) -> String {
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Evaluates the AST and returns a result indicating success or an error message.
+    pub fn evaluate(&self, ast: &[ASTNode]) -> Result<(), String> {
+        self.execute(ast).map_err(|error| error.to_string())
     }
 }
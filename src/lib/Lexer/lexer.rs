@@ -1,4 +1,6 @@
+use crate::lib::Checker::diagnostic::Diagnostic;
 use crate::lib::Enums::token::Token;
+use crate::lib::Enums::span::Span;
 
 /// Lexer for tokenizing input characters.
 pub struct Lexer {
@@ -6,17 +8,43 @@ pub struct Lexer {
     pub input: Vec<char>,
     /// The current position in the input.
     pub position: usize,
+    /// The position in the input at which each line starts.
+    line_starts: Vec<usize>,
+    /// For each `${` of an interpolated string being read, the number of
+    /// braces opened inside it and not yet closed.
+    interpolations: Vec<usize>,
+    /// The problems found in the input so far, such as literals out of range.
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 impl Lexer {
     /// Creates a new Lexer instance with the given input string.
     pub fn new(input: &str) -> Self {
+        let input: Vec<char> = input.chars().collect();
+        let mut line_starts = vec![0];
+        line_starts.extend(input.iter().enumerate().filter(|(_, c)| **c == '\n').map(|(i, _)| i + 1));
         Lexer {
-            input: input.chars().collect(),
+            input,
             position: 0,
+            line_starts,
+            interpolations: Vec::new(),
+            diagnostics: Vec::new(),
         }
     }
 
+    /// Returns the line and column of a position in the input.
+    pub fn span_at(&self, position: usize) -> Span {
+        let line = self.line_starts.partition_point(|start| *start <= position);
+        Span::new(line, position - self.line_starts[line - 1] + 1)
+    }
+
+    /// Returns the next token from the input together with the span at which it starts.
+    pub fn next_spanned_token(&mut self) -> Option<(Token, Span)> {
+        self.skip_whitespace();
+        let span = self.span_at(self.position);
+        self.next_token().map(|token| (token, span))
+    }
+
     /// Advances the lexer position by one and returns the character at the new position.
     pub fn advance(&mut self) -> Option<char> {
         let current_char = self.input.get(self.position).cloned();
@@ -54,33 +82,86 @@ impl Lexer {
         identifier
     }
 
-    /// Reads a number from the input.
-    pub fn read_number(&mut self) -> i32 {
+    /// Reads the digits of a number from the input.
+    pub fn read_number(&mut self) -> String {
         let mut number = String::new();
         while let Some(c) = self.peek() {
-            if c.is_digit(10) {
+            if c.is_ascii_digit() {
                 number.push(c);
                 self.advance();
             } else {
                 break;
             }
         }
-        number.parse().unwrap()
+        number
+    }
+
+    /// Reads an integer or, if the digits are followed by a decimal point and
+    /// more digits, a float literal. `0..10` is a range, not a float. An integer
+    /// too large for an `i32` is recorded as a diagnostic and read as 0.
+    pub fn read_numeric_literal(&mut self) -> Token {
+        let span = self.span_at(self.position);
+        let integer = self.read_number();
+        let has_fraction = self.peek() == Some('.')
+            && self.input.get(self.position + 1).is_some_and(|c| c.is_ascii_digit());
+        if !has_fraction {
+            let value = integer.parse().unwrap_or_else(|_| {
+                let message = format!("Integer literal {} is too large, the largest integer is {}", integer, i32::MAX);
+                self.diagnostics.push(Diagnostic::error(message).at(span));
+                0
+            });
+            return Token::IntegerLiteral(value);
+        }
+        self.advance(); // Consume the decimal point
+        let mut fraction = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() {
+                fraction.push(c);
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        Token::FloatLiteral(format!("{}.{}", integer, fraction).parse().unwrap())
     }
 
-    /// Reads a string literal from the input.
-    pub fn read_string(&mut self) -> String {
-        let mut string = String::new();
+    /// Reads a string literal from the input, or the first part of an interpolated one.
+    ///
+    /// `"total: ${a + b}!"` is read as `StringPart("total: ")`, the tokens of
+    /// `a + b` and `StringEnd("!")`; each further `${` in the string produces
+    /// another `StringPart` in place of the `StringEnd`.
+    pub fn read_string(&mut self) -> Token {
         self.advance(); // Consume the opening quote
+        match self.read_string_segment() {
+            Token::StringEnd(text) => Token::StringLiteral(text),
+            part => part,
+        }
+    }
+
+    /// Reads the text of a string up to its closing quote, returning `StringEnd`,
+    /// or up to the next `${`, returning `StringPart`. `\$` stands for a plain
+    /// `$`, so `"\${x}"` is the text `${x}` rather than an interpolation.
+    fn read_string_segment(&mut self) -> Token {
+        let mut text = String::new();
         while let Some(c) = self.peek() {
             if c == '"' {
                 break;
             }
-            string.push(c);
+            if c == '\\' && self.input.get(self.position + 1) == Some(&'$') {
+                self.position += 2; // Consume "\$"
+                text.push('$');
+                continue;
+            }
+            if c == '$' && self.input.get(self.position + 1) == Some(&'{') {
+                self.position += 2; // Consume "${"
+                self.interpolations.push(0);
+                return Token::StringPart(text);
+            }
+            text.push(c);
             self.advance();
         }
         self.advance(); // Consume the closing quote
-        string
+        Token::StringEnd(text)
     }
 
    /// Returns the next token from the input.
@@ -98,29 +179,66 @@ impl Lexer {
                 if let Some('=') = self.peek() {
                     self.position += 1;
                     Some(Token::Equals)
+                } else if let Some('>') = self.peek() {
+                    self.position += 1;
+                    Some(Token::FatArrow)
                 } else {
                     Some(Token::Assign)
                 }
             }
             '+' => {
                 self.position += 1;
-                Some(Token::Plus)
+                if let Some('=') = self.peek() {
+                    self.position += 1;
+                    Some(Token::PlusAssign)
+                } else if let Some('+') = self.peek() {
+                    self.position += 1;
+                    Some(Token::Increment)
+                } else {
+                    Some(Token::Plus)
+                }
             }
             '-' => {
                 self.position += 1;
-                Some(Token::Minus)
+                if let Some('>') = self.peek() {
+                    self.position += 1;
+                    Some(Token::Arrow)
+                } else if let Some('=') = self.peek() {
+                    self.position += 1;
+                    Some(Token::MinusAssign)
+                } else if let Some('-') = self.peek() {
+                    self.position += 1;
+                    Some(Token::Decrement)
+                } else {
+                    Some(Token::Minus)
+                }
             }
             '*' => {
                 self.position += 1;
-                Some(Token::Multiply)
+                if let Some('=') = self.peek() {
+                    self.position += 1;
+                    Some(Token::MultiplyAssign)
+                } else {
+                    Some(Token::Multiply)
+                }
             }
             '/' => {
                 self.position += 1;
-                Some(Token::Divide)
+                if let Some('=') = self.peek() {
+                    self.position += 1;
+                    Some(Token::DivideAssign)
+                } else {
+                    Some(Token::Divide)
+                }
             }
             '%' => {
                 self.position += 1;
-                Some(Token::Modulo)
+                if let Some('=') = self.peek() {
+                    self.position += 1;
+                    Some(Token::ModuloAssign)
+                } else {
+                    Some(Token::Modulo)
+                }
             }
             '(' => {
                 self.position += 1;
@@ -132,12 +250,32 @@ impl Lexer {
             }
             '{' => {
                 self.position += 1;
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
                 Some(Token::LBrace)
             }
+            '}' if self.interpolations.last() == Some(&0) => {
+                // The end of an interpolated expression: carry on reading the string
+                self.position += 1;
+                self.interpolations.pop();
+                Some(self.read_string_segment())
+            }
             '}' => {
                 self.position += 1;
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth -= 1;
+                }
                 Some(Token::RBrace)
             }
+            '[' => {
+                self.position += 1;
+                Some(Token::LBracket)
+            }
+            ']' => {
+                self.position += 1;
+                Some(Token::RBracket)
+            }
             ';' => {
                 self.position += 1;
                 Some(Token::Semicolon)
@@ -146,17 +284,50 @@ impl Lexer {
                 self.position += 1;
                 Some(Token::Comma)
             }
+            '.' => {
+                self.position += 1;
+                if let Some('.') = self.peek() {
+                    self.position += 1;
+                    Some(Token::DotDot)
+                } else {
+                    Some(Token::Dot)
+                }
+            }
+            ':' => {
+                self.position += 1;
+                if let Some(':') = self.peek() {
+                    self.position += 1;
+                    Some(Token::DoubleColon)
+                } else {
+                    Some(Token::Colon)
+                }
+            }
             '<' => {
                 self.position += 1;
-                Some(Token::LessThan)
+                if let Some('=') = self.peek() {
+                    self.position += 1;
+                    Some(Token::LessThanOrEqual)
+                } else {
+                    Some(Token::LessThan)
+                }
             }
             '>' => {
                 self.position += 1;
-                Some(Token::GreaterThan)
+                if let Some('=') = self.peek() {
+                    self.position += 1;
+                    Some(Token::GreaterThanOrEqual)
+                } else {
+                    Some(Token::GreaterThan)
+                }
             }
             '!' => {
                 self.position += 1;
-                Some(Token::Not)
+                if let Some('=') = self.peek() {
+                    self.position += 1;
+                    Some(Token::NotEquals)
+                } else {
+                    Some(Token::Not)
+                }
             }
             '&' => {
                 self.position += 1;
@@ -176,14 +347,21 @@ impl Lexer {
                     None
                 }
             }
-            '0'..='9' => Some(Token::IntegerLiteral(self.read_number())),
-            'a'..='z' | 'A'..='Z' => {
+            '0'..='9' => Some(self.read_numeric_literal()),
+            'a'..='z' | 'A'..='Z' | '_' => {
                 let identifier = self.read_identifier();
                 match identifier.as_str() {
                     "variable" => Some(Token::Variable),
+                    "constant" => Some(Token::Constant),
                     "integer" => Some(Token::Integer),
-                    "boolean" => Some(Token::Boolean),
+                    "float" => Some(Token::Float),
+                    "boolean" | "bool" => Some(Token::Boolean),
                     "loop" => Some(Token::Loop),
+                    "while" => Some(Token::While),
+                    "for" => Some(Token::For),
+                    "in" => Some(Token::In),
+                    "break" => Some(Token::Break),
+                    "continue" => Some(Token::Continue),
                     "function" => Some(Token::Function),
                     "return" => Some(Token::Return),
                     "true" => Some(Token::BooleanLiteral(true)),
@@ -192,10 +370,23 @@ impl Lexer {
                     "else" => Some(Token::Else),
                     "print" => Some(Token::Print),
                     "call" => Some(Token::Call),
+                    "list" => Some(Token::List),
+                    "string" => Some(Token::String),
+                    "void" => Some(Token::Void),
+                    "struct" => Some(Token::Struct),
+                    "enum" => Some(Token::Enum),
+                    "match" => Some(Token::Match),
+                    "import" => Some(Token::Import),
+                    "public" => Some(Token::Public),
+                    "try" => Some(Token::Try),
+                    "catch" => Some(Token::Catch),
+                    "finally" => Some(Token::Finally),
+                    "throw" => Some(Token::Throw),
+                    "as" => Some(Token::As),
                     _ => Some(Token::Identifier(identifier)),
                 }
             }
-            '"' => Some(Token::StringLiteral(self.read_string())),
+            '"' => Some(self.read_string()),
             _ => None,
         };
     
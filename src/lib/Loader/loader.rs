@@ -0,0 +1,455 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use crate::lib::Checker::constants::pattern_bindings;
+use crate::lib::Checker::diagnostic::Diagnostic;
+use crate::lib::Enums::astnode::ASTNode;
+use crate::lib::Enums::span::Span;
+use crate::lib::Lexer::lexer::Lexer;
+use crate::lib::Parser::parser::Parser;
+
+/// A module that has finished loading.
+struct LoadedModule {
+    /// The name of the module in messages, e.g. `util::strings`.
+    name: String,
+    /// Maps each public function and constant to its name in the combined program.
+    exports: HashMap<String, String>,
+    /// The module's other top-level names, which cannot be used from outside.
+    private: HashSet<String>,
+}
+
+/// Loads a script together with every module it imports, directly or
+/// indirectly, and combines them into a single program.
+///
+/// Each module's top-level functions, constants and variables are renamed to
+/// `module::name`, so modules cannot clash with each other or with the script,
+/// and `alias::member` references are rewritten to the renamed public items.
+/// Struct and enum declarations are not namespaced and are visible everywhere.
+/// A module's statements run once, before those of the first file that imports it.
+pub struct ModuleLoader {
+    /// The display name of every file loaded, indexed by `Span::file`. The main
+    /// script is file 0, with an empty name if it was not read from a file.
+    files: Vec<String>,
+    /// The modules loaded so far, by canonical path.
+    modules: HashMap<PathBuf, LoadedModule>,
+    /// The files being loaded, outermost first, used to detect import cycles.
+    loading: Vec<(PathBuf, String)>,
+    /// The statements of every module loaded so far, in the order they must run.
+    program: Vec<ASTNode>,
+    /// The problems found so far.
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ModuleLoader {
+    /// Creates a loader with nothing loaded.
+    pub fn new() -> Self {
+        ModuleLoader {
+            files: Vec::new(),
+            modules: HashMap::new(),
+            loading: Vec::new(),
+            program: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Loads the main script and everything it imports.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The source of the main script.
+    /// * `path` - The file the script was read from, if any. Imports are resolved
+    ///   relative to its directory, or to the working directory if there is none.
+    ///
+    /// # Returns
+    ///
+    /// The combined program, or every diagnostic found while loading it.
+    pub fn load(&mut self, code: &str, path: Option<&Path>) -> Result<Vec<ASTNode>, Vec<Diagnostic>> {
+        let name = path.map(|path| path.display().to_string()).unwrap_or_default();
+        let dir = path
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        if let Some(canonical) = path.and_then(|path| fs::canonicalize(path).ok()) {
+            self.loading.push((canonical, name.clone()));
+        }
+
+        if let Some((file, ast)) = self.parse_file(code, name) {
+            let statements = self.link(ast, &dir, file, None).0;
+            self.program.extend(statements);
+        }
+        self.loading.clear();
+
+        if self.diagnostics.is_empty() {
+            Ok(std::mem::take(&mut self.program))
+        } else {
+            Err(std::mem::take(&mut self.diagnostics))
+        }
+    }
+
+    /// The display name of every file loaded, indexed by `Span::file`.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Formats diagnostics for display, naming the file each one comes from.
+    pub fn render(&self, diagnostics: &[Diagnostic]) -> String {
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.render(&self.files))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Tokenizes and parses a file, recording its name.
+    ///
+    /// # Returns
+    ///
+    /// The index of the file, as used in `Span::file`, and its AST, or `None`
+    /// if the file has a lexical or syntax error, which is recorded as a diagnostic.
+    fn parse_file(&mut self, code: &str, name: String) -> Option<(usize, Vec<ASTNode>)> {
+        let file = self.files.len();
+        self.files.push(name);
+
+        let mut lexer = Lexer::new(code);
+        let mut tokens = Vec::new();
+        let mut spans = Vec::new();
+        while let Some((token, mut span)) = lexer.next_spanned_token() {
+            span.file = file;
+            tokens.push(token);
+            spans.push(span);
+        }
+        if !lexer.diagnostics.is_empty() {
+            for mut diagnostic in lexer.diagnostics {
+                if let Some(span) = &mut diagnostic.span {
+                    span.file = file;
+                }
+                self.diagnostics.push(diagnostic);
+            }
+            return None;
+        }
+        match Parser::with_spans(&tokens, &spans).parse() {
+            Ok(ast) => Some((file, ast)),
+            Err(diagnostic) => {
+                self.diagnostics.push(diagnostic);
+                None
+            }
+        }
+    }
+
+    /// Resolves the imports of a parsed file and rewrites its names.
+    ///
+    /// # Arguments
+    ///
+    /// * `ast` - The file's statements.
+    /// * `dir` - The directory imports are resolved against.
+    /// * `file` - The index of the file.
+    /// * `module` - The module name to prefix top-level names with, or `None`
+    ///   for the main script, whose names are left as they are.
+    ///
+    /// # Returns
+    ///
+    /// The file's statements without its imports, and the names of its public
+    /// and private top-level items.
+    fn link(
+        &mut self,
+        ast: Vec<ASTNode>,
+        dir: &Path,
+        file: usize,
+        module: Option<&str>,
+    ) -> (Vec<ASTNode>, HashMap<String, String>, HashSet<String>) {
+        let mut namespaces: HashMap<String, PathBuf> = HashMap::new();
+        let mut statements = Vec::new();
+        for node in ast {
+            if let ASTNode::Import { path, alias, span } = node {
+                if namespaces.contains_key(&alias) {
+                    let message = format!("Namespace '{}' is imported more than once", alias);
+                    self.diagnostics.push(Diagnostic::error(message).at(span));
+                    continue;
+                }
+                if let Some(canonical) = self.load_module(&normalize(&dir.join(&path)), &path, span) {
+                    namespaces.insert(alias, canonical);
+                }
+            } else {
+                self.report_nested_imports(&node);
+                statements.push(node);
+            }
+        }
+
+        // Give the module's top-level names their qualified form
+        let mut exports = HashMap::new();
+        let mut private = HashSet::new();
+        if let Some(module) = module {
+            let mut renames = HashMap::new();
+            for statement in &statements {
+                let (name, public) = match statement {
+                    ASTNode::FunctionDeclaration { name, public, .. }
+                    | ASTNode::ConstantDeclaration { name, public, .. } => (name, *public),
+                    ASTNode::VariableDeclaration { name, .. } => (name, false),
+                    _ => continue,
+                };
+                let qualified = format!("{}::{}", module, name);
+                if public {
+                    exports.insert(name.clone(), qualified.clone());
+                } else {
+                    private.insert(name.clone());
+                }
+                renames.insert(name.clone(), qualified);
+            }
+            for statement in statements.iter_mut() {
+                rename(statement, &mut renames, false);
+            }
+        }
+
+        // Point `alias::member` at the public items of the imported modules
+        let span = Span { file, line: 0, column: 0 };
+        for statement in statements.iter_mut() {
+            self.resolve_members(statement, &namespaces, span);
+        }
+
+        (statements, exports, private)
+    }
+
+    /// Loads the module at `path` unless it is already loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The module's file, relative to the working directory.
+    /// * `written` - The module as written in the import, for messages.
+    /// * `span` - The import statement.
+    ///
+    /// # Returns
+    ///
+    /// The canonical path identifying the module, or `None` if it could not be loaded.
+    fn load_module(&mut self, path: &Path, written: &str, span: Span) -> Option<PathBuf> {
+        let canonical = match fs::canonicalize(path) {
+            Ok(canonical) => canonical,
+            Err(_) => {
+                let message = format!("Cannot find module '{}' (looked for {})", written, path.display());
+                self.diagnostics.push(Diagnostic::error(message).at(span));
+                return None;
+            }
+        };
+        if let Some(position) = self.loading.iter().position(|(loading, _)| *loading == canonical) {
+            let mut cycle: Vec<String> = self.loading[position..].iter().map(|(_, name)| name.clone()).collect();
+            cycle.push(self.loading[position].1.clone());
+            let message = format!("Import cycle: {}", cycle.join(" -> "));
+            self.diagnostics.push(Diagnostic::error(message).at(span));
+            return None;
+        }
+        if self.modules.contains_key(&canonical) {
+            return Some(canonical);
+        }
+
+        let code = match fs::read_to_string(&canonical) {
+            Ok(code) => code,
+            Err(error) => {
+                let message = format!("Cannot read module '{}': {}", written, error);
+                self.diagnostics.push(Diagnostic::error(message).at(span));
+                return None;
+            }
+        };
+        let display = path.display().to_string();
+        let name = self.module_name(written);
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        let (file, ast) = self.parse_file(&code, display.clone())?;
+        self.loading.push((canonical.clone(), display));
+        let (statements, exports, private) = self.link(ast, &dir, file, Some(&name));
+        self.loading.pop();
+
+        self.program.extend(statements);
+        self.modules.insert(canonical.clone(), LoadedModule { name, exports, private });
+        Some(canonical)
+    }
+
+    /// Chooses a unique module name from the path written in an import, such
+    /// as `util::strings` for `util/strings.known`. Imports by absolute path,
+    /// or reaching outside the importing directory, are named after the file alone.
+    fn module_name(&self, written: &str) -> String {
+        let written = Path::new(written).with_extension("");
+        let base = if written.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir)) {
+            written
+                .components()
+                .filter_map(|component| match component {
+                    Component::Normal(segment) => Some(segment.to_string_lossy().into_owned()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("::")
+        } else {
+            written.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+        };
+        let taken = |name: &str| self.modules.values().any(|module| module.name == name);
+        if !taken(&base) {
+            return base;
+        }
+        let mut suffix = 2;
+        while taken(&format!("{}#{}", base, suffix)) {
+            suffix += 1;
+        }
+        format!("{}#{}", base, suffix)
+    }
+
+    /// Rewrites every `alias::member` reference in a node to the qualified name
+    /// of the public item it refers to.
+    fn resolve_members(&mut self, node: &mut ASTNode, namespaces: &HashMap<String, PathBuf>, span: Span) {
+        let name = match node {
+            ASTNode::Identifier(name) => Some(name),
+            ASTNode::FunctionCall { name, .. } => Some(name),
+            ASTNode::Assignment { identifier, .. } => Some(identifier),
+            _ => None,
+        };
+        if let Some(name) = name {
+            if let Some((alias, member)) = name.split_once("::") {
+                if let Some(module) = namespaces.get(alias).and_then(|path| self.modules.get(path)) {
+                    match module.exports.get(member) {
+                        Some(qualified) => *name = qualified.clone(),
+                        None => {
+                            let message = if module.private.contains(member) {
+                                format!("'{}' is not public in module '{}'", member, module.name)
+                            } else {
+                                format!("Module '{}' has no member '{}'", module.name, member)
+                            };
+                            self.diagnostics.push(Diagnostic::error(message).at(span));
+                        }
+                    }
+                }
+            }
+        }
+        for child in node.children_mut() {
+            self.resolve_members(child, namespaces, span);
+        }
+    }
+
+    /// Reports any import that is not at the top level of a file.
+    fn report_nested_imports(&mut self, node: &ASTNode) {
+        for child in node.children() {
+            if let ASTNode::Import { span, .. } = child {
+                let message = "Imports are only allowed at the top level of a file";
+                self.diagnostics.push(Diagnostic::error(message).at(*span));
+            }
+            self.report_nested_imports(child);
+        }
+    }
+}
+
+/// Removes `.` and `..` components from a path without touching the file system,
+/// so that files are shown as `lib/math.known` rather than `util/../lib/math.known`.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Renames references to a module's top-level names throughout a node.
+///
+/// Parameters, loop variables, match bindings and variables declared inside a
+/// function hide the top-level name they share, so those are left alone.
+///
+/// # Arguments
+///
+/// * `node` - The node to rewrite.
+/// * `renames` - Maps each visible top-level name to its qualified name.
+/// * `in_function` - Whether `node` is inside a function body.
+fn rename(node: &mut ASTNode, renames: &mut HashMap<String, String>, in_function: bool) {
+    match node {
+        ASTNode::Identifier(name)
+        | ASTNode::FunctionCall { name, .. }
+        | ASTNode::Assignment { identifier: name, .. } => {
+            if let Some(qualified) = renames.get(name.as_str()) {
+                *name = qualified.clone();
+            }
+        }
+        ASTNode::VariableDeclaration { .. } | ASTNode::ConstantDeclaration { .. } if in_function => {
+            // A local declaration hides the top-level name for the rest of the function
+            for child in node.children_mut() {
+                rename(child, renames, in_function);
+            }
+            if let ASTNode::VariableDeclaration { name, .. } | ASTNode::ConstantDeclaration { name, .. } = node {
+                renames.remove(name.as_str());
+            }
+            return;
+        }
+        ASTNode::VariableDeclaration { name, .. }
+        | ASTNode::ConstantDeclaration { name, .. } => {
+            if let Some(qualified) = renames.get(name.as_str()) {
+                *name = qualified.clone();
+            }
+        }
+        ASTNode::FunctionDeclaration { name, parameters, body, .. } => {
+            if let Some(qualified) = renames.get(name.as_str()) {
+                *name = qualified.clone();
+            }
+            let mut inner = renames.clone();
+            for (_, parameter) in parameters.iter() {
+                inner.remove(parameter);
+            }
+            for statement in body.iter_mut() {
+                rename(statement, &mut inner, true);
+            }
+            return;
+        }
+        ASTNode::FunctionExpression { parameters, body, .. } => {
+            let mut inner = renames.clone();
+            for (_, parameter) in parameters.iter() {
+                inner.remove(parameter);
+            }
+            for statement in body.iter_mut() {
+                rename(statement, &mut inner, true);
+            }
+            return;
+        }
+        ASTNode::For { variable, iterable, body } => {
+            rename(iterable, renames, in_function);
+            let mut inner = renames.clone();
+            inner.remove(variable.as_str());
+            for statement in body.iter_mut() {
+                rename(statement, &mut inner, in_function);
+            }
+            return;
+        }
+        ASTNode::Match { subject, arms, .. } => {
+            rename(subject, renames, in_function);
+            for arm in arms.iter_mut() {
+                let mut bindings = Vec::new();
+                pattern_bindings(&arm.pattern, &mut bindings);
+                let mut inner = renames.clone();
+                for binding in &bindings {
+                    inner.remove(binding);
+                }
+                for child in arm.guard.iter_mut().chain(arm.body.iter_mut()) {
+                    rename(child, &mut inner, in_function);
+                }
+            }
+            return;
+        }
+        ASTNode::Try { body, catch, finally } => {
+            for statement in body.iter_mut() {
+                rename(statement, renames, in_function);
+            }
+            if let Some((name, block)) = catch {
+                let mut inner = renames.clone();
+                inner.remove(name.as_str());
+                for statement in block.iter_mut() {
+                    rename(statement, &mut inner, in_function);
+                }
+            }
+            for statement in finally.iter_mut().flatten() {
+                rename(statement, renames, in_function);
+            }
+            return;
+        }
+        _ => {}
+    }
+    for child in node.children_mut() {
+        rename(child, renames, in_function);
+    }
+}
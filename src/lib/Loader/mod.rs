@@ -0,0 +1 @@
+pub mod loader;
\ No newline at end of file
@@ -0,0 +1 @@
+pub mod optimizer;
\ No newline at end of file
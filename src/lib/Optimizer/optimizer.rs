@@ -0,0 +1,469 @@
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use crate::lib::Asteva::asteva::{apply_binary_op, apply_unary_op};
+use crate::lib::Checker::constants::pattern_bindings;
+use crate::lib::Enums::astnode::ASTNode;
+use crate::lib::Enums::value::Value;
+
+/// Simplifies the program before it runs, without changing what it does.
+///
+/// * Operators whose operands are literals are replaced by their result
+///   (`60 * 60 * 24` becomes `86400`). An operation that fails, such as
+///   `1 / 0`, is left in place so that it still fails when it runs.
+/// * Identities are removed (`x + 0`, `x * 1`, `b && true`, ...) when the
+///   type of the remaining operand is known, as the operation would otherwise
+///   report an operand of the wrong type. See `static_type` for when it is.
+/// * `if`, `else if` and `while` with a literal condition lose the blocks that
+///   can never run, and a statement left with a single block is replaced by it.
+pub fn optimize(ast: &mut Vec<ASTNode>) {
+    let mut functions = HashSet::new();
+    for node in ast.iter() {
+        collect_functions(node, &mut functions);
+    }
+    let mut scope = Scope::function(&[], ast, &functions);
+    optimize_block(ast, &mut scope);
+}
+
+/// Records the name of every function declared anywhere in the tree.
+fn collect_functions(node: &ASTNode, functions: &mut HashSet<String>) {
+    if let ASTNode::FunctionDeclaration { name, .. } = node {
+        functions.insert(name.clone());
+    }
+    for child in node.children() {
+        collect_functions(child, functions);
+    }
+}
+
+/// The type of an expression, when it can be told from the form of the
+/// expression alone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StaticType {
+    Integer,
+    Float,
+    /// An integer or a float.
+    Number,
+    Boolean,
+}
+
+impl StaticType {
+    /// Returns true for the numeric types.
+    fn is_numeric(self) -> bool {
+        self != StaticType::Boolean
+    }
+
+    /// Returns the static type of a declared type, if it is one of them.
+    fn declared(type_name: &str) -> Option<Self> {
+        match type_name {
+            "integer" => Some(StaticType::Integer),
+            "float" => Some(StaticType::Float),
+            "boolean" => Some(StaticType::Boolean),
+            _ => None,
+        }
+    }
+}
+
+/// The variables and parameters of the function being optimized, or of the
+/// top level of the program, whose declared type they always hold.
+///
+/// Parameters are checked against their declared types when a function is
+/// called, but variables are not checked when they are written. A variable's
+/// declared type is therefore only relied on if every declaration of it and
+/// every assignment to it in the function writes a value of that type. Names
+/// bound in any other way, names assigned by a closure, which shares the
+/// variables it captures, and names of functions, which an identifier refers
+/// to until a variable of the same name is declared, are never relied on.
+struct Scope<'a> {
+    /// The declared type of every binding that always holds it.
+    typed: HashMap<String, StaticType>,
+    /// The bindings declared so far in each enclosing block, outermost first.
+    blocks: Vec<Vec<String>>,
+    /// The names of every function in the program.
+    functions: &'a HashSet<String>,
+}
+
+/// The ways a function writes its bindings, as found by `Scope::function`.
+#[derive(Default)]
+struct Writes<'a> {
+    /// Every variable or constant declaration, with its declared type and value.
+    declarations: Vec<(&'a str, &'a str, &'a ASTNode)>,
+    /// Every assignment, with the operator of a compound assignment.
+    assignments: Vec<(&'a str, Option<&'a str>, &'a ASTNode)>,
+    /// Names bound by `for`, `catch` or a match pattern, or assigned by a closure.
+    other: HashSet<String>,
+}
+
+impl<'a> Scope<'a> {
+    /// Finds the bindings of a function whose declared types can be relied on
+    /// inside its body.
+    ///
+    /// A binding is a candidate if it is declared with one numeric or boolean
+    /// type. Candidates are then dropped until every write to each remaining
+    /// one has its type, assuming the remaining ones hold theirs.
+    fn function(parameters: &[(String, String)], body: &[ASTNode], functions: &'a HashSet<String>) -> Self {
+        let mut writes = Writes::default();
+        for statement in body {
+            collect_writes(statement, &mut writes);
+        }
+        let mut declared: HashMap<&str, Option<StaticType>> = HashMap::new();
+        let declarations = parameters
+            .iter()
+            .map(|(type_name, name)| (name.as_str(), type_name.as_str()))
+            .chain(writes.declarations.iter().map(|(name, type_name, _)| (*name, *type_name)));
+        for (name, type_name) in declarations {
+            let static_type = StaticType::declared(type_name);
+            declared
+                .entry(name)
+                .and_modify(|existing| {
+                    if *existing != static_type {
+                        *existing = None;
+                    }
+                })
+                .or_insert(static_type);
+        }
+        let typed = declared
+            .into_iter()
+            .filter_map(|(name, static_type)| Some((name.to_string(), static_type?)))
+            .filter(|(name, _)| !writes.other.contains(name) && !functions.contains(name))
+            .collect();
+        let mut scope = Scope { typed, blocks: Vec::new(), functions };
+        loop {
+            // Every candidate is visible while the writes are checked
+            scope.blocks = vec![scope.typed.keys().cloned().collect()];
+            let mut wrong = Vec::new();
+            for (name, _, value) in &writes.declarations {
+                if scope.type_of(name).is_some_and(|expected| static_type(value, &scope) != Some(expected)) {
+                    wrong.push(name.to_string());
+                }
+            }
+            for (name, op, value) in &writes.assignments {
+                let Some(expected) = scope.type_of(name) else { continue };
+                let found = match op {
+                    Some(op) => binary_type(op, Some(expected), static_type(value, &scope)),
+                    None => static_type(value, &scope),
+                };
+                if found != Some(expected) {
+                    wrong.push(name.to_string());
+                }
+            }
+            if wrong.is_empty() {
+                break;
+            }
+            for name in wrong {
+                scope.typed.remove(&name);
+            }
+        }
+        scope.blocks = vec![parameters.iter().map(|(_, name)| name.clone()).collect()];
+        scope
+    }
+
+    /// Returns the type a binding visible here always holds, if it is known.
+    fn type_of(&self, name: &str) -> Option<StaticType> {
+        if self.blocks.iter().flatten().any(|declared| declared == name) {
+            self.typed.get(name).copied()
+        } else {
+            None
+        }
+    }
+}
+
+/// Collects the writes of a function's bindings in a node. Nested declared
+/// functions have bindings of their own and are left out; a closure may
+/// assign the variables it captures, so the names it assigns are collected
+/// as writes of unknown type.
+fn collect_writes<'a>(node: &'a ASTNode, writes: &mut Writes<'a>) {
+    match node {
+        ASTNode::VariableDeclaration { name, var_type: declared, value, .. }
+        | ASTNode::ConstantDeclaration { name, const_type: declared, value, .. } => {
+            writes.declarations.push((name, declared, value));
+        }
+        ASTNode::Assignment { identifier, value, .. } => writes.assignments.push((identifier, None, value)),
+        ASTNode::CompoundAssignment { op, target, value, .. } => {
+            if let ASTNode::Identifier(name) = target.as_ref() {
+                writes.assignments.push((name, Some(op), value));
+            }
+        }
+        ASTNode::For { variable, .. } => {
+            writes.other.insert(variable.clone());
+        }
+        ASTNode::Try { catch: Some((name, _)), .. } => {
+            writes.other.insert(name.clone());
+        }
+        ASTNode::Match { arms, .. } => {
+            let mut names = Vec::new();
+            for arm in arms {
+                pattern_bindings(&arm.pattern, &mut names);
+            }
+            writes.other.extend(names);
+        }
+        ASTNode::FunctionDeclaration { .. } => return,
+        ASTNode::FunctionExpression { body, .. } => {
+            for statement in body {
+                collect_closure_writes(statement, &mut writes.other);
+            }
+            return;
+        }
+        _ => {}
+    }
+    for child in node.children() {
+        collect_writes(child, writes);
+    }
+}
+
+/// Collects the names assigned anywhere in a closure's body.
+fn collect_closure_writes(node: &ASTNode, names: &mut HashSet<String>) {
+    match node {
+        ASTNode::Assignment { identifier, .. } => {
+            names.insert(identifier.clone());
+        }
+        ASTNode::CompoundAssignment { target, .. } => {
+            if let ASTNode::Identifier(name) = target.as_ref() {
+                names.insert(name.clone());
+            }
+        }
+        ASTNode::FunctionDeclaration { .. } => return,
+        _ => {}
+    }
+    for child in node.children() {
+        collect_closure_writes(child, names);
+    }
+}
+
+/// Optimizes every statement of a block, replacing each statement whose
+/// blocks were decided by a literal condition with the block that runs.
+fn optimize_block(block: &mut Vec<ASTNode>, scope: &mut Scope) {
+    let statements = mem::take(block);
+    let count = statements.len();
+    // A binding declared in the block may not have been declared after it
+    scope.blocks.push(Vec::new());
+    for (position, mut statement) in statements.into_iter().enumerate() {
+        optimize_node(&mut statement, scope);
+        if let ASTNode::VariableDeclaration { name, .. } | ASTNode::ConstantDeclaration { name, .. } = &statement {
+            scope.blocks.last_mut().expect("a block is open").push(name.clone());
+        }
+        match statement {
+            ASTNode::Conditional { branches, else_block, span } if branches.is_empty() => {
+                let taken = else_block.unwrap_or_default();
+                if taken.is_empty() && position + 1 == count {
+                    // The last statement gives the block its value, which must stay void
+                    block.push(ASTNode::Conditional { branches, else_block: None, span });
+                } else {
+                    block.extend(taken);
+                }
+            }
+            ASTNode::Loop { condition, span, .. } if matches!(*condition, ASTNode::BooleanLiteral(false)) => {
+                if position + 1 == count {
+                    block.push(ASTNode::Loop { condition, body: Vec::new(), span });
+                }
+            }
+            statement => block.push(statement),
+        }
+    }
+    scope.blocks.pop();
+}
+
+/// Optimizes a node and the nodes nested inside it.
+fn optimize_node(node: &mut ASTNode, scope: &mut Scope) {
+    match node {
+        ASTNode::Conditional { branches, else_block, .. } => {
+            for (condition, block) in branches.iter_mut() {
+                optimize_node(condition, scope);
+                optimize_block(block, scope);
+            }
+            if let Some(else_block) = else_block.as_mut() {
+                optimize_block(else_block, scope);
+            }
+            // Branches that can never be taken are dropped; a branch that is
+            // always taken becomes the else block and ends the chain
+            let mut remaining = Vec::new();
+            for (condition, block) in mem::take(branches) {
+                match condition {
+                    ASTNode::BooleanLiteral(false) => {}
+                    ASTNode::BooleanLiteral(true) => {
+                        *else_block = Some(block);
+                        break;
+                    }
+                    condition => remaining.push((condition, block)),
+                }
+            }
+            *branches = remaining;
+            // A conditional expression that always takes a one-statement block has that statement's value
+            if let ([], Some([_])) = (&branches[..], else_block.as_deref()) {
+                *node = else_block.take().and_then(|mut block| block.pop()).expect("the block has one statement");
+            }
+        }
+        ASTNode::Loop { condition, body, .. } => {
+            optimize_node(condition, scope);
+            optimize_block(body, scope);
+        }
+        ASTNode::For { iterable, body, .. } => {
+            optimize_node(iterable, scope);
+            optimize_block(body, scope);
+        }
+        ASTNode::FunctionDeclaration { parameters, body, .. } | ASTNode::FunctionExpression { parameters, body, .. } => {
+            let mut inner = Scope::function(parameters, body, scope.functions);
+            optimize_block(body, &mut inner);
+        }
+        ASTNode::Match { subject, arms, .. } => {
+            optimize_node(subject, scope);
+            for arm in arms.iter_mut() {
+                if let Some(guard) = arm.guard.as_mut() {
+                    optimize_node(guard, scope);
+                }
+                optimize_block(&mut arm.body, scope);
+            }
+        }
+        ASTNode::Try { body, catch, finally } => {
+            optimize_block(body, scope);
+            if let Some((_, block)) = catch {
+                optimize_block(block, scope);
+            }
+            if let Some(block) = finally {
+                optimize_block(block, scope);
+            }
+        }
+        _ => {
+            for child in node.children_mut() {
+                optimize_node(child, scope);
+            }
+            simplify(node, scope);
+        }
+    }
+}
+
+/// Folds or simplifies an operator or interpolated string whose operands have
+/// already been optimized.
+fn simplify(node: &mut ASTNode, scope: &Scope) {
+    let replacement = match node {
+        ASTNode::UnaryOp { op, operand, .. } => {
+            literal_value(operand).and_then(|value| apply_unary_op(op, value).ok()).and_then(|value| literal(&value))
+        }
+        ASTNode::BinaryOp { op, left, right, .. } if op == "And" || op == "Or" => {
+            simplify_logical(op == "And", left, right, scope)
+        }
+        ASTNode::BinaryOp { op, left, right, .. } => match (literal_value(left), literal_value(right)) {
+            (Some(a), Some(b)) => apply_binary_op(op, a, b).ok().and_then(|value| literal(&value)),
+            _ => simplify_identity(op, left, right, scope),
+        },
+        ASTNode::InterpolatedString(parts) => {
+            let mut text = String::new();
+            for part in parts.iter() {
+                match literal_value(part) {
+                    Some(value) => text.push_str(&value.to_string()),
+                    None => return,
+                }
+            }
+            Some(ASTNode::StringLiteral(text))
+        }
+        _ => None,
+    };
+    if let Some(replacement) = replacement {
+        *node = replacement;
+    }
+}
+
+/// Simplifies `&&` (`and` is true) or `||` with a literal operand.
+///
+/// The right operand is only dropped when the left decides the result, as
+/// `&&` and `||` would not have evaluated it either.
+fn simplify_logical(and: bool, left: &mut Box<ASTNode>, right: &mut Box<ASTNode>, scope: &Scope) -> Option<ASTNode> {
+    // The value that decides the result on its own: false for `&&`, true for `||`
+    let decisive = !and;
+    match (literal_value(left), literal_value(right)) {
+        (Some(Value::Boolean(a)), Some(Value::Boolean(b))) => Some(ASTNode::BooleanLiteral(if and { a && b } else { a || b })),
+        (Some(Value::Boolean(a)), _) if a == decisive => Some(ASTNode::BooleanLiteral(a)),
+        (Some(Value::Boolean(_)), _) if static_type(right, scope) == Some(StaticType::Boolean) => Some(take(right)),
+        (_, Some(Value::Boolean(b))) if b != decisive && static_type(left, scope) == Some(StaticType::Boolean) => {
+            Some(take(left))
+        }
+        _ => None,
+    }
+}
+
+/// Removes an arithmetic identity: adding or subtracting zero, or multiplying
+/// or dividing by one.
+///
+/// The other operand must be known to be a number whose type the operation
+/// keeps: an integer literal keeps any number, while a float literal would
+/// turn an integer into a float. `x + 0` is only removed for integers, as
+/// adding zero turns a float `-0.0` into `0.0`.
+fn simplify_identity(op: &str, left: &mut Box<ASTNode>, right: &mut Box<ASTNode>, scope: &Scope) -> Option<ASTNode> {
+    let keeps = |operand: &ASTNode, identity: &ASTNode| match (identity, static_type(operand, scope)) {
+        (ASTNode::IntegerLiteral(_), Some(operand)) => operand.is_numeric(),
+        (ASTNode::FloatLiteral(_), Some(operand)) => operand == StaticType::Float,
+        _ => false,
+    };
+    let is = |node: &ASTNode, identity: i32| match node {
+        ASTNode::IntegerLiteral(value) => *value == identity,
+        ASTNode::FloatLiteral(value) => *value == identity as f64,
+        _ => false,
+    };
+    let integer = |node: &ASTNode| static_type(node, scope) == Some(StaticType::Integer);
+    match op {
+        "Plus" if is(right, 0) && matches!(**right, ASTNode::IntegerLiteral(_)) && integer(left) => Some(take(left)),
+        "Plus" if is(left, 0) && matches!(**left, ASTNode::IntegerLiteral(_)) && integer(right) => Some(take(right)),
+        "Minus" if is(right, 0) && keeps(left, right) => Some(take(left)),
+        "Multiply" if is(right, 1) && keeps(left, right) => Some(take(left)),
+        "Multiply" if is(left, 1) && keeps(right, left) => Some(take(right)),
+        "Divide" if is(right, 1) && keeps(left, right) => Some(take(left)),
+        _ => None,
+    }
+}
+
+/// Returns the type of an expression if it can be told without running it.
+///
+/// Literals and the results of operators, which check their operands, have a
+/// known type, as do the bindings of `scope` whose declared type always holds.
+fn static_type(node: &ASTNode, scope: &Scope) -> Option<StaticType> {
+    match node {
+        ASTNode::IntegerLiteral(_) => Some(StaticType::Integer),
+        ASTNode::FloatLiteral(_) => Some(StaticType::Float),
+        ASTNode::BooleanLiteral(_) => Some(StaticType::Boolean),
+        ASTNode::Identifier(name) => scope.type_of(name),
+        ASTNode::UnaryOp { op, operand, .. } => match op.as_str() {
+            "Not" => Some(StaticType::Boolean),
+            _ => Some(static_type(operand, scope).filter(|operand| operand.is_numeric()).unwrap_or(StaticType::Number)),
+        },
+        ASTNode::BinaryOp { op, left, right, .. } => binary_type(op, static_type(left, scope), static_type(right, scope)),
+        _ => None,
+    }
+}
+
+/// Returns the type of the result of a binary operator, given the types of
+/// its operands where they are known.
+fn binary_type(op: &str, left: Option<StaticType>, right: Option<StaticType>) -> Option<StaticType> {
+    match op {
+        "Plus" | "Minus" | "Multiply" | "Divide" | "Modulo" => match (left, right) {
+            (Some(StaticType::Integer), Some(StaticType::Integer)) => Some(StaticType::Integer),
+            (Some(StaticType::Float), _) | (_, Some(StaticType::Float)) => Some(StaticType::Float),
+            _ => Some(StaticType::Number),
+        },
+        _ => Some(StaticType::Boolean),
+    }
+}
+
+/// Returns the value of a literal node.
+fn literal_value(node: &ASTNode) -> Option<Value> {
+    match node {
+        ASTNode::IntegerLiteral(value) => Some(Value::Integer(*value)),
+        ASTNode::FloatLiteral(value) => Some(Value::Float(*value)),
+        ASTNode::BooleanLiteral(value) => Some(Value::Boolean(*value)),
+        ASTNode::StringLiteral(value) => Some(Value::String(value.clone())),
+        _ => None,
+    }
+}
+
+/// Returns the literal node for a number, boolean or string value.
+pub fn literal(value: &Value) -> Option<ASTNode> {
+    match value {
+        Value::Integer(value) => Some(ASTNode::IntegerLiteral(*value)),
+        Value::Float(value) => Some(ASTNode::FloatLiteral(*value)),
+        Value::Boolean(value) => Some(ASTNode::BooleanLiteral(*value)),
+        Value::String(value) => Some(ASTNode::StringLiteral(value.clone())),
+        _ => None,
+    }
+}
+
+/// Moves a node out of its box, leaving a placeholder behind.
+fn take(node: &mut Box<ASTNode>) -> ASTNode {
+    mem::replace(&mut **node, ASTNode::NoOp)
+}
@@ -1,24 +1,61 @@
+use crate::lib::Checker::diagnostic::Diagnostic;
 use crate::lib::Enums::token::Token;
-use crate::lib::Enums::astnode::ASTNode;
+use crate::lib::Enums::astnode::{ASTNode, MatchArm, Pattern};
+use crate::lib::Enums::span::Span;
 
 /// Parser for parsing tokens into an Abstract Syntax Tree (AST).
 pub struct Parser<'a> {
     /// The slice of tokens to be parsed.
     pub tokens: &'a [Token],
+    /// The source position of each token, if known.
+    pub spans: &'a [Span],
     /// The current token being parsed.
     pub current_token: Option<&'a Token>,
     /// The current position in the token slice.
     pub position: usize,
+    /// Whether `Name {` may start a struct construction. This is switched off
+    /// where a `{` after an expression opens a block instead, as in `for i in 0..n {`.
+    pub allow_struct_literal: bool,
+    /// The namespaces introduced by the imports seen so far, so that
+    /// `alias::member` is read as a module member rather than an enum variant.
+    pub modules: Vec<String>,
 }
 
 impl<'a> Parser<'a> {
-    /// Creates a new `Parser` instance with the given tokens.
-    pub fn new(tokens: &'a [Token]) -> Self {
-        let current_token = tokens.get(0);
+    /// Creates a new `Parser` for tokens whose source positions are given by `spans`.
+    pub fn with_spans(tokens: &'a [Token], spans: &'a [Span]) -> Self {
+        let current_token = tokens.first();
         Parser {
             tokens,
+            spans,
             current_token,
             position: 0,
+            allow_struct_literal: true,
+            modules: Vec::new(),
+        }
+    }
+
+    /// Returns the source position of the current token, or the default span
+    /// if it is unknown.
+    pub fn current_span(&self) -> Span {
+        self.spans.get(self.position).copied().unwrap_or_default()
+    }
+
+    /// Returns a syntax error at the current token, or at the last token if
+    /// the input ended early.
+    pub fn error(&self, message: impl Into<String>) -> Diagnostic {
+        let span = match self.spans.get(self.position) {
+            Some(span) => *span,
+            None => self.spans.last().copied().unwrap_or_default(),
+        };
+        Diagnostic::error(message).at(span)
+    }
+
+    /// Describes the current token for a syntax error.
+    pub fn found(&self) -> String {
+        match self.current_token {
+            Some(token) => token.to_string(),
+            None => "the end of the file".to_string(),
         }
     }
 
@@ -28,299 +65,577 @@ impl<'a> Parser<'a> {
         self.current_token = self.tokens.get(self.position);
     }
 
-    /// Parses the tokens into an AST.
-    pub fn parse(&mut self) -> Vec<ASTNode> {
+    /// Consumes the current token if it is `expected`, otherwise returns a
+    /// syntax error with `message`.
+    pub fn expect(&mut self, expected: Token, message: &str) -> Result<(), Diagnostic> {
+        if self.current_token == Some(&expected) {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.error(message))
+        }
+    }
+
+    /// Consumes the current token if it is an identifier and returns its name,
+    /// otherwise returns a syntax error with `message`.
+    pub fn expect_identifier(&mut self, message: &str) -> Result<String, Diagnostic> {
+        match self.current_token {
+            Some(Token::Identifier(name)) => {
+                let name = name.clone();
+                self.advance(); // Consume identifier
+                Ok(name)
+            }
+            _ => Err(self.error(message)),
+        }
+    }
+
+    /// Parses the tokens into an AST, stopping at a closing brace.
+    pub fn parse(&mut self) -> Result<Vec<ASTNode>, Diagnostic> {
         let mut statements = Vec::new();
         while let Some(token) = self.current_token {
-            println!("Current token: {:?}", token); // Print current token
-            match token {
-                Token::Variable => {
-                    self.advance(); // Consume "variable"
-                    println!("I am inside the token::variable arm");
-                    let var_type = match self.current_token {
-                        Some(Token::Integer) => {
-                            println!("I am a integer");
-                            self.advance(); // Consume "integer"
-                            "integer".to_string()
-                        },
-                        Some(Token::Boolean) => {
-                            self.advance(); // Consume "boolean"
-                            "boolean".to_string()
-                        },
-                        _ => panic!("Expected variable type after 'variable' keyword"),
-                    };
-    
-                    let name = match self.current_token {
-                        Some(Token::Identifier(name)) => {
-                            println!("I am inside the identifier");
-                            println!("{:?}", self.current_token);
-                            name.clone()
-                        },
-                        _ => panic!("Expected identifier after variable type"),
-                    };
-                    self.advance(); // Consume identifier
-    
-                    if let Some(Token::Assign) = self.current_token {
-                        self.advance(); // Consume "="
-                        let value = self.parse_expression();
-                        if let Some(Token::Semicolon) = self.current_token {
-                            self.advance(); // Consume ";"
-                            statements.push(ASTNode::VariableDeclaration {
-                                var_type,
-                                name,
-                                value: Box::new(value),
-                            });
-                            println!("{:?}", statements);
-                        } else {
-                            panic!("Expected semicolon after variable declaration");
-                        }
-                    } else {
-                        panic!("Expected assignment operator after variable declaration");
-                    }
+            if let Token::RBrace = token {
+                break; // Exit the loop when encountering a closing brace
+            }
+            statements.push(self.parse_statement()?);
+        }
+        Ok(statements)
+    }
+
+    /// Parses a single statement.
+    pub fn parse_statement(&mut self) -> Result<ASTNode, Diagnostic> {
+        let token = match self.current_token {
+            Some(token) => token,
+            None => return Err(self.error("Expected a statement")),
+        };
+        let statement = match token {
+            Token::Variable | Token::Constant => self.parse_declaration()?,
+            Token::Public => {
+                self.advance(); // Consume "public"
+                if !matches!(self.current_token, Some(Token::Function) | Some(Token::Constant)) {
+                    return Err(self.error("Only functions and constants can be declared public"));
                 }
-                Token::Function => {
-                    self.advance(); // Consume "function"
-                    
-                    // Parse return type
-                    let return_type = match self.current_token {
-                        Some(Token::Integer) => {
-                            self.advance(); // Consume "integer"
-                            "integer".to_string()
-                        },
-                        Some(Token::Boolean) => {
-                            self.advance(); // Consume "boolean"
-                            "boolean".to_string()
-                        },
-                        _ => panic!("Expected return type after 'function' keyword"),
-                    };
-                    
-                    // Parse function name
-                    let name = match self.current_token {
-                        Some(Token::Identifier(name)) => {
-                            self.advance(); // Consume function name
-                            name.clone()
-                        },
-                        _ => panic!("Expected function name after return type"),
-                    };
-                    
-                    // Parse parameter list
-                    if let Some(Token::LParen) = self.current_token {
-                        self.advance(); // Consume "("
-                        let parameters = self.parse_parameters();
-                        if let Some(Token::RParen) = self.current_token {
-                            self.advance(); // Consume ")"
+                let mut declaration = self.parse_statement()?;
+                if let ASTNode::FunctionDeclaration { public, .. } | ASTNode::ConstantDeclaration { public, .. } =
+                    &mut declaration
+                {
+                    *public = true;
+                }
+                declaration
+            },
+            Token::Import => self.parse_import()?,
+            Token::Function => {
+                let span = self.current_span();
+                self.advance(); // Consume "function"
+                
+                // Parse return type
+                let return_type = match self.parse_return_type()? {
+                    Some(return_type) => return_type,
+                    None => return Err(self.error("Expected return type after 'function' keyword")),
+                };
+                
+                // Parse function name
+                let name = match self.current_token {
+                    Some(Token::Identifier(name)) => {
+                        self.advance(); // Consume function name
+                        name.clone()
+                    },
+                    _ => return Err(self.error("Expected function name after return type")),
+                };
+                
+                // Parse parameter list
+                if let Some(Token::LParen) = self.current_token {
+                    self.advance(); // Consume "("
+                    let parameters = self.parse_parameters()?;
+                    if let Some(Token::RParen) = self.current_token {
+                        self.advance(); // Consume ")"
+                        
+                        // Parse function body
+                        if let Some(Token::LBrace) = self.current_token {
+                            self.advance(); // Consume "{"
+                            let body = self.parse_block()?;
                             
-                            // Parse function body
-                            if let Some(Token::LBrace) = self.current_token {
-                                self.advance(); // Consume "{"
-                                let body = self.parse_block();
-                                
-                                // Create FunctionDeclaration node
-                                statements.push(ASTNode::FunctionDeclaration {
-                                    name,
-                                    return_type,
-                                    parameters,
-                                    body,
-                                });
-                            } else {
-                                panic!("Expected function body after parameters");
+                            // Create FunctionDeclaration node
+                            ASTNode::FunctionDeclaration {
+                                name,
+                                return_type,
+                                parameters,
+                                body,
+                                public: false,
+                                span,
                             }
                         } else {
-                            panic!("Expected closing parenthesis after parameters");
+                            return Err(self.error("Expected function body after parameters"));
                         }
                     } else {
-                        panic!("Expected opening parenthesis after function name");
+                        return Err(self.error("Expected closing parenthesis after parameters"));
                     }
-                },
-                Token::Return => {
-                    self.advance(); // Consume "return"
-                    let value = self.parse_expression();
-                    if let Some(Token::Semicolon) = self.current_token {
-                        self.advance(); // Consume ";"
-                        statements.push(ASTNode::ReturnStatement(Box::new(value)));
-                    } else {
-                        panic!("Expected semicolon after return statement");
-                    }
-                },
-                Token::RBrace => {
-                    // Consume "}"
-                    
-                    break; // Exit the loop when encountering a closing brace
-                },
-                Token::Loop => {
-                    self.advance(); // Consume "loop"
-                    self.advance(); // Consume "("
-                    let condition = self.parse_expression(); // Parse the loop condition
-                    self.advance(); // Consume ")"
-                    if let Some(Token::LBrace) = self.current_token {
-                        self.advance(); // Consume "{"
-                        let body = self.parse_block(); // Parse the block of statements inside the loop
-                        statements.push(ASTNode::Loop { condition:Box::new(condition), body:body });
-                    } else {
-                        panic!("Expected opening brace after loop condition");
-                    }
-                },
-                Token::Identifier(_) => {
-                    let identifier_name = match self.current_token {
-                        Some(Token::Identifier(name)) => name.clone(),
-                        _ => panic!("Expected identifier"),
+                } else {
+                    return Err(self.error("Expected opening parenthesis after function name"));
+                }
+            },
+            Token::Struct => {
+                self.advance(); // Consume "struct"
+                let name = self.expect_identifier("Expected struct name after 'struct'")?;
+                self.expect(Token::LBrace, "Expected opening brace after struct name")?;
+
+                // Parse fields of the form `type name;`
+                let mut fields = Vec::new();
+                while !matches!(self.current_token, Some(Token::RBrace) | None) {
+                    let field_type = match self.parse_type()? {
+                        Some(field_type) => field_type,
+                        None => return Err(self.error(format!("Expected field type in struct '{}'", name))),
                     };
-                    
-                    self.advance(); // Consume identifier
-                
-                    if let Some(Token::Assign) = self.current_token {
-                        self.advance(); // Consume "="
-                        let value = self.parse_expression(); // Parse the value to be assigned
-                
-                        if let Some(Token::Semicolon) = self.current_token {
-                            self.advance(); // Consume ";"
-                            let assignment_node = ASTNode::Assignment {
-                                identifier: identifier_name,
-                                value: Box::new(value),
-                            };
-                            statements.push(assignment_node);
-                        } else {
-                            panic!("Expected semicolon after assignment");
-                        }
-                    } else {
-                        panic!("Expected assignment operator after identifier");
+                    let field_name = self.expect_identifier("Expected field name after field type")?;
+                    self.expect(Token::Semicolon, "Expected semicolon after struct field")?;
+                    if fields.iter().any(|(_, existing)| *existing == field_name) {
+                        return Err(self.error(format!("Duplicate field '{}' in struct '{}'", field_name, name)));
                     }
-                },
-                Token::If => {
-                    self.advance(); // Consume "if"
-                
-                    // Parse condition
+                    fields.push((field_type, field_name));
+                }
+                self.expect(Token::RBrace, "Expected closing brace after struct fields")?;
+
+                ASTNode::StructDeclaration { name, fields }
+            },
+            Token::Enum => {
+                self.advance(); // Consume "enum"
+                let name = self.expect_identifier("Expected enum name after 'enum'")?;
+                self.expect(Token::LBrace, "Expected opening brace after enum name")?;
+
+                // Parse variants of the form `Name` or `Name(type, ...)`
+                let mut variants: Vec<(String, Vec<String>)> = Vec::new();
+                while !matches!(self.current_token, Some(Token::RBrace) | None) {
+                    let variant = self.expect_identifier("Expected variant name in enum declaration")?;
+                    let mut payload = Vec::new();
                     if let Some(Token::LParen) = self.current_token {
                         self.advance(); // Consume "("
-                        let condition = self.parse_expression();
-                        if let Some(Token::RParen) = self.current_token {
-                            self.advance(); // Consume ")"
-                
-                            // Parse if block
-                            if let Some(Token::LBrace) = self.current_token {
-                                self.advance(); // Consume "{"
-                                let if_block = self.parse_block();
-                
-                                // Parse else block
-                                let else_block = if let Some(Token::Else) = self.current_token {
-                                    self.advance(); // Consume "else"
-                                    if let Some(Token::LBrace) = self.current_token {
-                                        self.advance(); // Consume "{"
-                                        let else_block = self.parse_block();
-                                        Some(else_block)
-                                    } else {
-                                        panic!("Expected block after 'else'");
-                                    }
-                                } else {
-                                    None
-                                };
-                
-                                // Create IfElse node
-                                statements.push(ASTNode::IfElse {
-                                    condition: Box::new(condition),
-                                    if_block,
-                                    else_block,
-                                });
+                        while !matches!(self.current_token, Some(Token::RParen)) {
+                            match self.parse_type()? {
+                                Some(payload_type) => payload.push(payload_type),
+                                None => return Err(self.error(format!("Expected payload type for variant '{}'", variant))),
+                            }
+                            if let Some(Token::Comma) = self.current_token {
+                                self.advance(); // Consume ","
                             } else {
-                                panic!("Expected block after if condition");
+                                break;
                             }
-                        } else {
-                            panic!("Expected closing parenthesis after if condition");
                         }
-                    } else {
-                        panic!("Expected opening parenthesis after 'if'");
+                        self.expect(Token::RParen, "Expected closing parenthesis after variant payload")?;
                     }
-                },
-                Token::Else => {
-                    self.advance(); // Consume "else"
-                    
-                    // Parse else block
-                    if let Some(Token::LBrace) = self.current_token {
-                        self.advance(); // Consume "{"
-                        let else_block = self.parse_block();
-                        
-                        statements.push(ASTNode::ElseStatement{
-                            body: else_block,
-                        });
-                    } else {
-                        panic!("Expected block after 'else'");
+                    if variants.iter().any(|(existing, _)| *existing == variant) {
+                        return Err(self.error(format!("Duplicate variant '{}' in enum '{}'", variant, name)));
                     }
-                },
-                Token::Print => {
-                    self.advance(); // Consume "print"
-                    let expr = self.parse_expression();
-                    if let Some(Token::Semicolon) = self.current_token {
-                        self.advance(); // Consume ";"
-                        
-                        statements.push(ASTNode::PrintStatement { context: Box::new(expr) });
+                    variants.push((variant, payload));
+                    if let Some(Token::Comma) = self.current_token {
+                        self.advance(); // Consume ","
                     } else {
-                        panic!("Expected semicolon after print statement");
+                        break;
                     }
-                
-                },
-                Token::Call => {
-                    println!("I am hereee which is the call");
-                    self.advance(); // Consume "call"
-                    let function_name = match self.current_token {
-                        Some(Token::Identifier(name)) => name.clone(),
-                        _ => panic!("Expected function identifier after 'call'"),
-                    };
-                    self.advance(); // Consume function identifier
-                    self.advance(); // Consume "("
-                    let arguments = self.parse_argument_list(); // Parse function arguments
-                    self.advance(); // Consume ")"
+                }
+                self.expect(Token::RBrace, "Expected closing brace after enum variants")?;
+
+                ASTNode::EnumDeclaration { name, variants }
+            },
+            Token::Match => self.parse_match()?,
+            Token::Return => {
+                let span = self.current_span();
+                self.advance(); // Consume "return"
+                // A `void` function returns without a value
+                let value = match self.current_token {
+                    Some(Token::Semicolon) => None,
+                    _ => Some(Box::new(self.parse_expression()?)),
+                };
+                if let Some(Token::Semicolon) = self.current_token {
                     self.advance(); // Consume ";"
-                
-                    let boxed_arguments: Vec<Box<ASTNode>> = arguments.into_iter().map(Box::new).collect();
-                    let function_call_node = ASTNode::FunctionCall {
-                        name: function_name.clone(),
-                        arguments: boxed_arguments,
+                    ASTNode::ReturnStatement { value, span }
+                } else {
+                    return Err(self.error("Expected semicolon after return statement"));
+                }
+            },
+            Token::Loop | Token::While => {
+                let span = self.current_span();
+                self.advance(); // Consume "loop" or "while"
+                self.expect(Token::LParen, "Expected opening parenthesis after 'while'")?;
+                let condition = self.parse_expression()?; // Parse the loop condition
+                self.expect(Token::RParen, "Expected closing parenthesis after loop condition")?;
+                if let Some(Token::LBrace) = self.current_token {
+                    self.advance(); // Consume "{"
+                    let body = self.parse_block()?; // Parse the block of statements inside the loop
+                    ASTNode::Loop { condition:Box::new(condition), body, span }
+                } else {
+                    return Err(self.error("Expected opening brace after loop condition"));
+                }
+            },
+            Token::For => {
+                self.advance(); // Consume "for"
+                let variable = self.expect_identifier("Expected loop variable after 'for'")?;
+                self.expect(Token::In, "Expected 'in' after loop variable")?;
+
+                // The iterable is followed directly by the body's "{"
+                self.allow_struct_literal = false;
+                let mut iterable = self.parse_expression()?;
+                if let Some(Token::DotDot) = self.current_token {
+                    self.advance(); // Consume ".."
+                    let end = self.parse_expression()?;
+                    iterable = ASTNode::Range {
+                        start: Box::new(iterable),
+                        end: Box::new(end),
                     };
+                }
+                self.allow_struct_literal = true;
+
+                self.expect(Token::LBrace, "Expected opening brace after for loop header")?;
+                let body = self.parse_block()?;
+                ASTNode::For {
+                    variable,
+                    iterable: Box::new(iterable),
+                    body,
+                }
+            },
+            Token::Break => {
+                let span = self.current_span();
+                self.advance(); // Consume "break"
+                self.expect(Token::Semicolon, "Expected semicolon after 'break'")?;
+                ASTNode::Break { span }
+            },
+            Token::Continue => {
+                let span = self.current_span();
+                self.advance(); // Consume "continue"
+                self.expect(Token::Semicolon, "Expected semicolon after 'continue'")?;
+                ASTNode::Continue { span }
+            },
+            Token::If => self.parse_conditional(false)?,
+            Token::Throw => {
+                let span = self.current_span();
+                self.advance(); // Consume "throw"
+                let value = self.parse_expression()?;
+                self.expect(Token::Semicolon, "Expected semicolon after thrown value")?;
+                ASTNode::Throw { value: Box::new(value), span }
+            },
+            Token::Try => self.parse_try()?,
+            Token::Print => {
+                let span = self.current_span();
+                self.advance(); // Consume "print"
+                let mut arguments = Vec::new();
+                if self.current_token != Some(&Token::Semicolon) {
+                    arguments.push(self.parse_expression()?);
+                    while let Some(Token::Comma) = self.current_token {
+                        self.advance(); // Consume ","
+                        arguments.push(self.parse_expression()?);
+                    }
+                }
+                if let Some(Token::Semicolon) = self.current_token {
+                    self.advance(); // Consume ";"
+                    
+                    ASTNode::PrintStatement { arguments, span }
+                } else {
+                    return Err(self.error("Expected semicolon after print statement"));
+                }
+            
+            },
+            _ => {
+                // An assignment, or any other expression used as a statement
+                let span = self.current_span();
+                let expression = self.parse_expression()?;
+                self.finish_expression_statement(expression, span)?
+            }
+        };
+        Ok(statement)
+    }
+
+    /// Parse a declaration such as `variable integer x = 5;` or
+    /// `constant integer MAX = 10 * 10;`.
+    pub fn parse_declaration(&mut self) -> Result<ASTNode, Diagnostic> {
+        let span = self.current_span();
+        let constant = matches!(self.current_token, Some(Token::Constant));
+        let keyword = if constant { "constant" } else { "variable" };
+        self.advance(); // Consume "variable" or "constant"
 
-                    statements.push(function_call_node);
+        let declared_type = match self.parse_type()? {
+            Some(declared_type) => declared_type,
+            None => return Err(self.error(format!("Expected {} type after '{}' keyword", keyword, keyword))),
+        };
+        let name = self.expect_identifier(&format!("Expected identifier after {} type", keyword))?;
+        self.expect(Token::Assign, &format!("Expected assignment operator after {} declaration", keyword))?;
+        let value = Box::new(self.parse_expression()?);
+        self.expect(Token::Semicolon, &format!("Expected semicolon after {} declaration", keyword))?;
+
+        if constant {
+            Ok(ASTNode::ConstantDeclaration {
+                name,
+                value,
+                const_type: declared_type,
+                public: false,
+                span,
+            })
+        } else {
+            Ok(ASTNode::VariableDeclaration {
+                name,
+                value,
+                var_type: declared_type,
+                span,
+            })
+        }
+    }
+
+    /// Completes a statement that starts with an already parsed expression:
+    /// `target = value;`, a compound assignment such as `target += value;`,
+    /// `target++;`, `target--;`, or the bare expression followed by `;`.
+    pub fn finish_expression_statement(&mut self, expression: ASTNode, span: Span) -> Result<ASTNode, Diagnostic> {
+        let statement = match self.current_token {
+            Some(Token::Assign) => {
+                self.advance(); // Consume "="
+                let value = self.parse_expression()?; // Parse the value to be assigned
+                self.expect(Token::Semicolon, "Expected semicolon after assignment")?;
+                Self::assignment(expression, value, span)?
+            }
+            Some(token @ (Token::PlusAssign
+            | Token::MinusAssign
+            | Token::MultiplyAssign
+            | Token::DivideAssign
+            | Token::ModuloAssign)) => {
+                self.advance(); // Consume the compound assignment operator
+                let op = match token {
+                    Token::PlusAssign => "Plus",
+                    Token::MinusAssign => "Minus",
+                    Token::MultiplyAssign => "Multiply",
+                    Token::DivideAssign => "Divide",
+                    _ => "Modulo",
+                };
+                let value = self.parse_expression()?;
+                self.expect(Token::Semicolon, "Expected semicolon after assignment")?;
+                Self::compound_assignment(expression, op, value, span)?
+            }
+            Some(token @ (Token::Increment | Token::Decrement)) => {
+                self.advance(); // Consume "++" or "--"
+                let op = if let Token::Increment = token { "Plus" } else { "Minus" };
+                self.expect(Token::Semicolon, "Expected semicolon after increment or decrement")?;
+                Self::compound_assignment(expression, op, ASTNode::IntegerLiteral(1), span)?
+            }
+            Some(Token::Semicolon) => {
+                self.advance(); // Consume ";"
+                expression
+            }
+            _ => return Err(self.error(format!("Expected ';' after expression, found {}", self.found()))),
+        };
+        Ok(statement)
+    }
+
+    /// Parse an import of another file, given either as a file name
+    /// (`import "math.known";`) or as a module path (`import util::strings;`,
+    /// which names the file `util/strings.known`). The module's items are used
+    /// through its file or last path segment name, or through the name given
+    /// after `as`.
+    pub fn parse_import(&mut self) -> Result<ASTNode, Diagnostic> {
+        let span = self.current_span();
+        self.advance(); // Consume "import"
+        let (path, default_alias) = match self.current_token {
+            Some(Token::StringLiteral(path)) => {
+                self.advance(); // Consume file name
+                let stem = std::path::Path::new(path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                (path.clone(), stem)
+            }
+            Some(Token::Identifier(_)) => {
+                let mut segments = vec![self.expect_identifier("Expected module name after 'import'")?];
+                while let Some(Token::DoubleColon) = self.current_token {
+                    self.advance(); // Consume "::"
+                    segments.push(self.expect_identifier("Expected module name after '::'")?);
                 }
-                _ => panic!("Unexpected this is comes from  token {:?}", token),
+                let last = segments[segments.len() - 1].clone();
+                (format!("{}.known", segments.join("/")), last)
             }
+            _ => return Err(self.error("Expected a file name or module path after 'import'")),
+        };
+        let alias = if let Some(Token::As) = self.current_token {
+            self.advance(); // Consume "as"
+            self.expect_identifier("Expected a name after 'as'")?
+        } else {
+            default_alias
+        };
+        self.expect(Token::Semicolon, "Expected semicolon after import")?;
+        self.modules.push(alias.clone());
+        Ok(ASTNode::Import { path, alias, span })
+    }
+
+    /// Builds the assignment node that writes `value` to `target`, which must be
+    /// a variable, an indexed element or a struct field. Any other target is an
+    /// error at `span`, the start of the statement.
+    pub fn assignment(target: ASTNode, value: ASTNode, span: Span) -> Result<ASTNode, Diagnostic> {
+        match target {
+            ASTNode::Identifier(identifier) => Ok(ASTNode::Assignment {
+                identifier,
+                value: Box::new(value),
+                span,
+            }),
+            ASTNode::Index { target, index, .. } => Ok(ASTNode::IndexAssignment {
+                target,
+                index,
+                value: Box::new(value),
+            }),
+            ASTNode::FieldAccess { target, field, span } => Ok(ASTNode::FieldAssignment {
+                target,
+                field,
+                value: Box::new(value),
+                span,
+            }),
+            _ => Err(Diagnostic::error("Invalid assignment target").at(span)),
         }
-        statements
     }
-    
-    
+
+    /// Builds the node that updates `target` in place by applying the binary
+    /// operator `op` with `value` as its right operand. Like `assignment`, any
+    /// target but a variable, an indexed element or a struct field is an error.
+    pub fn compound_assignment(target: ASTNode, op: &str, value: ASTNode, span: Span) -> Result<ASTNode, Diagnostic> {
+        match target {
+            ASTNode::Identifier(_) | ASTNode::Index { .. } | ASTNode::FieldAccess { .. } => Ok(ASTNode::CompoundAssignment {
+                target: Box::new(target),
+                op: op.to_string(),
+                value: Box::new(value),
+                span,
+            }),
+            _ => Err(Diagnostic::error("Invalid assignment target").at(span)),
+        }
+    }
+
+    /// Parse `try { } catch (e) { } finally { }`, where either the `catch` or
+    /// the `finally` block may be left out.
+    pub fn parse_try(&mut self) -> Result<ASTNode, Diagnostic> {
+        self.advance(); // Consume "try"
+        self.expect(Token::LBrace, "Expected opening brace after 'try'")?;
+        let body = self.parse_block()?;
+        let catch = match self.current_token {
+            Some(Token::Catch) => {
+                self.advance(); // Consume "catch"
+                self.expect(Token::LParen, "Expected '(' after 'catch'")?;
+                let name = self.expect_identifier("Expected a name for the caught error")?;
+                self.expect(Token::RParen, "Expected ')' after the caught error's name")?;
+                self.expect(Token::LBrace, "Expected opening brace after 'catch'")?;
+                Some((name, self.parse_block()?))
+            }
+            _ => None,
+        };
+        let finally = match self.current_token {
+            Some(Token::Finally) => {
+                self.advance(); // Consume "finally"
+                self.expect(Token::LBrace, "Expected opening brace after 'finally'")?;
+                Some(self.parse_block()?)
+            }
+            _ => None,
+        };
+        if catch.is_none() && finally.is_none() {
+            return Err(self.error("Expected 'catch' or 'finally' after 'try' block"));
+        }
+        Ok(ASTNode::Try { body, catch, finally })
+    }
+
+    /// Parse an `if` with any number of `else if` arms and an optional `else`.
+    ///
+    /// As a statement the blocks hold ordinary statements. As an expression
+    /// (`if (a > b) { a } else { b }`) each block may end in an expression
+    /// without a semicolon, which becomes the value of the whole `if`.
+    pub fn parse_conditional(&mut self, as_expression: bool) -> Result<ASTNode, Diagnostic> {
+        let span = self.current_span();
+        let mut branches = Vec::new();
+        let mut else_block = None;
+        loop {
+            self.expect(Token::If, "Expected 'if'")?;
+            self.expect(Token::LParen, "Expected opening parenthesis after 'if'")?;
+            let condition = self.parse_expression()?;
+            self.expect(Token::RParen, "Expected closing parenthesis after if condition")?;
+            self.expect(Token::LBrace, "Expected block after if condition")?;
+            let block = if as_expression { self.parse_value_block()? } else { self.parse_block()? };
+            branches.push((condition, block));
+
+            if let Some(Token::Else) = self.current_token {
+                self.advance(); // Consume "else"
+            } else {
+                break;
+            }
+            if let Some(Token::If) = self.current_token {
+                continue; // An `else if` arm
+            }
+            self.expect(Token::LBrace, "Expected block after 'else'")?;
+            else_block = Some(if as_expression { self.parse_value_block()? } else { self.parse_block()? });
+            break;
+        }
+        Ok(ASTNode::Conditional { branches, else_block, span })
+    }
+
     /// Parse expression according to defined rules
-    pub fn parse_expression(&mut self) -> ASTNode {
-        let mut left = self.parse_term();
+    pub fn parse_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        self.parse_binary(1)
+    }
+
+    /// Returns the binding strength of a binary operator, or `None` if the
+    /// token is not one. Higher values bind more tightly; every level is
+    /// left-associative.
+    pub fn binary_precedence(token: &Token) -> Option<u8> {
+        match token {
+            Token::Or => Some(1),
+            Token::And => Some(2),
+            Token::Equals | Token::NotEquals => Some(3),
+            Token::LessThan | Token::LessThanOrEqual | Token::GreaterThan | Token::GreaterThanOrEqual => Some(4),
+            Token::Plus | Token::Minus => Some(5),
+            Token::Multiply | Token::Divide | Token::Modulo => Some(6),
+            _ => None,
+        }
+    }
+
+    /// Parse a chain of binary operations whose operators bind at least as
+    /// tightly as `min_precedence`, so that `a + b * c` groups as `a + (b * c)`
+    /// and `a || b && c` as `a || (b && c)`.
+    pub fn parse_binary(&mut self, min_precedence: u8) -> Result<ASTNode, Diagnostic> {
+        let mut left = self.parse_unary()?;
 
         while let Some(token) = self.current_token {
-            match token {
-                Token::Plus | Token::Minus | Token::Star | Token::Slash | Token::Percent
-                | Token::Equals | Token::NotEquals | Token::LessThan | Token::LessThanOrEqual
-                | Token::GreaterThan | Token::GreaterThanOrEqual | Token::And | Token::Or => {
-                    self.advance(); // Consume operator
-                    let right = self.parse_term();
-                    left = ASTNode::BinaryOp {
-                        op: token.to_string(),
-                        left: Box::new(left),
-                        right: Box::new(right),
-                    };
-                }
+            let precedence = match Self::binary_precedence(token) {
+                Some(precedence) if precedence >= min_precedence => precedence,
                 _ => break,
-            }
+            };
+            let span = self.current_span();
+            self.advance(); // Consume operator
+            let right = self.parse_binary(precedence + 1)?;
+            left = ASTNode::BinaryOp {
+                op: token.to_string(),
+                left: Box::new(left),
+                right: Box::new(right),
+                span,
+            };
         }
 
-        left
+        Ok(left)
+    }
+
+    /// Parse an operand that may be preceded by unary `-` or `!`.
+    pub fn parse_unary(&mut self) -> Result<ASTNode, Diagnostic> {
+        match self.current_token {
+            Some(token @ (Token::Minus | Token::Not)) => {
+                let span = self.current_span();
+                self.advance(); // Consume "-" or "!"
+                let operand = self.parse_unary()?;
+                Ok(ASTNode::UnaryOp {
+                    op: token.to_string(),
+                    operand: Box::new(operand),
+                    span,
+                })
+            }
+            _ => self.parse_term(),
+        }
     }
 
     /// Parse arguments of function definition according to rules
-    pub fn parse_argument_list(&mut self) -> Vec<ASTNode> {
+    pub fn parse_argument_list(&mut self) -> Result<Vec<ASTNode>, Diagnostic> {
         let mut arguments = Vec::new();
     
         // Parse the first argument if it exists
         if let Some(Token::RParen) = self.current_token {
-            return arguments;
+            return Ok(arguments);
         }
     
         loop {
-            let argument = self.parse_expression(); // Parse the argument expression
+            let argument = self.parse_expression()?; // Parse the argument expression
             arguments.push(argument);
     
             if let Some(Token::Comma) = self.current_token {
@@ -330,68 +645,445 @@ impl<'a> Parser<'a> {
             }
         }
     
-        arguments
+        Ok(arguments)
     }
     
-    /// Parse term of function definition according to rules
-    pub fn parse_term(&mut self) -> ASTNode {
-        match self.current_token {
+    /// Parse term of function definition according to rules, including any
+    /// trailing index, field and call operations such as `xs[i].name` or `f(x)(y)`.
+    pub fn parse_term(&mut self) -> Result<ASTNode, Diagnostic> {
+        let mut term = self.parse_primary()?;
+
+        loop {
+            let span = self.current_span();
+            match self.current_token {
+                Some(Token::LBracket) => {
+                    self.advance(); // Consume "["
+                    let index = self.parse_expression()?;
+                    self.expect(Token::RBracket, "Expected closing bracket after index")?;
+                    term = ASTNode::Index {
+                        target: Box::new(term),
+                        index: Box::new(index),
+                        span,
+                    };
+                }
+                Some(Token::Dot) => {
+                    self.advance(); // Consume "."
+                    let field = self.expect_identifier("Expected field name after '.'")?;
+                    term = ASTNode::FieldAccess {
+                        target: Box::new(term),
+                        field,
+                        span,
+                    };
+                }
+                Some(Token::LParen) => {
+                    self.advance(); // Consume "("
+                    let arguments = self.parse_argument_list()?;
+                    self.expect(Token::RParen, "Expected closing parenthesis after arguments")?;
+                    term = match term {
+                        // Calling a name directly: `name(args)`
+                        ASTNode::Identifier(name) => ASTNode::FunctionCall { name, arguments, span },
+                        // Calling the value of any other expression, e.g. `make_adder(1)(2)`
+                        callee => ASTNode::CallExpression {
+                            callee: Box::new(callee),
+                            arguments,
+                            span,
+                        },
+                    };
+                }
+                _ => break,
+            }
+        }
+
+        Ok(term)
+    }
+
+    /// Parse a single primary expression without any trailing index, field or call operations
+    pub fn parse_primary(&mut self) -> Result<ASTNode, Diagnostic> {
+        let primary = match self.current_token {
             Some(Token::RBrace) => {
                 // Exit the loop when encountering a closing brace
                 self.advance(); // Consume "}"
-                return ASTNode::NoOp; // NoOp or some other way to represent an empty statement
+                ASTNode::NoOp // NoOp or some other way to represent an empty statement
             },
             Some(Token::IntegerLiteral(value)) => {
                 self.advance(); // Consume integer literal
                 ASTNode::IntegerLiteral(*value)
             }
+            Some(Token::FloatLiteral(value)) => {
+                self.advance(); // Consume float literal
+                ASTNode::FloatLiteral(*value)
+            }
             Some(Token::BooleanLiteral(value)) => {
                 self.advance(); // Consume boolean literal
                 ASTNode::BooleanLiteral(*value)
             }
+            Some(Token::StringLiteral(value)) => {
+                self.advance(); // Consume string literal
+                ASTNode::StringLiteral(value.clone())
+            }
+            Some(Token::StringPart(_)) => self.parse_interpolated_string()?,
             Some(Token::Identifier(name)) => {
+                let span = self.current_span();
                 self.advance(); // Consume identifier
-                println!("no problem we are here");
-                ASTNode::Identifier(name.clone())
+                match self.current_token {
+                    Some(Token::LBrace) if self.allow_struct_literal => self.parse_struct_literal(name.clone(), span)?,
+                    Some(Token::DoubleColon) if self.modules.contains(name) => {
+                        // A member of an imported module, resolved by the module loader
+                        self.advance(); // Consume "::"
+                        let member = self.expect_identifier("Expected module member after '::'")?;
+                        ASTNode::Identifier(format!("{}::{}", name, member))
+                    }
+                    Some(Token::DoubleColon) => {
+                        self.advance(); // Consume "::"
+                        let variant = self.expect_identifier("Expected variant name after '::'")?;
+                        let mut arguments = Vec::new();
+                        if let Some(Token::LParen) = self.current_token {
+                            self.advance(); // Consume "("
+                            arguments = self.parse_argument_list()?;
+                            self.expect(Token::RParen, "Expected closing parenthesis after variant payload")?;
+                        }
+                        ASTNode::EnumVariant {
+                            enum_name: name.clone(),
+                            variant,
+                            arguments,
+                            span,
+                        }
+                    }
+                    _ => ASTNode::Identifier(name.clone()),
+                }
             },
+            Some(Token::Match) => self.parse_match()?,
+            Some(Token::If) => self.parse_conditional(true)?,
+            Some(Token::Function) => {
+                // Anonymous function: `function(integer a) -> integer { ... }`
+                let span = self.current_span();
+                self.advance(); // Consume "function"
+                self.expect(Token::LParen, "Expected opening parenthesis after 'function'")?;
+                let parameters = self.parse_parameters()?;
+                self.expect(Token::RParen, "Expected closing parenthesis after parameters")?;
+                self.expect(Token::Arrow, "Expected '->' and a return type after anonymous function parameters")?;
+                let return_type = match self.parse_return_type()? {
+                    Some(return_type) => return_type,
+                    None => return Err(self.error("Expected return type after '->'")),
+                };
+                self.expect(Token::LBrace, "Expected anonymous function body")?;
+                let body = self.parse_block()?;
+                ASTNode::FunctionExpression {
+                    return_type,
+                    parameters,
+                    body,
+                    span,
+                }
+            }
             Some(Token::Call) => {
+                // `call` before a function call is optional legacy syntax
                 self.advance(); // Consume "call"
-                let function_name = match self.current_token {
-                    Some(Token::Identifier(name)) => name.clone(),
-                    _ => panic!("Expected function name after 'call'"),
-                };
-                self.advance(); // Consume function name
+                match self.parse_term()? {
+                    call @ (ASTNode::FunctionCall { .. } | ASTNode::CallExpression { .. }) => call,
+                    _ => return Err(self.error("Expected function call after 'call'")),
+                }
+            }
+            Some(Token::LParen) => {
                 self.advance(); // Consume "("
-                let arguments = self.parse_argument_list(); // Parse the function arguments
-                self.advance(); // Consume ")"
-                let boxed_arguments: Vec<Box<ASTNode>> = arguments.into_iter().map(Box::new).collect();
-                let function_call_node = ASTNode::FunctionCall {
-                    name: function_name.clone(),
-                    arguments: boxed_arguments,
-                };
-                function_call_node
+                // Parentheses delimit the expression, so a struct literal is unambiguous inside them
+                let allow_struct_literal = std::mem::replace(&mut self.allow_struct_literal, true);
+                let expression = self.parse_expression()?;
+                self.allow_struct_literal = allow_struct_literal;
+                self.expect(Token::RParen, "Expected closing parenthesis after expression")?;
+                expression
             }
-            _ => panic!("Expected integer literal, boolean literal, or identifier"),
-        }
+            Some(Token::LBracket) => {
+                self.advance(); // Consume "["
+                let mut elements = Vec::new();
+                while !matches!(self.current_token, Some(Token::RBracket)) {
+                    elements.push(self.parse_expression()?);
+                    if let Some(Token::Comma) = self.current_token {
+                        self.advance(); // Consume ","
+                    } else {
+                        break;
+                    }
+                }
+                if let Some(Token::RBracket) = self.current_token {
+                    self.advance(); // Consume "]"
+                } else {
+                    return Err(self.error("Expected closing bracket after list elements"));
+                }
+                ASTNode::ListLiteral(elements)
+            }
+            Some(Token::LBrace) => {
+                self.advance(); // Consume "{"
+                let mut entries = Vec::new();
+                while !matches!(self.current_token, Some(Token::RBrace)) {
+                    let key = self.parse_expression()?;
+                    self.expect(Token::Colon, "Expected ':' after map key")?;
+                    let value = self.parse_expression()?;
+                    entries.push((key, value));
+                    if let Some(Token::Comma) = self.current_token {
+                        self.advance(); // Consume ","
+                    } else {
+                        break;
+                    }
+                }
+                self.expect(Token::RBrace, "Expected closing brace after map entries")?;
+                ASTNode::MapLiteral(entries)
+            }
+            _ => return Err(self.error(format!("Expected an expression, found {}", self.found()))),
+        };
+        Ok(primary)
     }
     
     
-    /// Parse parameters of function call according to rules
-    pub fn parse_parameters(&mut self) -> Vec<(String, String)> {
-        let mut parameters = Vec::new();
-        while let Some(Token::Variable) = self.current_token {
-            self.advance(); // Consume "variable"
-    
-            let parameter_type = match self.current_token {
-                Some(Token::Integer) => {
-                    self.advance(); // Consume "integer"
-                    "integer".to_string()
+    /// Parse a match construct such as
+    /// `match (shape) { Shape::Circle(r) => r * r, _ => { return 0; } }`.
+    /// Each arm body is either a block or a single expression.
+    pub fn parse_match(&mut self) -> Result<ASTNode, Diagnostic> {
+        let span = self.current_span();
+        self.expect(Token::Match, "Expected 'match'")?;
+        self.expect(Token::LParen, "Expected opening parenthesis after 'match'")?;
+        let subject = self.parse_expression()?;
+        self.expect(Token::RParen, "Expected closing parenthesis after match subject")?;
+        self.expect(Token::LBrace, "Expected opening brace after match subject")?;
+
+        let mut arms = Vec::new();
+        while !matches!(self.current_token, Some(Token::RBrace) | None) {
+            let pattern = self.parse_pattern()?;
+            let guard = if let Some(Token::If) = self.current_token {
+                self.advance(); // Consume "if"
+                Some(self.parse_expression()?)
+            } else {
+                None
+            };
+            self.expect(Token::FatArrow, "Expected '=>' after match pattern")?;
+            let body = if let Some(Token::LBrace) = self.current_token {
+                self.advance(); // Consume "{"
+                self.parse_value_block()?
+            } else {
+                vec![self.parse_expression()?]
+            };
+            arms.push(MatchArm { pattern, guard, body });
+            if let Some(Token::Comma) = self.current_token {
+                self.advance(); // Consume ","
+            }
+        }
+        self.expect(Token::RBrace, "Expected closing brace after match arms")?;
+
+        Ok(ASTNode::Match {
+            subject: Box::new(subject),
+            arms,
+            span,
+        })
+    }
+
+    /// Parse a single match pattern: `_`, a binding name, a literal, or an enum
+    /// variant with nested patterns for its payload.
+    pub fn parse_pattern(&mut self) -> Result<Pattern, Diagnostic> {
+        let pattern = match self.current_token {
+            Some(Token::IntegerLiteral(value)) => {
+                self.advance(); // Consume integer literal
+                Pattern::Integer(*value)
+            }
+            Some(Token::BooleanLiteral(value)) => {
+                self.advance(); // Consume boolean literal
+                Pattern::Boolean(*value)
+            }
+            Some(Token::StringLiteral(value)) => {
+                self.advance(); // Consume string literal
+                Pattern::String(value.clone())
+            }
+            Some(Token::Identifier(name)) => {
+                let name = name.clone();
+                let span = self.current_span();
+                self.advance(); // Consume identifier
+                if let Some(Token::DoubleColon) = self.current_token {
+                    self.advance(); // Consume "::"
+                    let variant = self.expect_identifier("Expected variant name after '::'")?;
+                    let mut fields = Vec::new();
+                    if let Some(Token::LParen) = self.current_token {
+                        self.advance(); // Consume "("
+                        while !matches!(self.current_token, Some(Token::RParen)) {
+                            fields.push(self.parse_pattern()?);
+                            if let Some(Token::Comma) = self.current_token {
+                                self.advance(); // Consume ","
+                            } else {
+                                break;
+                            }
+                        }
+                        self.expect(Token::RParen, "Expected closing parenthesis after variant patterns")?;
+                    }
+                    Pattern::Variant { enum_name: name, variant, fields, span }
+                } else if name == "_" {
+                    Pattern::Wildcard
+                } else {
+                    Pattern::Binding(name)
                 }
-                Some(Token::Boolean) => {
-                    self.advance(); // Consume "boolean"
-                    "boolean".to_string()
+            }
+            _ => return Err(self.error(format!("Expected a pattern, found {}", self.found()))),
+        };
+        Ok(pattern)
+    }
+
+    /// Parse the field initialisers of a struct construction expression such as
+    /// `Point { x: 1, y: 2 }`, starting at the opening brace. `span` is the
+    /// position of the struct name.
+    pub fn parse_struct_literal(&mut self, name: String, span: Span) -> Result<ASTNode, Diagnostic> {
+        self.expect(Token::LBrace, "Expected opening brace in struct construction")?;
+        let mut fields = Vec::new();
+        while !matches!(self.current_token, Some(Token::RBrace)) {
+            let field = self.expect_identifier("Expected field name in struct construction")?;
+            self.expect(Token::Colon, "Expected ':' after field name")?;
+            let value = self.parse_expression()?;
+            fields.push((field, value));
+            if let Some(Token::Comma) = self.current_token {
+                self.advance(); // Consume ","
+            } else {
+                break;
+            }
+        }
+        self.expect(Token::RBrace, "Expected closing brace after struct fields")?;
+        Ok(ASTNode::StructLiteral { name, fields, span })
+    }
+
+    /// Parse an interpolated string such as `"total: ${a + b}"`, which the lexer
+    /// splits into a `StringPart` before each embedded expression and a
+    /// `StringEnd` after the last one.
+    pub fn parse_interpolated_string(&mut self) -> Result<ASTNode, Diagnostic> {
+        let mut parts = Vec::new();
+        let allow_struct_literal = std::mem::replace(&mut self.allow_struct_literal, true);
+        while let Some(Token::StringPart(text)) = self.current_token {
+            self.advance(); // Consume the text and "${"
+            if !text.is_empty() {
+                parts.push(ASTNode::StringLiteral(text.clone()));
+            }
+            parts.push(self.parse_expression()?);
+            match self.current_token {
+                Some(Token::StringEnd(text)) => {
+                    self.advance(); // Consume "}" and the rest of the string
+                    if !text.is_empty() {
+                        parts.push(ASTNode::StringLiteral(text.clone()));
+                    }
+                    break;
                 }
-                _ => unreachable!(), // This should not happen if the input is correct
+                Some(Token::StringPart(_)) => {}
+                _ => return Err(self.error(format!("Expected '}}' after interpolated expression, found {}", self.found()))),
+            }
+        }
+        self.allow_struct_literal = allow_struct_literal;
+        Ok(ASTNode::InterpolatedString(parts))
+    }
+
+    /// Parse the return type of a function, which may be `void` for a function
+    /// that returns no value. Returns `None` without consuming anything if no
+    /// type starts here.
+    pub fn parse_return_type(&mut self) -> Result<Option<String>, Diagnostic> {
+        if let Some(Token::Void) = self.current_token {
+            self.advance(); // Consume "void"
+            return Ok(Some("void".to_string()));
+        }
+        self.parse_type()
+    }
+
+    /// Parse a type name such as `integer`, `list<integer>`, `map<string, integer>`,
+    /// `function(integer) -> boolean` or the name of a struct.
+    /// Returns `None` without consuming anything if no type starts here.
+    pub fn parse_type(&mut self) -> Result<Option<String>, Diagnostic> {
+        let parsed = match self.current_token {
+            Some(Token::Integer) => {
+                self.advance(); // Consume "integer"
+                Some("integer".to_string())
+            }
+            Some(Token::Float) => {
+                self.advance(); // Consume "float"
+                Some("float".to_string())
+            }
+            Some(Token::Boolean) => {
+                self.advance(); // Consume "boolean"
+                Some("boolean".to_string())
+            }
+            Some(Token::String) => {
+                self.advance(); // Consume "string"
+                Some("string".to_string())
+            }
+            // `map` is not reserved, so that the `map` builtin can still be called
+            Some(Token::Identifier(name))
+                if name == "map" && self.tokens.get(self.position + 1) == Some(&Token::LessThan) =>
+            {
+                self.advance(); // Consume "map"
+                self.expect(Token::LessThan, "Expected '<' after 'map'")?;
+                let key_type = match self.parse_type()? {
+                    Some(key_type) => key_type,
+                    None => return Err(self.error("Expected key type in map type")),
+                };
+                self.expect(Token::Comma, "Expected ',' after map key type")?;
+                let value_type = match self.parse_type()? {
+                    Some(value_type) => value_type,
+                    None => return Err(self.error("Expected value type in map type")),
+                };
+                self.expect(Token::GreaterThan, "Expected '>' after map value type")?;
+                Some(format!("map<{}, {}>", key_type, value_type))
+            }
+            Some(Token::Identifier(name)) => {
+                self.advance(); // Consume struct name
+                Some(name.clone())
+            }
+            Some(Token::Function) => {
+                // Function type: `function(integer, integer) -> boolean`
+                self.advance(); // Consume "function"
+                self.expect(Token::LParen, "Expected '(' after 'function' in function type")?;
+                let mut parameter_types = Vec::new();
+                while !matches!(self.current_token, Some(Token::RParen)) {
+                    match self.parse_type()? {
+                        Some(parameter_type) => parameter_types.push(parameter_type),
+                        None => return Err(self.error("Expected parameter type in function type")),
+                    }
+                    if let Some(Token::Comma) = self.current_token {
+                        self.advance(); // Consume ","
+                    } else {
+                        break;
+                    }
+                }
+                self.expect(Token::RParen, "Expected ')' after function type parameters")?;
+                self.expect(Token::Arrow, "Expected '->' in function type")?;
+                let return_type = match self.parse_return_type()? {
+                    Some(return_type) => return_type,
+                    None => return Err(self.error("Expected return type in function type")),
+                };
+                Some(format!("function({}) -> {}", parameter_types.join(", "), return_type))
+            }
+            Some(Token::List) => {
+                self.advance(); // Consume "list"
+                if let Some(Token::LessThan) = self.current_token {
+                    self.advance(); // Consume "<"
+                } else {
+                    return Err(self.error("Expected '<' after 'list'"));
+                }
+                let element_type = match self.parse_type()? {
+                    Some(element_type) => element_type,
+                    None => return Err(self.error("Expected element type in list type")),
+                };
+                if let Some(Token::GreaterThan) = self.current_token {
+                    self.advance(); // Consume ">"
+                } else {
+                    return Err(self.error("Expected '>' after list element type"));
+                }
+                Some(format!("list<{}>", element_type))
+            }
+            _ => None,
+        };
+        Ok(parsed)
+    }
+
+    /// Parse parameters of function call according to rules. Each parameter is
+    /// a type and a name, optionally preceded by the `variable` keyword.
+    pub fn parse_parameters(&mut self) -> Result<Vec<(String, String)>, Diagnostic> {
+        let mut parameters = Vec::new();
+        while !matches!(self.current_token, Some(Token::RParen) | None) {
+            if let Some(Token::Variable) = self.current_token {
+                self.advance(); // Consume "variable"
+            }
+    
+            let parameter_type = match self.parse_type()? {
+                Some(parameter_type) => parameter_type,
+                None => return Err(self.error("Expected parameter type")),
             };
     
             let name = match self.current_token {
@@ -399,7 +1091,7 @@ impl<'a> Parser<'a> {
                     self.advance(); // Consume parameter name
                     name.clone()
                 }
-                _ => panic!("Expected identifier for parameter"),
+                _ => return Err(self.error("Expected identifier for parameter")),
             };
     
             parameters.push((parameter_type, name));
@@ -410,11 +1102,63 @@ impl<'a> Parser<'a> {
                 break;
             }
         }
-        parameters
+        Ok(parameters)
     }
     
+    /// Parse a block whose value is that of its final expression, as in the
+    /// branches of an `if` expression or match arms (`{ a }`). Statements
+    /// may precede the final expression.
+    pub fn parse_value_block(&mut self) -> Result<Vec<ASTNode>, Diagnostic> {
+        let mut statements = Vec::new();
+        loop {
+            match self.current_token {
+                Some(Token::RBrace) => {
+                    self.advance(); // Consume "}"
+                    break;
+                }
+                None => return Err(self.error("Expected closing brace at end of block")),
+                Some(Token::IntegerLiteral(_))
+                | Some(Token::FloatLiteral(_))
+                | Some(Token::BooleanLiteral(_))
+                | Some(Token::StringLiteral(_))
+                | Some(Token::StringPart(_))
+                | Some(Token::Identifier(_))
+                | Some(Token::LBracket)
+                | Some(Token::LBrace)
+                | Some(Token::LParen)
+                | Some(Token::Minus)
+                | Some(Token::Not)
+                | Some(Token::Call)
+                | Some(Token::If)
+                | Some(Token::Function) => {
+                    let span = self.current_span();
+                    let conditional = matches!(self.current_token, Some(Token::If));
+                    let expression = if conditional {
+                        self.parse_conditional(true)?
+                    } else {
+                        self.parse_expression()?
+                    };
+                    if let Some(Token::RBrace) = self.current_token {
+                        // The final expression gives the block its value
+                        self.advance(); // Consume "}"
+                        statements.push(expression);
+                        break;
+                    }
+                    if conditional {
+                        // An `if` before the end is a statement, which needs no ";"
+                        statements.push(expression);
+                        continue;
+                    }
+                    statements.push(self.finish_expression_statement(expression, span)?);
+                }
+                Some(_) => statements.push(self.parse_statement()?),
+            }
+        }
+        Ok(statements)
+    }
+
     /// Parse block scope of function according to rules
-    pub fn parse_block(&mut self) -> Vec<ASTNode> {
+    pub fn parse_block(&mut self) -> Result<Vec<ASTNode>, Diagnostic> {
         let mut statements = Vec::new();
         while let Some(token) = self.current_token {
             match token {
@@ -423,14 +1167,12 @@ impl<'a> Parser<'a> {
                     break;
                 }
                 _ => {
-                    println!("Comes from different section");
-                    println!("{:?}",self.current_token);
-                    let statement = self.parse();
+                    let statement = self.parse()?;
                     statements.extend(statement);
                 }
             }
         }
-        statements
+        Ok(statements)
     }
     
 }
\ No newline at end of file
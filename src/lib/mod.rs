@@ -2,4 +2,7 @@ pub mod Enums;
 pub mod Lexer;
 pub mod Asteva;
 pub mod Parser;
+pub mod Checker;
+pub mod Loader;
+pub mod Optimizer;
 pub mod Interpreter;
\ No newline at end of file
@@ -1,35 +1,226 @@
+#![allow(special_module_name)]
+
+// `lib` holds the interpreter itself, in capitalised module directories.
+#[allow(non_snake_case)]
 mod lib;
 
-use lib::Interpreter::interpreter::Interpreter;
+use lib::Asteva::budget::CountingAllocator;
+use lib::Asteva::context::{DEFAULT_MAX_DEPTH, STACK_PER_CALL};
+use lib::Asteva::files::Capabilities;
+use lib::Checker::lints::{Level, Lint};
+use lib::Asteva::error::ErrorKind;
+use lib::Interpreter::interpreter::{Failure, Interpreter};
 use std::env;
 use std::fs;
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Counts allocations so that --max-heap can be enforced
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// The most stack the runner thread may reserve, which bounds --max-depth
+const MAX_STACK_SIZE: usize = 4 * 1024 * 1024 * 1024;
+
+// The exit statuses of a run that fails: an uncaught runtime error, or a file
+// that cannot be read or run; an invalid command line; a program that does not
+// compile; and a script stopped by each of its budget's limits
+const EXIT_FAILURE: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_COMPILE_ERROR: u8 = 3;
+const EXIT_OUT_OF_FUEL: u8 = 4;
+const EXIT_TIMEOUT: u8 = 5;
+const EXIT_OUT_OF_MEMORY: u8 = 6;
+
+/// Returns the exit status for a program that failed.
+fn exit_status(failure: &Failure) -> u8 {
+    match failure.kind {
+        None => EXIT_COMPILE_ERROR,
+        Some(ErrorKind::OutOfFuel) => EXIT_OUT_OF_FUEL,
+        Some(ErrorKind::Timeout) => EXIT_TIMEOUT,
+        Some(ErrorKind::OutOfMemory) => EXIT_OUT_OF_MEMORY,
+        Some(_) => EXIT_FAILURE,
+    }
+}
 
-fn main() {
+fn main() -> ExitCode {
     // Get the command-line arguments
     let args: Vec<String> = env::args().collect();
     
-    // Check if the argument count is correct
-    if args.len() != 2 {
-        eprintln!("Usage: {} <file>", args[0]);
-        return;
+    // Collect the options that come before the file path
+    let mut capabilities = Capabilities::none();
+    let mut max_depth = DEFAULT_MAX_DEPTH;
+    let mut fuel = None;
+    let mut timeout = None;
+    let mut max_heap = None;
+    let mut optimization_level = 1;
+    let mut dump_ast = false;
+    let mut lint_levels = Vec::new();
+    let mut position = 1;
+    while let Some(option) = args.get(position).filter(|arg| arg.starts_with('-')) {
+        if option == "--dump-ast" {
+            dump_ast = true;
+        } else if let Some(level) = option.strip_prefix("-O") {
+            optimization_level = match level.parse() {
+                Ok(level @ 0..=1) => level,
+                _ => {
+                    eprintln!("Invalid optimization level: {} (expected -O0 or -O1)", option);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            };
+        } else if let Some((level, codes)) = option
+            .strip_prefix("--allow=")
+            .map(|codes| (Level::Allow, codes))
+            .or_else(|| option.strip_prefix("--warn=").map(|codes| (Level::Warn, codes)))
+            .or_else(|| option.strip_prefix("--deny=").map(|codes| (Level::Deny, codes)))
+        {
+            for code in codes.split(',') {
+                match code {
+                    "all" => lint_levels.extend(Lint::ALL.map(|lint| (lint, level))),
+                    code => match Lint::from_code(code) {
+                        Some(lint) => lint_levels.push((lint, level)),
+                        None => {
+                            let codes: Vec<&str> = Lint::ALL.iter().map(|lint| lint.code()).collect();
+                            eprintln!("Unknown lint: {} (expected all, {})", code, codes.join(", "));
+                            return ExitCode::from(EXIT_USAGE);
+                        }
+                    },
+                }
+            }
+        } else if let Some(paths) = option.strip_prefix("--allow-read=") {
+            for path in paths.split(',') {
+                capabilities = capabilities.allow_read(path);
+            }
+        } else if let Some(paths) = option.strip_prefix("--allow-write=") {
+            for path in paths.split(',') {
+                capabilities = capabilities.allow_write(path);
+            }
+        } else if let Some(depth) = option.strip_prefix("--max-depth=") {
+            max_depth = match depth.parse::<usize>() {
+                Ok(depth) if depth.checked_mul(STACK_PER_CALL).is_some_and(|size| size <= MAX_STACK_SIZE) => depth,
+                Ok(_) => {
+                    eprintln!("Maximum call depth too large: {} (at most {})", depth, MAX_STACK_SIZE / STACK_PER_CALL);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+                Err(_) => {
+                    eprintln!("Invalid maximum call depth: {}", depth);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            };
+        } else if let Some(steps) = option.strip_prefix("--fuel=") {
+            fuel = match steps.parse() {
+                Ok(steps) => Some(steps),
+                Err(_) => {
+                    eprintln!("Invalid number of steps: {}", steps);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            };
+        } else if let Some(milliseconds) = option.strip_prefix("--timeout=") {
+            timeout = match milliseconds.parse() {
+                Ok(milliseconds) => Some(Duration::from_millis(milliseconds)),
+                Err(_) => {
+                    eprintln!("Invalid timeout: {}", milliseconds);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            };
+        } else if let Some(bytes) = option.strip_prefix("--max-heap=") {
+            max_heap = match bytes.parse() {
+                Ok(bytes) => Some(bytes),
+                Err(_) => {
+                    eprintln!("Invalid heap size: {}", bytes);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            };
+        } else {
+            eprintln!("Unknown option: {}", option);
+            return ExitCode::from(EXIT_USAGE);
+        }
+        position += 1;
+    }
+
+    // Check that a file path was given
+    if position >= args.len() {
+        eprintln!("Usage: {} [--allow-read=<dir>] [--allow-write=<dir>] [--max-depth=<calls>] [--fuel=<steps>] [--timeout=<ms>] [--max-heap=<bytes>] [--allow=<lint>] [--warn=<lint>] [--deny=<lint>] [-O0|-O1] [--dump-ast] <file> [arguments...]", args[0]);
+        return ExitCode::from(EXIT_USAGE);
     }
 
     // Get the file path from the arguments
-    let file_path = &args[1];
+    let file_path = &args[position];
 
     // Read the file contents
     let code = match fs::read_to_string(file_path) {
         Ok(contents) => contents,
         Err(err) => {
             eprintln!("Error reading file: {}", err);
-            return;
+            return ExitCode::from(EXIT_FAILURE);
         }
     };
 
-    // Create an interpreter and interpret the code
-    let interpreter = Interpreter::new(&code);
-    if let Err(err) = interpreter.interpret() {
-        eprintln!("Error interpreting code: {}", err);
-    }
-}
+    // Script calls recurse on the native stack, so interpret on a thread whose
+    // stack can hold the deepest chain of calls the script is allowed to make.
+    // The memory is only committed as the stack actually grows. The depth was
+    // checked against MAX_STACK_SIZE, so the size cannot overflow
+    let started = Instant::now();
+    let stack_size = (max_depth * STACK_PER_CALL).max(8 * 1024 * 1024);
+    let status = thread::scope(|scope| {
+        let runner = thread::Builder::new().stack_size(stack_size).spawn_scoped(scope, || {
+            // Create an interpreter and interpret the code
+            // Everything after the file path is passed on to the script
+            let mut interpreter = Interpreter::new(&code)
+                .with_path(file_path)
+                .with_args(args[position + 1..].to_vec())
+                .with_capabilities(capabilities)
+                .with_max_depth(max_depth)
+                .with_optimization_level(optimization_level);
+            // Later options override earlier ones, so `--deny=all --allow=unused_function` works
+            for (lint, level) in lint_levels {
+                interpreter = interpreter.with_lint_level(lint, level);
+            }
 
+            // Show the program as it would run instead of running it
+            if dump_ast {
+                return match interpreter.ast() {
+                    Ok(ast) => {
+                        ast.iter().for_each(|node| println!("{:#?}", node));
+                        0
+                    }
+                    Err(err) => {
+                        eprintln!("Error interpreting code: {}", err);
+                        EXIT_COMPILE_ERROR
+                    }
+                };
+            }
+            if let Some(fuel) = fuel {
+                interpreter = interpreter.with_fuel(fuel);
+            }
+            if let Some(timeout) = timeout {
+                interpreter = interpreter.with_deadline(started + timeout);
+            }
+            if let Some(max_heap) = max_heap {
+                interpreter = interpreter.with_max_heap(max_heap);
+            }
+            match interpreter.interpret() {
+                Ok(()) => 0,
+                Err(failure) => {
+                    eprintln!("Error interpreting code: {}", failure);
+                    exit_status(&failure)
+                }
+            }
+        });
+        match runner {
+            // A panic has already been reported by the panic hook
+            Ok(runner) => runner.join().unwrap_or(EXIT_FAILURE),
+            Err(err) => {
+                eprintln!(
+                    "Error starting interpreter: cannot reserve {} MiB of stack for a maximum call depth of {}: {}",
+                    stack_size / (1024 * 1024),
+                    max_depth,
+                    err
+                );
+                EXIT_FAILURE
+            }
+        }
+    });
+    ExitCode::from(status)
+}
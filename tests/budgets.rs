@@ -0,0 +1,71 @@
+mod common;
+
+use common::{run, run_ok, run_path, run_with_input, script};
+
+#[test]
+fn running_out_of_fuel_stops_the_script() {
+    let run = run(&["--fuel=500"], "fuel.known");
+    assert!(run.stderr.contains("error: OutOfFuel: Exceeded the limit of 500 evaluation steps"), "{}", run.stderr);
+    assert_eq!(run.status, Some(4));
+}
+
+#[test]
+fn running_out_of_time_stops_the_script() {
+    let run = run(&["--timeout=50"], "fuel.known");
+    assert!(run.stderr.contains("error: Timeout: Exceeded the time limit"), "{}", run.stderr);
+    assert_eq!(run.status, Some(5));
+}
+
+#[test]
+fn one_oversized_allocation_is_refused() {
+    let run = run(&["--max-heap=1000000"], "heap_limit.known");
+    assert_eq!(run.stdout, "1000\n");
+    assert!(run.stderr.contains("3:31: error: OutOfMemory: Exceeded the limit of 1000000 bytes of memory"), "{}", run.stderr);
+    assert_eq!(run.status, Some(6));
+}
+
+#[test]
+fn the_same_allocation_succeeds_without_a_limit() {
+    assert_eq!(run_ok(&[], "heap_limit.known"), "1000\n1000000\n");
+}
+
+#[test]
+fn input_larger_than_the_heap_limit_is_refused() {
+    let input = "a".repeat(2_000_000);
+    let run = run_with_input(&["--max-heap=1000000"], "read_all.known", &input);
+    assert!(run.stderr.contains("OutOfMemory"), "{}", run.stderr);
+    let run = run_with_input(&["--max-heap=1000000"], "read_all.known", "small\n");
+    assert_eq!(run.stdout, "6\n", "{}", run.stderr);
+}
+
+#[test]
+fn strings_built_by_interpolation_are_refused_past_the_heap_limit() {
+    let run = run(&["--max-heap=1000000"], "heap_interpolation.known");
+    assert!(run.stderr.contains("error: OutOfMemory: Exceeded the limit of 1000000 bytes of memory"), "{}", run.stderr);
+    assert_eq!(run.status, Some(6));
+}
+
+#[test]
+fn values_too_large_to_show_are_refused_past_the_heap_limit() {
+    for way in ["print", "to_string"] {
+        let run = run_path(&["--max-heap=1000000"], &script("heap_nested.known"), &[way], "");
+        assert_eq!(run.stdout, "4\n", "{}", run.stderr);
+        assert!(run.stderr.contains("error: OutOfMemory: Exceeded the limit of 1000000 bytes of memory"), "{}", run.stderr);
+    }
+}
+
+#[test]
+fn a_line_larger_than_the_heap_limit_is_refused() {
+    let input = "a".repeat(2_000_000) + "\n";
+    let run = run_with_input(&["--max-heap=1000000"], "read_line.known", &input);
+    assert!(run.stderr.contains("error: OutOfMemory: Exceeded the limit of 1000000 bytes of memory"), "{}", run.stderr);
+    let run = run_with_input(&["--max-heap=1000000"], "read_line.known", "small\nmore\n");
+    assert_eq!(run.stdout, "5\n", "{}", run.stderr);
+}
+
+#[test]
+fn format_widths_are_bounded() {
+    let run = run(&[], "format_width.known");
+    assert_eq!(run.stdout, "[   42]\n");
+    assert!(run.stderr.contains("format() placeholder '{:300000000}' asks for more than 65535 characters"), "{}", run.stderr);
+}
@@ -0,0 +1,89 @@
+// Each test file uses only some of these helpers
+#![allow(dead_code)]
+
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// What a run of the interpreter printed.
+pub struct Run {
+    /// Everything printed to stdout.
+    pub stdout: String,
+    /// Everything printed to stderr.
+    pub stderr: String,
+    /// The exit status, or `None` if the interpreter was killed by a signal.
+    pub status: Option<i32>,
+}
+
+/// Returns the path of a test script in `tests/scripts`.
+pub fn script(name: &str) -> String {
+    format!("{}/tests/scripts/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+/// Returns the path of an example script in `src/examples`.
+pub fn example(name: &str) -> String {
+    format!("{}/src/examples/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+/// Runs a test script with the given options, feeding it `input` on stdin.
+pub fn run_with_input(options: &[&str], name: &str, input: &str) -> Run {
+    run_path(options, &script(name), &[], input)
+}
+
+/// Runs an example script that must succeed without printing anything to
+/// stderr, and returns what it printed to stdout.
+pub fn run_example_ok(options: &[&str], name: &str) -> String {
+    let run = run_path(options, &example(name), &[], "");
+    assert!(run.stderr.is_empty(), "{}", run.stderr);
+    run.stdout
+}
+
+/// Runs the script at `path` with the given options and script arguments,
+/// feeding it `input` on stdin.
+pub fn run_path(options: &[&str], path: &str, arguments: &[&str], input: &str) -> Run {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_knownlanguage"))
+        .args(options)
+        .arg(path)
+        .args(arguments)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run the interpreter");
+    // Write from another thread, so a large input cannot fill the pipe while
+    // the interpreter waits for its output to be read. A script may stop
+    // before reading all of it, which closes the pipe
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_string();
+    let writer = thread::spawn(move || match stdin.write_all(input.as_bytes()) {
+        Err(err) if err.kind() != ErrorKind::BrokenPipe => panic!("failed to write the input: {}", err),
+        _ => {}
+    });
+    let output = child.wait_with_output().expect("failed to wait for the interpreter");
+    writer.join().expect("failed to write the input");
+    Run {
+        stdout: String::from_utf8(output.stdout).expect("output is not UTF-8"),
+        stderr: String::from_utf8(output.stderr).expect("output is not UTF-8"),
+        status: output.status.code(),
+    }
+}
+
+/// Runs a test script with the given options and no input.
+pub fn run(options: &[&str], name: &str) -> Run {
+    run_with_input(options, name, "")
+}
+
+/// Runs a test script that must succeed without printing anything to stderr,
+/// and returns what it printed to stdout.
+pub fn run_ok(options: &[&str], name: &str) -> String {
+    let run = run(options, name);
+    assert!(run.stderr.is_empty(), "{}", run.stderr);
+    run.stdout
+}
+
+/// Runs a test script that must fail, and returns what it printed to stderr.
+pub fn run_err(options: &[&str], name: &str) -> String {
+    let run = run(options, name);
+    assert!(!run.stderr.is_empty(), "expected an error, got output {:?}", run.stdout);
+    run.stderr
+}
@@ -0,0 +1,27 @@
+mod common;
+
+use common::{run_err, run_example_ok, run_ok, script};
+
+#[test]
+fn conditionals_choose_a_branch() {
+    assert_eq!(run_example_ok(&[], "conditionals.known"), "8\nmedium\n-1\n0\n1\n");
+}
+
+#[test]
+fn an_if_may_be_a_statement_or_the_value_of_a_value_block() {
+    let expected = ["far above", "10", "4", "many", "checking", "1"];
+    assert_eq!(run_ok(&[], "value_block_conditionals.known"), expected.join("\n") + "\n");
+}
+
+#[test]
+fn non_boolean_conditions_are_reported_at_their_line() {
+    let stderr = run_err(&[], "non_boolean_conditions.known");
+    let file = script("non_boolean_conditions.known");
+    for expected in [
+        "2:1: error: Condition of 'if' must be a boolean, found integer",
+        "6:1: error: Condition of 'while' must be a boolean, found string",
+        "9:28: error: Operand of '&&' must be a boolean, found integer",
+    ] {
+        assert!(stderr.contains(&format!("{}:{}", file, expected)), "{}", stderr);
+    }
+}
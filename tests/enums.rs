@@ -0,0 +1,27 @@
+mod common;
+
+use common::{run_err, run_example_ok, script};
+
+#[test]
+fn enum_values_are_matched() {
+    assert_eq!(run_example_ok(&[], "shapes.known"), "6\n3\n7\nShape::Rect(3, 4)\nsquare\n");
+}
+
+#[test]
+fn a_match_missing_a_variant_is_rejected_at_the_match() {
+    let stderr = run_err(&[], "match_not_exhaustive.known");
+    let expected = "7:12: error: Non-exhaustive match on enum 'Light': missing variant(s) Light::Amber";
+    assert!(stderr.contains(&format!("{}:{}", script("match_not_exhaustive.known"), expected)), "{}", stderr);
+}
+
+#[test]
+fn unknown_variants_and_wrong_payloads_are_rejected_where_they_are_named() {
+    let stderr = run_err(&[], "unknown_variant.known");
+    let file = script("unknown_variant.known");
+    for expected in [
+        "2:24: error: Enum 'Light' has no variant 'Blue'",
+        "4:5: error: Variant 'Light::Red' expects 0 value(s), got 1",
+    ] {
+        assert!(stderr.contains(&format!("{}:{}", file, expected)), "{}", stderr);
+    }
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use common::{run, run_example_ok, script};
+
+#[test]
+fn errors_are_caught_and_finally_blocks_run() {
+    let expected = [
+        "DivisionByZero Division by zero 2 14",
+        "cleanup",
+        "KeyNotFound 13 12",
+        "caught custom",
+        "inner finally",
+        "IndexOutOfBounds Index 5 out of bounds for list of length 2",
+        "rethrown IndexOutOfBounds",
+        "leaving first",
+        "7",
+        "caught 4 at 4",
+        "4",
+        "StackOverflow",
+    ];
+    assert_eq!(run_example_ok(&[], "exceptions.known"), expected.join("\n") + "\n");
+}
+
+#[test]
+fn an_uncaught_throw_stops_the_script_with_its_calls() {
+    let run = run(&[], "uncaught_throw.known");
+    assert_eq!(run.stdout, "ok\n");
+    let file = script("uncaught_throw.known");
+    let expected = [
+        format!("{}:3:9: error: Uncaught exception: too big", file),
+        format!("{}:7:10: note: in 'check', called here", file),
+        format!("{}:11:4: note: in 'run', called here", file),
+    ];
+    assert!(run.stderr.contains(&expected.join("\n")), "{}", run.stderr);
+    assert_eq!(run.status, Some(1));
+}
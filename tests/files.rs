@@ -0,0 +1,68 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+use common::{example, run_example_ok, run_path, script};
+
+/// Creates an empty directory for one test under the system's temporary directory.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("knownlanguage-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("failed to create a temporary directory");
+    dir
+}
+
+#[test]
+fn files_are_read_from_granted_directories() {
+    assert_eq!(run_example_ok(&["--allow-read=src/examples"], "files.known"), "true\nfalse\ntrue\n");
+}
+
+#[test]
+fn files_outside_granted_directories_are_refused() {
+    let run = run_path(&[], &example("files.known"), &[], "");
+    assert!(run.stderr.contains("PermissionDenied: list_dir() may not read 'src/examples'"), "{}", run.stderr);
+}
+
+#[test]
+fn files_larger_than_the_heap_limit_are_refused() {
+    let dir = temp_dir("large");
+    fs::write(dir.join("large.txt"), "a".repeat(2_000_000)).unwrap();
+    let read = format!("--allow-read={}", dir.display());
+    let path = dir.join("large.txt");
+    let run = run_path(&[&read, "--max-heap=1000000"], &script("read_file.known"), &[path.to_str().unwrap()], "");
+    assert!(run.stderr.contains("error: OutOfMemory: Exceeded the limit of 1000000 bytes of memory"), "{}", run.stderr);
+    let run = run_path(&[&read], &script("read_file.known"), &[path.to_str().unwrap()], "");
+    assert_eq!(run.stdout, "2000000\n", "{}", run.stderr);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn symbolic_links_cannot_lead_out_of_the_sandbox() {
+    use std::os::unix::fs::symlink;
+
+    let dir = temp_dir("sandbox");
+    fs::create_dir(dir.join("sandbox")).unwrap();
+    fs::create_dir(dir.join("outside")).unwrap();
+    fs::write(dir.join("outside/secret.txt"), "secret").unwrap();
+    symlink(dir.join("outside/target.txt"), dir.join("sandbox/dangling")).unwrap();
+    symlink(dir.join("outside"), dir.join("sandbox/linked")).unwrap();
+
+    let sandbox = dir.join("sandbox");
+    let read = format!("--allow-read={}", sandbox.display());
+    let write = format!("--allow-write={}", sandbox.display());
+    let run = run_path(&[&read, &write], &script("sandbox.known"), &[dir.to_str().unwrap()], "");
+    let expected = [
+        "inside allowed",
+        "kept",
+        "dotdot PermissionDenied",
+        "dangling PermissionDenied",
+        "dangling append PermissionDenied",
+        "linked parent PermissionDenied",
+        "linked read PermissionDenied",
+    ];
+    assert_eq!(run.stdout, expected.join("\n") + "\n", "{}", run.stderr);
+    assert!(!dir.join("outside/target.txt").exists());
+    assert!(!dir.join("outside/note.txt").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
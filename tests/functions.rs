@@ -0,0 +1,82 @@
+mod common;
+
+use common::{run, run_err, run_example_ok, run_ok, script};
+
+#[test]
+fn functions_are_called_with_and_without_call() {
+    assert_eq!(run_example_ok(&[], "ex.known"), "12\n");
+    assert_eq!(run_example_ok(&[], "fib.known"), "3\n");
+}
+
+#[test]
+fn closures_capture_their_environment() {
+    assert_eq!(run_example_ok(&[], "closures.known"), "[2, 4, 6]\n[11, 12, 13]\n[2, 3]\n6\n21\n12\n1\n3\n");
+}
+
+#[test]
+fn closures_share_the_variables_they_capture() {
+    assert_eq!(run_ok(&[], "closure_state.known"), "1 2 1\n11\n0 2\n1 10\n120\n");
+}
+
+#[test]
+fn arguments_must_have_the_parameter_type() {
+    let run = run(&[], "closure_parameter.known");
+    assert_eq!(run.stdout, "15\n");
+    assert!(run.stderr.contains("4:10: error: Parameter 'n' expects integer, got string"), "{}", run.stderr);
+}
+
+#[test]
+fn calling_an_undefined_function_fails() {
+    let run = run(&[], "undefined_function.known");
+    assert_eq!(run.stdout, "1\n");
+    assert!(run.stderr.contains("2:14: error: Function 'missing' not defined"), "{}", run.stderr);
+}
+
+#[test]
+fn returns_are_checked_against_the_declared_type() {
+    let stderr = run_err(&[], "missing_return.known");
+    let file = script("missing_return.known");
+    for expected in [
+        "1:1: error: Function 'sign' must return integer, but can reach the end of its body without returning",
+        "8:5: error: Function 'log' is declared void and cannot return a value",
+        "11:5: error: Function 'name' is declared to return string, but returns integer",
+    ] {
+        assert!(stderr.contains(&format!("{}:{}", file, expected)), "{}", stderr);
+    }
+}
+
+#[test]
+fn runtime_errors_show_the_calls_they_happened_in() {
+    let run = run(&[], "stack_trace.known");
+    assert_eq!(run.stdout, "3\n");
+    let file = script("stack_trace.known");
+    let expected = [
+        format!("{}:2:15: error: DivisionByZero: Division by zero", file),
+        format!("{}:5:17: note: in 'inner', called here", file),
+        format!("{}:8:12: note: in 'outer', called here", file),
+    ];
+    assert!(run.stderr.contains(&expected.join("\n")), "{}", run.stderr);
+}
+
+#[test]
+fn recursion_deeper_than_the_limit_overflows() {
+    let run = run(&["--max-depth=100"], "deep_recursion.known");
+    assert_eq!(run.stdout, "40\n");
+    assert!(run.stderr.contains("error: StackOverflow: Maximum call depth of 100 exceeded calling 'depth'"), "{}", run.stderr);
+    assert!(run.stderr.contains("note: in 'depth', called here (99 times)"), "{}", run.stderr);
+}
+
+#[test]
+fn recursion_nested_deep_in_blocks_overflows_before_the_stack_does() {
+    let run = run(&[], "nested_recursion.known");
+    assert!(run.stderr.contains("error: StackOverflow:"), "{}", run.stderr);
+    assert!(!run.stderr.contains("overflowed its stack"), "{}", run.stderr);
+}
+
+#[test]
+fn call_depths_the_stack_cannot_hold_are_rejected() {
+    let run = run(&["--max-depth=1000000"], "deep_recursion.known");
+    assert_eq!(run.stdout, "");
+    assert!(run.stderr.contains("Maximum call depth too large: 1000000"), "{}", run.stderr);
+    assert_eq!(run.status, Some(2));
+}
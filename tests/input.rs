@@ -0,0 +1,23 @@
+mod common;
+
+use common::{example, run_path, run_with_input};
+
+#[test]
+fn scripts_read_their_arguments_and_input() {
+    let run = run_path(&[], &example("input.known"), &["first", "second"], "Ada\n2\n  rest of\ninput  \n");
+    assert_eq!(run.stdout, "[\"first\", \"second\"]\nADA\nADA\n13\n", "{}", run.stderr);
+    assert!(run.stderr.is_empty(), "{}", run.stderr);
+}
+
+#[test]
+fn input_that_is_not_an_integer_fails() {
+    let run = run_with_input(&[], "read_int.known", "41\nabc\n");
+    assert_eq!(run.stdout, "42\n");
+    assert!(run.stderr.contains("2:15: error: read_int() cannot read \"abc\" as an integer"), "{}", run.stderr);
+}
+
+#[test]
+fn reading_past_the_end_of_input_fails() {
+    let run = run_with_input(&[], "read_int.known", "41\n");
+    assert!(run.stderr.contains("2:15: error: IoError: read_int() reached the end of input"), "{}", run.stderr);
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use common::{run, run_ok, script};
+
+#[test]
+fn statements_after_a_try_or_endless_loop_that_never_completes_are_unreachable() {
+    let run = run(&[], "unreachable.known");
+    assert_eq!(run.stdout, "picked\n1 0\n");
+    let file = script("unreachable.known");
+    for expected in [
+        "7:5: warning[unreachable_code]: Unreachable statement",
+        "3:9: note: this 'try' makes the rest of the block unreachable",
+        "13:5: warning[unreachable_code]: Unreachable statement",
+        "10:5: note: this endless loop makes the rest of the block unreachable",
+    ] {
+        assert!(run.stderr.contains(&format!("{}:{}", file, expected)), "{}", run.stderr);
+    }
+}
+
+#[test]
+fn lints_warn_by_default_and_the_program_still_runs() {
+    let run = run(&[], "unused.known");
+    assert_eq!(run.stdout, "done\n");
+    assert!(run.stderr.contains("2:1: warning[unused_function]: Function 'helper' is never called"), "{}", run.stderr);
+    assert!(run.stderr.contains("1:1: warning[unused_variable]: Variable 'unused' is assigned but never read"), "{}", run.stderr);
+}
+
+#[test]
+fn a_denied_lint_stops_the_program() {
+    let run = run(&["--deny=unused_variable"], "unused.known");
+    assert_eq!(run.stdout, "");
+    assert!(run.stderr.contains("1:1: error[unused_variable]: Variable 'unused' is assigned but never read"), "{}", run.stderr);
+    assert!(run.stderr.contains("2:1: warning[unused_function]"), "{}", run.stderr);
+}
+
+#[test]
+fn allowed_lints_are_silent_and_later_levels_win() {
+    assert_eq!(run_ok(&["--allow=all"], "unused.known"), "done\n");
+    let run = run(&["--deny=all", "--allow=unused_variable"], "unused.known");
+    assert!(run.stderr.contains("error[unused_function]"), "{}", run.stderr);
+    assert!(!run.stderr.contains("unused_variable"), "{}", run.stderr);
+}
@@ -0,0 +1,16 @@
+mod common;
+
+use common::{run_err, run_example_ok, script};
+
+#[test]
+fn loops_break_and_continue() {
+    assert_eq!(run_example_ok(&[], "loops.known"), "18\n0\n9\napples\npears\n");
+}
+
+#[test]
+fn break_and_continue_outside_a_loop_are_reported_at_their_line() {
+    let stderr = run_err(&[], "break_outside_loop.known");
+    let file = script("break_outside_loop.known");
+    assert!(stderr.contains(&format!("{}:4:5: error: 'break' used outside of a loop", file)), "{}", stderr);
+    assert!(stderr.contains(&format!("{}:9:1: error: 'continue' used outside of a loop", file)), "{}", stderr);
+}
@@ -0,0 +1,29 @@
+mod common;
+
+use common::{run, run_err, run_example_ok, script};
+
+#[test]
+fn imported_modules_are_reached_through_their_namespace() {
+    assert_eq!(run_example_ok(&[], "modules/main.known"), "16\n12\n10\n19\n3\nhi\n25\n");
+}
+
+#[test]
+fn private_members_are_not_exported() {
+    let stderr = run_err(&[], "private_import.known");
+    assert!(stderr.contains("error: 'SECRET' is not public in module 'math'"), "{}", stderr);
+}
+
+#[test]
+fn importing_a_missing_module_fails() {
+    let stderr = run_err(&[], "missing_module.known");
+    assert!(stderr.contains("1:1: error: Cannot find module 'nowhere.known'"), "{}", stderr);
+}
+
+#[test]
+fn a_syntax_error_in_a_module_names_the_module() {
+    let run = run(&[], "import_syntax_error.known");
+    let expected = format!("{}:2:16: error: Expected an expression", script("syntax_error.known"));
+    assert!(run.stderr.contains(&expected), "{}", run.stderr);
+    assert!(!run.stderr.contains("panicked"), "{}", run.stderr);
+    assert_eq!(run.status, Some(3));
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use common::{run, run_err, run_example_ok, run_ok};
+
+#[test]
+fn operators_follow_precedence_and_short_circuit() {
+    let expected = "false\ntrue\nfalse\nevaluated\ntrue\n7\n9\n3\n3\nfalse\n-6\n";
+    assert_eq!(run_example_ok(&[], "logic.known"), expected);
+}
+
+#[test]
+fn compound_assignments_update_variables_and_elements() {
+    assert_eq!(run_ok(&[], "compound_assignment.known"), "3 [11, 1]\n");
+}
+
+#[test]
+fn constants_are_folded_and_may_be_shadowed() {
+    assert_eq!(run_example_ok(&[], "constants.known"), "100\n50\ntrue\nlimit\n4\n0\n1\n");
+}
+
+#[test]
+fn constants_cannot_be_assigned() {
+    let stderr = run_err(&[], "assign_constant.known");
+    assert!(stderr.contains("2:1: error: Cannot assign to constant 'LIMIT'"), "{}", stderr);
+    assert!(stderr.contains("1:1: note: 'LIMIT' is declared as a constant here"), "{}", stderr);
+}
+
+#[test]
+fn math_functions_work_on_integers_and_floats() {
+    let expected = [
+        "5", "2.5", "3", "7.5", "1024", "1.4142135623730951", "4", "46340", "1.5", "6", "10", "1.0", "2", "3",
+        "-3", "0.0", "1.0", "0.0", "3.0", "3.5", "true", "0.5", "0", "1",
+    ];
+    assert_eq!(run_example_ok(&[], "math.known"), expected.join("\n") + "\n");
+}
+
+#[test]
+fn integer_overflow_fails() {
+    let run = run(&[], "overflow.known");
+    assert_eq!(run.stdout, "3 7\n");
+    assert!(run.stderr.contains("2:18: error: Overflow: Integer overflow in +"), "{}", run.stderr);
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use common::run_ok;
+
+/// Returns how many binary operators the program has after optimizing at `level`.
+fn binary_ops(level: &str, name: &str) -> usize {
+    run_ok(&[level, "--dump-ast"], name).matches("BinaryOp").count()
+}
+
+#[test]
+fn identities_on_typed_variables_and_parameters_are_removed() {
+    assert_eq!(binary_ops("-O1", "identities.known"), 0);
+    assert_eq!(run_ok(&["-O1"], "identities.known"), "4 2.5 true\n");
+}
+
+#[test]
+fn level_zero_leaves_the_program_alone() {
+    assert_eq!(binary_ops("-O0", "identities.known"), 3);
+    assert_eq!(run_ok(&["-O0"], "identities.known"), "4 2.5 true\n");
+}
+
+#[test]
+fn operations_that_fail_or_have_side_effects_are_kept() {
+    // `loose` is declared integer but assigned a boolean, so `loose * 1` must
+    // still fail, and `shared` is given a string by the closure that captured it
+    assert_eq!(binary_ops("-O1", "kept_operations.known"), 4);
+    let expected = "shouting\ntrue\nRuntimeError\nDivisionByZero\nRuntimeError\n";
+    assert_eq!(run_ok(&["-O1"], "kept_operations.known"), expected);
+    assert_eq!(run_ok(&["-O0"], "kept_operations.known"), expected);
+}
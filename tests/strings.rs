@@ -0,0 +1,32 @@
+mod common;
+
+use common::{run, run_example_ok, run_ok};
+
+#[test]
+fn strings_are_handled_by_character() {
+    let expected = "ALICE & BOB & CAROL\n5\néll\n2\na+b+c\ntrue\n42\n[1, 2, 3]\n";
+    assert_eq!(run_example_ok(&[], "strings.known"), expected);
+}
+
+#[test]
+fn substrings_out_of_range_fail() {
+    let run = run(&[], "substring_range.known");
+    assert_eq!(run.stdout, "el\n");
+    assert!(run.stderr.contains("2:16: error: substring() range 2..10 is out of bounds for a string of length 5"), "{}", run.stderr);
+}
+
+#[test]
+fn values_are_formatted_to_a_template() {
+    let expected = [
+        "apple   |   120|000000",
+        "kiwi    |    85|000001",
+        "banana  |   240|000010",
+        "total: 445 average: 148.0",
+    ];
+    assert_eq!(run_example_ok(&[], "format.known"), expected.join("\n") + "\n");
+}
+
+#[test]
+fn an_escaped_dollar_is_not_interpolated() {
+    assert_eq!(run_ok(&[], "dollar_escape.known"), "cost: ${price} is 5\n$5 and a \\ stays\n");
+}
@@ -0,0 +1,16 @@
+mod common;
+
+use common::{run_example_ok, run_ok};
+
+#[test]
+fn tail_calls_run_in_constant_stack() {
+    // A million self-recursive and a hundred thousand mutually recursive tail
+    // calls, with only a hundred nested calls allowed
+    assert_eq!(run_example_ok(&["--max-depth=100"], "countdown.known"), "0\nfalse\n");
+}
+
+#[test]
+fn calls_ending_a_returned_if_or_match_are_tail_calls() {
+    let stdout = run_ok(&["--max-depth=100"], "tail_branches.known");
+    assert_eq!(stdout, "1250025000\n111\n0\n");
+}
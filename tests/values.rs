@@ -0,0 +1,71 @@
+mod common;
+
+use common::{run, run_example_ok, run_ok};
+
+#[test]
+fn lists_are_indexed_and_grown() {
+    assert_eq!(run_example_ok(&[], "lists.known"), "[10, 2, 3]\n4\n4\n2\n");
+}
+
+#[test]
+fn indexing_past_the_end_of_a_list_fails() {
+    let run = run(&[], "list_index.known");
+    assert_eq!(run.stdout, "[1, 2, 3]\n");
+    assert!(run.stderr.contains("4:9: error: IndexOutOfBounds: Index 5 out of bounds for list of length 3"), "{}", run.stderr);
+}
+
+#[test]
+fn maps_keep_their_insertion_order() {
+    let expected = "{\"alice\": 31, \"carol\": 45, \"dave\": 19}\n31\n27\nfalse\n[\"alice\", \"carol\", \"dave\"]\n";
+    assert_eq!(run_example_ok(&[], "maps.known"), expected);
+}
+
+#[test]
+fn reading_a_missing_key_fails() {
+    let run = run(&[], "map_missing_key.known");
+    assert_eq!(run.stdout, "41\n");
+    assert!(run.stderr.contains("4:11: error: KeyNotFound: Key \"grace\" not found in map"), "{}", run.stderr);
+}
+
+#[test]
+fn structs_are_built_and_their_fields_read() {
+    let expected = "Point { x: 3, y: 2 }\n6\nSegment { start: Point { x: 3, y: 2 }, end: Point { x: 4, y: 6 } }\n";
+    assert_eq!(run_example_ok(&[], "structs.known"), expected);
+}
+
+#[test]
+fn reading_a_missing_field_fails() {
+    let run = run(&[], "struct_field.known");
+    assert_eq!(run.stdout, "Point { x: 5, y: 2 }\n");
+    assert!(run.stderr.contains("8:8: error: Struct 'Point' has no field 'z'"), "{}", run.stderr);
+}
+
+#[test]
+fn assigning_a_field_the_wrong_type_fails_at_the_field() {
+    let run = run(&[], "field_type.known");
+    assert_eq!(run.stdout, "5\n");
+    assert!(run.stderr.contains("8:2: error: Field 'y' of struct 'Point' expects integer, got string"), "{}", run.stderr);
+}
+
+#[test]
+fn cyclic_collections_are_printed_and_compared() {
+    let expected = [
+        "[1, [...]]",
+        "true",
+        "true",
+        "false",
+        "{\"a\": 1, \"self\": {...}}",
+        "[[1, [...]], [1, [...]]]",
+        "Node { value: 1, next: [Node {...}] }",
+        "true",
+    ];
+    assert_eq!(run_ok(&[], "cyclic_values.known"), expected.join("\n") + "\n");
+}
+
+#[test]
+fn an_integer_literal_out_of_range_is_a_compile_error() {
+    let run = run(&[], "integer_literal.known");
+    assert_eq!(run.stdout, "");
+    assert!(run.stderr.contains("2:28: error: Integer literal 99999999999 is too large"), "{}", run.stderr);
+    assert!(!run.stderr.contains("panicked"), "{}", run.stderr);
+}